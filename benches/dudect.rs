@@ -0,0 +1,123 @@
+//! dudect-style timing leakage test for signing
+//!
+//! Signs inputs from two classes, a fixed input and random inputs, in random
+//! order and compares the two timing distributions with Welch's t-test, as in
+//! "Dude, is my code constant time?" (Reparaz, Balasch and Verbauwhede). A |t|
+//! above 4.5 means the classes are distinguishable and signing time depends on
+//! the input. Measurements above several percentiles are also tested on their
+//! own, since leaks often show up only once noisy outliers are cut.
+//!
+//! Run with `cargo bench --bench dudect`. The number of measurements per
+//! configuration defaults to 20000 and can be set with `DUDECT_SAMPLES`.
+
+use num_bigint::{BigUint, RandBigInt};
+use rabin_williams::{KeyPair, PrivateKey};
+use rand::Rng;
+use std::hint::black_box;
+use std::time::Instant;
+
+/// |t| above which the two classes are considered distinguishable
+const THRESHOLD: f64 = 4.5;
+
+/// Percentiles at which measurements are cropped, in addition to no cropping
+const CROP_PERCENTILES: [f64; 3] = [0.5, 0.75, 0.9];
+
+/// Welch's t-test accumulator using Welford's online mean and variance
+#[derive(Default)]
+struct TTest {
+    count: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl TTest {
+    fn push(&mut self, class: usize, value: f64) {
+        self.count[class] += 1.0;
+        let delta = value - self.mean[class];
+        self.mean[class] += delta / self.count[class];
+        self.m2[class] += delta * (value - self.mean[class]);
+    }
+
+    fn t(&self) -> f64 {
+        let var = |class: usize| self.m2[class] / (self.count[class] - 1.0);
+        let denominator = (var(0) / self.count[0] + var(1) / self.count[1]).sqrt();
+        (self.mean[0] - self.mean[1]) / denominator
+    }
+}
+
+/// Times `raw_sign` on fixed and random inputs and returns (class, nanoseconds) pairs
+fn measure(key: &PrivateKey, samples: usize) -> Vec<(usize, f64)> {
+    let n = key.n();
+    let mut rng = rand::thread_rng();
+
+    // A tiny fixed input is the extreme case for value-dependent big integer
+    // arithmetic. Both classes are squares so that they take the same tweak
+    // and differ only in value.
+    let fixed = BigUint::from(4u32).to_bytes_be();
+    let inputs: Vec<(usize, Vec<u8>)> = (0..samples)
+        .map(|_| {
+            let class = rng.gen_range(0..2);
+            let input = match class {
+                0 => fixed.clone(),
+                _ => {
+                    let r = rng.gen_biguint_below(&n);
+                    (&r * &r % &n).to_bytes_be()
+                }
+            };
+            (class, input)
+        })
+        .collect();
+
+    inputs
+        .into_iter()
+        .map(|(class, input)| {
+            let start = Instant::now();
+            black_box(key.raw_sign(black_box(&input)).expect("signing failed"));
+            (class, start.elapsed().as_nanos() as f64)
+        })
+        .collect()
+}
+
+/// Returns the largest |t| over the uncropped and cropped measurements
+fn max_t(measurements: &[(usize, f64)]) -> f64 {
+    let mut sorted: Vec<f64> = measurements.iter().map(|&(_, time)| time).collect();
+    sorted.sort_by(f64::total_cmp);
+    let thresholds = CROP_PERCENTILES
+        .iter()
+        .map(|percentile| sorted[((sorted.len() - 1) as f64 * percentile) as usize])
+        .chain([f64::INFINITY]);
+
+    thresholds
+        .map(|threshold| {
+            let mut test = TTest::default();
+            for &(class, time) in measurements.iter().filter(|&&(_, time)| time <= threshold) {
+                test.push(class, time);
+            }
+            test.t().abs()
+        })
+        .fold(0.0, f64::max)
+}
+
+fn main() {
+    let samples = std::env::var("DUDECT_SAMPLES")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(20_000);
+    let key = KeyPair::generate(1024).expect("key generation failed").private;
+
+    for blinding in [false, true] {
+        let key = key.clone().with_side_channel_blinding(blinding);
+        // Warm up caches and the allocator before measuring
+        measure(&key, samples / 10);
+
+        let t = max_t(&measure(&key, samples));
+        let verdict = if t > THRESHOLD { "leakage detected" } else { "no leakage detected" };
+        println!(
+            "side-channel blinding {:<3}  max |t| = {:>8.2}  ({} measurements): {}",
+            if blinding { "on" } else { "off" },
+            t,
+            samples,
+            verdict
+        );
+    }
+}
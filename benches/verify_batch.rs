@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rabin_williams::KeyPair;
+
+fn bench_verify_batch(c: &mut Criterion) {
+    let key_pair = KeyPair::generate(2048).expect("key generation failed");
+    let mut group = c.benchmark_group("verify");
+
+    for size in [16usize, 256, 4096] {
+        let batch: Vec<(Vec<u8>, Vec<u8>)> = (0..size)
+            .map(|i| {
+                let message = format!("message {}", i).into_bytes();
+                let signature = key_pair.private.sign(&message).expect("signing failed");
+                (message, signature)
+            })
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("loop", size), &batch, |b, batch| {
+            b.iter(|| {
+                batch
+                    .iter()
+                    .all(|(message, signature)| key_pair.public.verify(message, signature).unwrap())
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", size), &batch, |b, batch| {
+            b.iter(|| key_pair.public.verify_batch(batch).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_verify_batch);
+criterion_main!(benches);
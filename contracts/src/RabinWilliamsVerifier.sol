@@ -5,11 +5,14 @@ pragma solidity ^0.8.13;
  * @title RabinWilliamsVerifier
  * @notice Verifies Rabin-Williams digital signatures
  * @dev This contract verifies signatures using the Rabin-Williams scheme
- *      with SHA-256 as the hash function. The signature format is:
+ *      with SHA-256 or Keccak-256 as the hash function. The signature format is:
  *      - First byte: flags (bit 0 = e, bit 1 = f)
  *      - Remaining bytes: signature value x (big-endian)
  */
 contract RabinWilliamsVerifier {
+    /// @notice Context string that `sign_merkle_batch` signs Merkle roots under
+    string public constant MERKLE_ROOT_CONTEXT = "rabin-williams merkle root";
+
     /**
      * @notice Verifies a Rabin-Williams signature
      * @param n The public key modulus (1024-bit, hex-encoded)
@@ -23,8 +26,122 @@ contract RabinWilliamsVerifier {
         bytes memory signature
     ) public view returns (bool isValid) {
         // Hash the message using SHA-256
-        bytes32 messageHash = sha256(message);
-        
+        return verifyDigest(n, sha256(message), signature);
+    }
+
+    /**
+     * @notice Verifies a Rabin-Williams signature made with the Keccak-256 hash mode
+     * @param n The public key modulus
+     * @param message The message that was signed
+     * @param signature The signature to verify
+     * @return isValid True if the signature is valid, false otherwise
+     */
+    function verifyKeccak256(
+        bytes memory n,
+        bytes memory message,
+        bytes memory signature
+    ) public view returns (bool isValid) {
+        return verifyDigest(n, keccak256(message), signature);
+    }
+
+    /**
+     * @notice Verifies a signature over an EIP-191 personal message
+     * @dev Matches signing `eip191_personal_message(message)` with the Keccak-256 hash mode
+     * @param n The public key modulus
+     * @param message The message before EIP-191 encoding
+     * @param signature The signature to verify
+     * @return isValid True if the signature is valid, false otherwise
+     */
+    function verifyEthSignedMessage(
+        bytes memory n,
+        bytes memory message,
+        bytes memory signature
+    ) public view returns (bool isValid) {
+        return verifyDigest(n, toEthSignedMessageHash(message), signature);
+    }
+
+    /**
+     * @notice Verifies a signature over EIP-712 typed data
+     * @dev Matches signing `eip712_typed_data(domainSeparator, structHash)` with the Keccak-256 hash mode
+     * @param n The public key modulus
+     * @param domainSeparator The EIP-712 domain separator
+     * @param structHash The EIP-712 hashStruct of the message
+     * @param signature The signature to verify
+     * @return isValid True if the signature is valid, false otherwise
+     */
+    function verifyTypedData(
+        bytes memory n,
+        bytes32 domainSeparator,
+        bytes32 structHash,
+        bytes memory signature
+    ) public view returns (bool isValid) {
+        bytes32 digest = keccak256(abi.encodePacked("\x19\x01", domainSeparator, structHash));
+        return verifyDigest(n, digest, signature);
+    }
+
+    /**
+     * @notice Verifies that a message belongs to a batch signed with `sign_merkle_batch`
+     * @dev Rebuilds the SHA-256 Merkle root from the message and its inclusion proof,
+     *      then verifies the root signature under `MERKLE_ROOT_CONTEXT`, which the
+     *      library hashes as sha256(u64(len(context)) || context || u64(32) || root)
+     * @param n The public key modulus
+     * @param message The message to check
+     * @param proof The sibling hashes from the leaf up to the root
+     * @param path Bit i is set when proof[i] is the left child
+     * @param rootSignature The signature over the Merkle root
+     * @return isValid True if the message is in the signed batch, false otherwise
+     */
+    function verifyWithMerkleProof(
+        bytes memory n,
+        bytes memory message,
+        bytes32[] memory proof,
+        uint256 path,
+        bytes memory rootSignature
+    ) public view returns (bool isValid) {
+        // Reject path bits that no sibling accounts for
+        if (proof.length < 256 && (path >> proof.length) != 0) {
+            return false;
+        }
+
+        bytes32 root = computeMerkleRoot(message, proof, path);
+        bytes32 digest = sha256(
+            abi.encodePacked(uint64(bytes(MERKLE_ROOT_CONTEXT).length), MERKLE_ROOT_CONTEXT, uint64(32), root)
+        );
+        return verifyDigest(n, digest, rootSignature);
+    }
+
+    /**
+     * @notice Computes the Merkle root implied by a message and its inclusion proof
+     * @dev Leaves are sha256(0x00 || message), nodes are sha256(0x01 || left || right)
+     */
+    function computeMerkleRoot(
+        bytes memory message,
+        bytes32[] memory proof,
+        uint256 path
+    ) internal pure returns (bytes32 node) {
+        node = sha256(abi.encodePacked(bytes1(0x00), message));
+        for (uint256 i = 0; i < proof.length; i++) {
+            if ((path >> i) & 1 == 1) {
+                node = sha256(abi.encodePacked(bytes1(0x01), proof[i], node));
+            } else {
+                node = sha256(abi.encodePacked(bytes1(0x01), node, proof[i]));
+            }
+        }
+    }
+
+    /**
+     * @notice Verifies a Rabin-Williams signature over a precomputed 32-byte digest
+     * @dev Use this with digests a contract already computes, e.g. `_hashTypedDataV4`
+     * @param n The public key modulus
+     * @param messageHash The digest that was signed
+     * @param signature The signature to verify
+     * @return isValid True if the signature is valid, false otherwise
+     */
+    function verifyDigest(
+        bytes memory n,
+        bytes32 messageHash,
+        bytes memory signature
+    ) public view returns (bool isValid) {
         // Extract signature components
         (int256 e, uint256 f, bytes memory x) = extractSignature(signature);
         
@@ -81,6 +198,39 @@ contract RabinWilliamsVerifier {
         return bytesEqual(cleanResult, hashBytes);
     }
     
+    /**
+     * @notice Computes the EIP-191 personal message hash of a message
+     * @dev keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)
+     */
+    function toEthSignedMessageHash(bytes memory message) internal pure returns (bytes32) {
+        return keccak256(
+            abi.encodePacked("\x19Ethereum Signed Message:\n", uintToString(message.length), message)
+        );
+    }
+
+    /**
+     * @notice Formats an unsigned integer as a decimal string
+     */
+    function uintToString(uint256 value) internal pure returns (bytes memory) {
+        if (value == 0) {
+            return "0";
+        }
+
+        uint256 digits = 0;
+        for (uint256 temp = value; temp != 0; temp /= 10) {
+            digits++;
+        }
+
+        bytes memory result = new bytes(digits);
+        while (value != 0) {
+            digits--;
+            result[digits] = bytes1(uint8(48 + (value % 10)));
+            value /= 10;
+        }
+
+        return result;
+    }
+    
     /**
      * @notice Extracts e, f, and x from a signature
      * @param signature The signature bytes
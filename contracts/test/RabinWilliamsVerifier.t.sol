@@ -11,6 +11,7 @@ contract RabinWilliamsVerifierTest is Test {
     bytes public n; // public key modulus
     bytes public message;
     bytes public signature;
+    bytes public eip191Signature; // Keccak-256 hash mode over the EIP-191 encoded message
 
     function setUp() public {
         verifier = new RabinWilliamsVerifier();
@@ -20,11 +21,13 @@ contract RabinWilliamsVerifierTest is Test {
         string memory publicKeyHex = vm.readFile("contracts/test/fixtures/public_key.hex");
         string memory messageText = vm.readFile("contracts/test/fixtures/message.txt");
         string memory signatureHex = vm.readFile("contracts/test/fixtures/signature.hex");
+        string memory eip191SignatureHex = vm.readFile("contracts/test/fixtures/signature_eip191.hex");
         
         // Convert hex strings to bytes
         n = hexStringToBytes(publicKeyHex);
         message = bytes(messageText);
         signature = hexStringToBytes(signatureHex);
+        eip191Signature = hexStringToBytes(eip191SignatureHex);
     }
 
     function test_VerifySignature() public {
@@ -79,6 +82,31 @@ contract RabinWilliamsVerifierTest is Test {
         assertFalse(isValid, "Signature should be invalid for wrong message");
     }
 
+    function test_VerifyEthSignedMessage() public view {
+        bool isValid = verifier.verifyEthSignedMessage(n, message, eip191Signature);
+        assertTrue(isValid, "EIP-191 signature should be valid");
+    }
+
+    function test_VerifyDigestMatchesEthSignedMessage() public view {
+        bytes32 digest = keccak256(
+            abi.encodePacked("\x19Ethereum Signed Message:\n", vm.toString(message.length), message)
+        );
+        assertTrue(verifier.verifyDigest(n, digest, eip191Signature), "Digest verification should match");
+        assertTrue(verifier.verifyDigest(n, sha256(message), signature), "SHA-256 digest should verify");
+    }
+
+    function test_VerifyEthSignedMessageRejectsOtherModes() public view {
+        assertFalse(verifier.verify(n, message, eip191Signature), "SHA-256 mode should reject");
+        assertFalse(verifier.verifyKeccak256(n, message, eip191Signature), "Unencoded Keccak-256 should reject");
+        assertFalse(verifier.verifyEthSignedMessage(n, message, signature), "SHA-256 signature should reject");
+    }
+
+    function test_VerifyEthSignedMessageWrongMessage() public view {
+        bytes memory wrongMessage = "Wrong message";
+        bool isValid = verifier.verifyEthSignedMessage(n, wrongMessage, eip191Signature);
+        assertFalse(isValid, "EIP-191 signature should be invalid for wrong message");
+    }
+
     /**
      * @notice Converts a hex string to bytes
      * @dev Removes whitespace and converts hex string to bytes
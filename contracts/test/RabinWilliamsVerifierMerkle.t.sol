@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import {Test} from "forge-std/Test.sol";
+import {RabinWilliamsVerifier} from "../src/RabinWilliamsVerifier.sol";
+
+contract RabinWilliamsVerifierMerkleTest is Test {
+    RabinWilliamsVerifier public verifier;
+
+    // Fixture data generated by `rabin-williams sign-merkle`
+    bytes public n;
+    bytes public rootSignature;
+    bytes[] public messages;
+    bytes[] public proofs;
+
+    string constant MESSAGES_PATH = "contracts/test/fixtures/merkle_messages.txt";
+    string constant PROOFS_PATH = "contracts/test/fixtures/merkle_proofs.txt";
+
+    function setUp() public {
+        verifier = new RabinWilliamsVerifier();
+
+        n = hexToBytes(vm.readFile("contracts/test/fixtures/public_key.hex"));
+        rootSignature = hexToBytes(vm.readFile("contracts/test/fixtures/merkle_signature.hex"));
+
+        // One message and one proof per line
+        for (string memory line = vm.readLine(MESSAGES_PATH); bytes(line).length > 0; line = vm.readLine(MESSAGES_PATH)) {
+            messages.push(bytes(line));
+        }
+        for (string memory line = vm.readLine(PROOFS_PATH); bytes(line).length > 0; line = vm.readLine(PROOFS_PATH)) {
+            proofs.push(hexToBytes(line));
+        }
+        require(messages.length == proofs.length, "Fixture length mismatch");
+    }
+
+    // ============ Helper Functions ============
+
+    /**
+     * @notice Splits a serialized proof into its path and sibling hashes
+     * @dev The encoding is an 8-byte big-endian path followed by 32-byte siblings
+     */
+    function decodeProof(bytes memory encoded) internal pure returns (bytes32[] memory siblings, uint256 path) {
+        require(encoded.length >= 8 && (encoded.length - 8) % 32 == 0, "Invalid proof length");
+
+        for (uint256 i = 0; i < 8; i++) {
+            path = (path << 8) | uint8(encoded[i]);
+        }
+
+        siblings = new bytes32[]((encoded.length - 8) / 32);
+        for (uint256 i = 0; i < siblings.length; i++) {
+            bytes32 sibling;
+            uint256 offset = 8 + i * 32;
+            assembly {
+                sibling := mload(add(add(encoded, 32), offset))
+            }
+            siblings[i] = sibling;
+        }
+    }
+
+    function hexToBytes(string memory hexString) internal pure returns (bytes memory) {
+        bytes memory hexBytes = bytes(hexString);
+        require(hexBytes.length % 2 == 0, "Invalid hex string length");
+
+        bytes memory result = new bytes(hexBytes.length / 2);
+        for (uint256 i = 0; i < result.length; i++) {
+            uint8 high = charToHex(hexBytes[i * 2]);
+            uint8 low = charToHex(hexBytes[i * 2 + 1]);
+            result[i] = bytes1((high << 4) | low);
+        }
+        return result;
+    }
+
+    function charToHex(bytes1 char) internal pure returns (uint8) {
+        if (char >= 0x30 && char <= 0x39) {
+            return uint8(char) - 0x30;
+        } else if (char >= 0x41 && char <= 0x46) {
+            return uint8(char) - 0x37;
+        } else if (char >= 0x61 && char <= 0x66) {
+            return uint8(char) - 0x57;
+        }
+        revert("Invalid hex character");
+    }
+
+    // ============ Tests ============
+
+    function test_VerifyEveryMessageInBatch() public view {
+        for (uint256 i = 0; i < messages.length; i++) {
+            (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[i]);
+            bool isValid = verifier.verifyWithMerkleProof(n, messages[i], siblings, path, rootSignature);
+            assertTrue(isValid, "Message in batch should verify");
+        }
+    }
+
+    function test_RejectMessageNotInBatch() public view {
+        (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[0]);
+        bool isValid = verifier.verifyWithMerkleProof(n, "transfer 1000 to mallory", siblings, path, rootSignature);
+        assertFalse(isValid, "Forged message should be invalid");
+    }
+
+    function test_RejectProofForOtherMessage() public view {
+        (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[1]);
+        bool isValid = verifier.verifyWithMerkleProof(n, messages[0], siblings, path, rootSignature);
+        assertFalse(isValid, "Proof for another position should be invalid");
+    }
+
+    function test_RejectFlippedPath() public view {
+        (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[0]);
+        bool isValid = verifier.verifyWithMerkleProof(n, messages[0], siblings, path ^ 1, rootSignature);
+        assertFalse(isValid, "Flipped path should be invalid");
+    }
+
+    function test_RejectExcessPathBits() public view {
+        (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[0]);
+        uint256 excess = path | (uint256(1) << siblings.length);
+        bool isValid = verifier.verifyWithMerkleProof(n, messages[0], siblings, excess, rootSignature);
+        assertFalse(isValid, "Path bits beyond the proof should be invalid");
+    }
+
+    function test_RootSignatureIsNotAPlainSignature() public view {
+        // Rebuild the root of the fixture batch and check it as an ordinary message
+        (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[0]);
+        bytes32 node = sha256(abi.encodePacked(bytes1(0x00), messages[0]));
+        for (uint256 i = 0; i < siblings.length; i++) {
+            node = (path >> i) & 1 == 1
+                ? sha256(abi.encodePacked(bytes1(0x01), siblings[i], node))
+                : sha256(abi.encodePacked(bytes1(0x01), node, siblings[i]));
+        }
+        bool isValid = verifier.verify(n, abi.encodePacked(node), rootSignature);
+        assertFalse(isValid, "Root signature should only verify under the Merkle context");
+    }
+
+    function test_RejectTamperedSibling() public view {
+        (bytes32[] memory siblings, uint256 path) = decodeProof(proofs[2]);
+        siblings[0] = bytes32(uint256(siblings[0]) ^ 1);
+        bool isValid = verifier.verifyWithMerkleProof(n, messages[2], siblings, path, rootSignature);
+        assertFalse(isValid, "Tampered sibling should be invalid");
+    }
+}
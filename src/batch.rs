@@ -0,0 +1,221 @@
+//! Batch signing and verification of many messages under one key
+//!
+//! `PrivateKey::sign_batch` computes the CRT parameters once and signs every
+//! message with them, spreading the work over all cores when the `parallel`
+//! feature is enabled.
+//!
+//! A Rabin-Williams signature `(e, f, x)` on `m` is valid when `ex² ≡ fm (mod n)`.
+//! Rather than reducing every `ex² - fm` modulo `n`, the batch check draws small
+//! random coefficients `cᵢ` and tests the single congruence
+//! `Σ cᵢ(eᵢxᵢ² - fᵢmᵢ) ≡ 0 (mod n)`. If any signature is invalid, the check passes
+//! with probability at most 2⁻⁶⁴.
+
+use crate::errors::Result;
+use crate::hash::HashFunction;
+use crate::keys::{PrivateKey, PublicKey};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::Zero;
+use rand::Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A signature whose message has been hashed and whose flags have been parsed
+struct BatchEntry {
+    index: usize,
+    m: BigUint,
+    e: i32,
+    f: u32,
+    x: BigUint,
+}
+
+impl<D: HashFunction + Send + Sync> PrivateKey<D> {
+    /// Signs many messages, returning one result per message in input order
+    ///
+    /// The CRT parameters are computed once and shared by every signature. With
+    /// the `parallel` feature the messages are signed concurrently on the rayon
+    /// thread pool; otherwise they are signed one after another.
+    pub fn sign_batch<M>(&self, messages: &[M]) -> Result<Vec<Result<Vec<u8>>>>
+    where
+        M: AsRef<[u8]> + Sync,
+    {
+        self.require_signing()?;
+        let params = self.crt_params()?;
+        let n = self.n();
+        let sign_one = |message: &M| {
+            let hash = self.hash_fn.hash_for_modulus(message.as_ref(), &n).to_bytes_be();
+            self.raw_sign_with_params(&hash, &params)
+        };
+
+        #[cfg(feature = "parallel")]
+        let signatures = messages.par_iter().map(sign_one).collect();
+        #[cfg(not(feature = "parallel"))]
+        let signatures = messages.iter().map(sign_one).collect();
+
+        Ok(signatures)
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Verifies many signatures made by this key at once
+    ///
+    /// Returns `true` only if every signature is valid. Malformed signatures count
+    /// as invalid rather than producing an error. Use `find_invalid_signatures` to
+    /// locate the offending entries when the batch fails.
+    pub fn verify_batch<M, S>(&self, items: &[(M, S)]) -> Result<bool>
+    where
+        M: AsRef<[u8]>,
+        S: AsRef<[u8]>,
+    {
+        self.require_signing()?;
+        let (entries, malformed) = self.prepare_batch(items);
+        Ok(malformed.is_empty() && self.batch_check(&entries))
+    }
+
+    /// Returns the indices of all invalid signatures in `items`, in ascending order
+    ///
+    /// The whole batch is checked first; failing batches are split in half and
+    /// rechecked, so a batch with few bad signatures costs little more than
+    /// `verify_batch`.
+    pub fn find_invalid_signatures<M, S>(&self, items: &[(M, S)]) -> Result<Vec<usize>>
+    where
+        M: AsRef<[u8]>,
+        S: AsRef<[u8]>,
+    {
+        self.require_signing()?;
+        let (entries, mut invalid) = self.prepare_batch(items);
+        self.bisect(&entries, &mut invalid);
+        invalid.sort_unstable();
+        Ok(invalid)
+    }
+
+    /// Hashes every message and parses every signature, setting aside malformed ones
+    fn prepare_batch<M, S>(&self, items: &[(M, S)]) -> (Vec<BatchEntry>, Vec<usize>)
+    where
+        M: AsRef<[u8]>,
+        S: AsRef<[u8]>,
+    {
+        let mut entries = Vec::with_capacity(items.len());
+        let mut malformed = Vec::new();
+        for (index, (message, signature)) in items.iter().enumerate() {
+            match self.extract_signature(signature.as_ref()) {
+                Ok((e, f, x)) => {
+                    let m = self.hash_fn.hash_for_modulus(message.as_ref(), self.n());
+                    entries.push(BatchEntry { index, m, e, f, x });
+                }
+                Err(_) => malformed.push(index),
+            }
+        }
+        (entries, malformed)
+    }
+
+    /// Checks `Σ cᵢ(eᵢxᵢ² - fᵢmᵢ) ≡ 0 (mod n)` for random 64-bit coefficients `cᵢ`
+    fn batch_check(&self, entries: &[BatchEntry]) -> bool {
+        let mut rng = rand::thread_rng();
+        let mut sum = BigInt::zero();
+        for entry in entries {
+            let c = BigInt::from(rng.gen_range(1..=u64::MAX));
+            let x_squared = BigInt::from_biguint(Sign::Plus, &entry.x * &entry.x);
+            let e_x_squared = if entry.e == -1 { -x_squared } else { x_squared };
+            let f_m = BigInt::from_biguint(Sign::Plus, &entry.m * entry.f);
+            sum += c * (e_x_squared - f_m);
+        }
+        let n = BigInt::from_biguint(Sign::Plus, self.n().clone());
+        (sum % n).is_zero()
+    }
+
+    /// Recursively halves a failing batch until each invalid signature is isolated
+    fn bisect(&self, entries: &[BatchEntry], invalid: &mut Vec<usize>) {
+        if entries.is_empty() || self.batch_check(entries) {
+            return;
+        }
+        if entries.len() == 1 {
+            invalid.push(entries[0].index);
+            return;
+        }
+        let (left, right) = entries.split_at(entries.len() / 2);
+        self.bisect(left, invalid);
+        self.bisect(right, invalid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Result;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    fn signed_batch(key_pair: &KeyPair<Sha256>, count: usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        (0..count)
+            .map(|i| {
+                let message = format!("message {}", i).into_bytes();
+                let signature = key_pair.private.sign(&message)?;
+                Ok((message, signature))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sign_batch() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let messages: Vec<Vec<u8>> = (0..32).map(|i| format!("message {}", i).into_bytes()).collect();
+
+        let signatures = key_pair.private.sign_batch(&messages)?;
+        assert_eq!(signatures.len(), messages.len());
+
+        // Results come back in input order and match one-at-a-time signing
+        for (message, signature) in messages.iter().zip(signatures) {
+            let signature = signature?;
+            assert_eq!(signature, key_pair.private.sign(message)?);
+            assert!(key_pair.public.verify(message, &signature)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_batch() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let batch = signed_batch(&key_pair, 20)?;
+
+        assert!(key_pair.public.verify_batch(&batch)?);
+        assert!(key_pair.public.find_invalid_signatures(&batch)?.is_empty());
+
+        // An empty batch is trivially valid
+        let empty: [(&[u8], &[u8]); 0] = [];
+        assert!(key_pair.public.verify_batch(&empty)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_batch_locates_invalid() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let mut batch = signed_batch(&key_pair, 20)?;
+
+        // Flip a flag, swap a message, and corrupt the encoding
+        batch[3].1[0] ^= 1;
+        batch[11].0 = b"forged".to_vec();
+        batch[17].1 = vec![0xFF, 0x01];
+
+        assert!(!key_pair.public.verify_batch(&batch)?);
+        assert_eq!(key_pair.public.find_invalid_signatures(&batch)?, vec![3, 11, 17]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_batch_rejects_cancelling_errors() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let mut batch = signed_batch(&key_pair, 2)?;
+
+        // Swapping the signatures keeps Σ(ex² - fm) at zero, which fixed coefficients would miss
+        let first = batch[0].1.clone();
+        batch[0].1 = batch[1].1.clone();
+        batch[1].1 = first;
+
+        assert!(!key_pair.public.verify_batch(&batch)?);
+        assert_eq!(key_pair.public.find_invalid_signatures(&batch)?, vec![0, 1]);
+
+        Ok(())
+    }
+}
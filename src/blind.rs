@@ -0,0 +1,323 @@
+//! Structured blind-signature protocol
+//!
+//! The protocol has one round trip:
+//!
+//! 1. The client describes what it wants signed with a [`BlindingRequest`] and
+//!    calls `PublicKey::blind`, which returns a [`BlindedMessage`] to send to the
+//!    signer and a [`BlindingState`] to keep.
+//! 2. The signer answers with `PrivateKey::blind_sign`, producing a [`BlindSignature`].
+//! 3. The client calls `BlindingState::finalize`, which unblinds the signature
+//!    and checks that it verifies before handing it out.
+//!
+//! The blinding factor never leaves `BlindingState`, so callers cannot mix up
+//! factors between sessions.
+//!
+//! # Security
+//!
+//! A Rabin-Williams blind signer is a square-root oracle, and a malicious
+//! client can use it to factor `n`. A client that sends `x²` for an `x` of its
+//! choosing gets back a root other than `±x` half of the time, and
+//! `gcd(root - x, n)` is then a factor of `n`. Related requests work too: the
+//! roots of `y` and `c²·y` differ by a factor other than `±c` whenever `c` has
+//! different quadratic characters modulo `p` and `q`, as `2` does for every RW
+//! key. No check on the blinded value can tell such requests from honest ones,
+//! so only offer blind signing to clients trusted with the private key.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::HashFunction;
+use crate::keys::{CrtParams, PrivateKey, PublicKey};
+use crate::utils::jacobi;
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+/// A message the client wants signed blindly, optionally bound to a context string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlindingRequest {
+    message: Vec<u8>,
+    context: Option<Vec<u8>>,
+}
+
+impl BlindingRequest {
+    /// Creates a request for `message`
+    pub fn new(message: &[u8]) -> Self {
+        Self {
+            message: message.to_vec(),
+            context: None,
+        }
+    }
+
+    /// Binds the request to a context string, as with `PrivateKey::sign_with_context`
+    pub fn with_context(mut self, context: &[u8]) -> Self {
+        self.context = Some(context.to_vec());
+        self
+    }
+
+    /// Returns the message to be signed
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// Returns the context string, if any
+    pub fn context(&self) -> Option<&[u8]> {
+        self.context.as_deref()
+    }
+}
+
+/// A blinded message hash, sent from the client to the signer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlindedMessage(BigUint);
+
+impl BlindedMessage {
+    /// Wraps a blinded value received from a client
+    pub fn new(value: BigUint) -> Self {
+        Self(value)
+    }
+
+    /// Returns the blinded value
+    pub fn value(&self) -> &BigUint {
+        &self.0
+    }
+
+    /// Encodes the blinded value as big-endian bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes_be()
+    }
+
+    /// Decodes a blinded value from big-endian bytes
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self(BigUint::from_bytes_be(bytes))
+    }
+}
+
+/// The signer's signature over a blinded message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlindSignature(Vec<u8>);
+
+impl BlindSignature {
+    /// Returns the signature in the same encoding as `PrivateKey::sign`
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Parses a blind signature, checking the flag byte and length
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 2 || bytes[0] & 0xFC != 0 {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+        Ok(Self(bytes.to_vec()))
+    }
+}
+
+/// Client-side state for one blind-signing session
+///
+/// Holds the blinding factor and the original request until the signer's
+/// response arrives.
+#[derive(Clone, Debug)]
+pub struct BlindingState<D: HashFunction> {
+    public_key: PublicKey<D>,
+    request: BlindingRequest,
+    r: BigUint,
+}
+
+impl<D: HashFunction> BlindingState<D> {
+    /// Returns the request this session was started for
+    pub fn request(&self) -> &BlindingRequest {
+        &self.request
+    }
+
+    /// Unblinds the signer's response and verifies it against the original message
+    ///
+    /// Returns the final signature, which verifies with `PublicKey::verify` (or
+    /// `verify_with_context` if the request had a context). Fails with
+    /// `InvalidSignature` if the signer's response does not unblind to a valid
+    /// signature.
+    pub fn finalize(self, blind_signature: &BlindSignature) -> Result<Vec<u8>> {
+        let signature = self.public_key.unblind_signature(blind_signature.as_bytes(), &self.r)?;
+        let is_valid = match self.request.context() {
+            Some(context) => self.public_key.verify_with_context(context, self.request.message(), &signature)?,
+            None => self.public_key.verify(self.request.message(), &signature)?,
+        };
+        if !is_valid {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+        Ok(signature)
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Starts a blind-signing session for `request`
+    ///
+    /// Returns the blinded message to send to the signer and the state needed
+    /// to finalize the signature.
+    pub fn blind(&self, request: BlindingRequest) -> (BlindedMessage, BlindingState<D>) {
+        let (blinded, r) = match request.context() {
+            Some(context) => self.blind_message_with_context(context, request.message()),
+            None => self.blind_unencoded(request.message()),
+        };
+        let state = BlindingState {
+            public_key: self.clone(),
+            request,
+            r,
+        };
+        (BlindedMessage(blinded), state)
+    }
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Checks that a blinded value is well formed
+    ///
+    /// The value must lie in `[1, n)` and have a Jacobi symbol of ±1 modulo `n`.
+    /// A Jacobi symbol of 0 means the value shares a factor with `n`, and the
+    /// signer's answer would reveal that factor. Passing these checks does not
+    /// make a value safe to sign (see the module documentation).
+    pub fn validate_blinded_message(&self, blinded: &BlindedMessage) -> Result<()> {
+        let n = self.n();
+        if blinded.value().is_zero() || blinded.value() >= &n || jacobi(blinded.value(), &n) == 0 {
+            return Err(RabinWilliamsError::InvalidBlindedMessage);
+        }
+        Ok(())
+    }
+
+    /// Signs an already validated blinded value, checking the result before releasing it
+    ///
+    /// A root corrupted by a fault in one half of the CRT computation would
+    /// reveal a factor of `n`, so it is never returned.
+    pub(crate) fn sign_blinded(&self, blinded: &BlindedMessage, params: &CrtParams) -> Result<BlindSignature> {
+        let signature = self.raw_sign_with_params(&blinded.to_bytes(), params)?;
+        if !PublicKey::<D>::from_n(self.n()).verify_hash(blinded.value(), &signature)? {
+            return Err(RabinWilliamsError::ComputationError);
+        }
+        Ok(BlindSignature(signature))
+    }
+
+    /// Signs a blinded message received from a client
+    ///
+    /// The blinded value is checked with `validate_blinded_message` first. This
+    /// does not stop a malicious client from factoring `n` (see the module
+    /// documentation).
+    pub fn blind_sign(&self, blinded: &BlindedMessage) -> Result<BlindSignature> {
+        self.validate_blinded_message(blinded)?;
+        self.sign_blinded(blinded, &self.crt_params()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use num_integer::Integer;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_blind_protocol() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let message = b"Hello, World!";
+
+        let (blinded, state) = key_pair.public.blind(BlindingRequest::new(message));
+        let blind_signature = key_pair.private.blind_sign(&blinded)?;
+        let signature = state.finalize(&blind_signature)?;
+
+        assert!(key_pair.public.verify(message, &signature)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blind_protocol_with_context() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let message = b"Hello, World!";
+
+        let request = BlindingRequest::new(message).with_context(b"token");
+        let (blinded, state) = key_pair.public.blind(request);
+        let signature = state.finalize(&key_pair.private.blind_sign(&blinded)?)?;
+
+        assert!(key_pair.public.verify_with_context(b"token", message, &signature)?);
+        assert!(!key_pair.public.verify(message, &signature)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_rejects_wrong_response() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        // A response to another session does not unblind to a valid signature
+        let (blinded_a, _state_a) = key_pair.public.blind(BlindingRequest::new(b"a"));
+        let (_blinded_b, state_b) = key_pair.public.blind(BlindingRequest::new(b"b"));
+        let response_a = key_pair.private.blind_sign(&blinded_a)?;
+
+        assert!(matches!(state_b.finalize(&response_a), Err(RabinWilliamsError::InvalidSignature)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blind_sign_rejects_out_of_range() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let n = key_pair.public.n().clone();
+
+        for value in [BigUint::zero(), n.clone(), &n + 1u32] {
+            let result = key_pair.private.blind_sign(&BlindedMessage::new(value));
+            assert!(matches!(result, Err(RabinWilliamsError::InvalidBlindedMessage)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blind_sign_rejects_shared_factors() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let p = key_pair.private.p.clone();
+        let q = key_pair.private.q.clone();
+
+        // Multiples of p or q would make the signer reveal the other factor
+        for value in [p.clone(), &p * 3u32, q.clone(), &q * 5u32] {
+            let result = key_pair.private.blind_sign(&BlindedMessage::new(value));
+            assert!(matches!(result, Err(RabinWilliamsError::InvalidBlindedMessage)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_square_root_attack_factors_n() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let public = &key_pair.public;
+        let n = public.n().clone();
+
+        // A malicious client sends squares of values it picked. The signer
+        // answers with the principal root, which is not ±x half of the time.
+        let mut factored = false;
+        for _ in 0..64 {
+            let x = public.coprime();
+            let (_, _, root) = public.extract_signature(&key_pair.private.blind_sign(&BlindedMessage::new(&x * &x % &n))?.0)?;
+            let g = ((&root + &n - &x) % &n).gcd(&n);
+            if g != BigUint::from(1u32) && g != n {
+                assert!(g == key_pair.private.p || g == key_pair.private.q);
+                factored = true;
+                break;
+            }
+        }
+        assert!(factored, "the square-root attack is expected to factor n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wire_encoding() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let (blinded, state) = key_pair.public.blind(BlindingRequest::new(b"wire"));
+        let received = BlindedMessage::from_bytes(&blinded.to_bytes());
+        assert_eq!(received, blinded);
+
+        let response = key_pair.private.blind_sign(&received)?;
+        let response = BlindSignature::from_bytes(response.as_bytes())?;
+        assert!(state.finalize(&response).is_ok());
+
+        assert!(BlindSignature::from_bytes(&[0x00]).is_err());
+        assert!(BlindSignature::from_bytes(&[0x04, 0x01]).is_err());
+
+        Ok(())
+    }
+}
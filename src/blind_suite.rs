@@ -0,0 +1,436 @@
+//! Blind signature suites modeled on RFC 9474 (RSABSSA)
+//!
+//! A suite fixes how a message is prepared and encoded before blinding:
+//!
+//! - *Randomized* suites prepend a 32-byte random prefix to the message, so the
+//!   signer cannot link a signature to a message it has seen before.
+//!   *Deterministic* suites sign the message as is.
+//! - *FDH* suites encode the prepared message with a full-domain hash (MGF1 over
+//!   the key's hash function, one bit shorter than the modulus). *PSS* suites use
+//!   EMSA-PSS encoding with a random salt as long as the hash output.
+//!
+//! The flow follows RFC 9474: the client calls `BlindSuite::prepare`, then
+//! `PublicKey::blind_with_suite`, the signer answers with `PrivateKey::blind_sign`,
+//! and `SuiteBlindingState::finalize` checks the response against the blinded
+//! message before unblinding it. Anyone can then check the final signature with
+//! `PublicKey::verify_with_suite` and the prepared message.
+
+use crate::blind::{BlindSignature, BlindedMessage};
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, mgf1, HashFunction};
+use crate::keys::PublicKey;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+use rand::RngCore;
+use std::fmt;
+
+/// Length of the random prefix added by randomized suites
+pub const PREFIX_LEN: usize = 32;
+
+/// A named blind-signature protocol variant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlindSuite {
+    /// Full-domain hash encoding, message signed as is
+    FdhDeterministic,
+    /// Full-domain hash encoding, random prefix added to the message
+    FdhRandomized,
+    /// EMSA-PSS encoding, message signed as is
+    PssDeterministic,
+    /// EMSA-PSS encoding, random prefix added to the message
+    PssRandomized,
+}
+
+impl BlindSuite {
+    /// Every suite, in a stable order
+    pub const ALL: [BlindSuite; 4] = [
+        BlindSuite::FdhDeterministic,
+        BlindSuite::FdhRandomized,
+        BlindSuite::PssDeterministic,
+        BlindSuite::PssRandomized,
+    ];
+
+    /// Returns the suite's name, e.g. `RWBSSA-PSS-Randomized`
+    pub fn name(self) -> &'static str {
+        match self {
+            BlindSuite::FdhDeterministic => "RWBSSA-FDH-Deterministic",
+            BlindSuite::FdhRandomized => "RWBSSA-FDH-Randomized",
+            BlindSuite::PssDeterministic => "RWBSSA-PSS-Deterministic",
+            BlindSuite::PssRandomized => "RWBSSA-PSS-Randomized",
+        }
+    }
+
+    /// Looks up a suite by its name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|suite| suite.name() == name)
+    }
+
+    /// Returns true if the suite adds a random prefix to messages
+    pub fn is_randomized(self) -> bool {
+        matches!(self, BlindSuite::FdhRandomized | BlindSuite::PssRandomized)
+    }
+
+    /// Returns true if the suite uses EMSA-PSS encoding
+    pub fn is_pss(self) -> bool {
+        matches!(self, BlindSuite::PssDeterministic | BlindSuite::PssRandomized)
+    }
+
+    /// Prepares a message for blinding
+    ///
+    /// The prepared message is what gets signed, so it must be kept and passed
+    /// to `verify_with_suite` alongside the signature.
+    pub fn prepare(self, message: &[u8]) -> Vec<u8> {
+        if !self.is_randomized() {
+            return message.to_vec();
+        }
+        let mut prefix = [0u8; PREFIX_LEN];
+        rand::thread_rng().fill_bytes(&mut prefix);
+        prepare_with_prefix(&prefix, message)
+    }
+}
+
+impl fmt::Display for BlindSuite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn prepare_with_prefix(prefix: &[u8; PREFIX_LEN], message: &[u8]) -> Vec<u8> {
+    let mut prepared = prefix.to_vec();
+    prepared.extend_from_slice(message);
+    prepared
+}
+
+/// Mask for the first byte of an encoded message, clearing the bits above `em_bits`
+fn top_byte_mask(em_bits: usize) -> u8 {
+    0xFF >> (8 * em_bits.div_ceil(8) - em_bits)
+}
+
+/// Full-domain hash of a message into `em_bits` bits
+fn fdh_encode<D: HashFunction>(message: &[u8], em_bits: usize) -> BigUint {
+    let mut em = mgf1::<D>(message, em_bits.div_ceil(8));
+    em[0] &= top_byte_mask(em_bits);
+    BigUint::from_bytes_be(&em)
+}
+
+/// EMSA-PSS encoding from RFC 8017 into `em_bits` bits
+fn pss_encode<D: HashFunction>(message: &[u8], salt: &[u8], em_bits: usize) -> Result<BigUint> {
+    let h_len = D::default_bits() / 8;
+    let em_len = em_bits.div_ceil(8);
+    if em_len < h_len + salt.len() + 2 {
+        return Err(RabinWilliamsError::InvalidKeySize);
+    }
+
+    let m_hash = digest::<D>(&[message]);
+    let h = digest::<D>(&[&[0u8; 8], &m_hash, salt]);
+
+    let mut db = vec![0u8; em_len - salt.len() - h_len - 2];
+    db.push(0x01);
+    db.extend_from_slice(salt);
+    for (byte, mask) in db.iter_mut().zip(mgf1::<D>(&h, em_len - h_len - 1)) {
+        *byte ^= mask;
+    }
+    db[0] &= top_byte_mask(em_bits);
+
+    db.extend_from_slice(&h);
+    db.push(0xBC);
+    Ok(BigUint::from_bytes_be(&db))
+}
+
+/// EMSA-PSS verification from RFC 8017, with the salt as long as the hash output
+fn pss_verify<D: HashFunction>(message: &[u8], em: &BigUint, em_bits: usize) -> bool {
+    let h_len = D::default_bits() / 8;
+    let s_len = h_len;
+    let em_len = em_bits.div_ceil(8);
+    if em.bits() as usize > em_bits || em_len < h_len + s_len + 2 {
+        return false;
+    }
+
+    let bytes = em.to_bytes_be();
+    let mut encoded = vec![0u8; em_len - bytes.len()];
+    encoded.extend_from_slice(&bytes);
+    if encoded[em_len - 1] != 0xBC {
+        return false;
+    }
+
+    let (masked_db, rest) = encoded.split_at(em_len - h_len - 1);
+    let h = &rest[..h_len];
+    if masked_db[0] & !top_byte_mask(em_bits) != 0 {
+        return false;
+    }
+    let mut db: Vec<u8> = masked_db
+        .iter()
+        .zip(mgf1::<D>(h, masked_db.len()))
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    db[0] &= top_byte_mask(em_bits);
+
+    let ps_len = em_len - h_len - s_len - 2;
+    if db[..ps_len].iter().any(|&byte| byte != 0) || db[ps_len] != 0x01 {
+        return false;
+    }
+    let salt = &db[ps_len + 1..];
+
+    let m_hash = digest::<D>(&[message]);
+    digest::<D>(&[&[0u8; 8], &m_hash, salt]) == h
+}
+
+/// Client-side state for one blind-signing session under a suite
+#[derive(Clone, Debug)]
+pub struct SuiteBlindingState<D: HashFunction> {
+    public_key: PublicKey<D>,
+    suite: BlindSuite,
+    prepared_message: Vec<u8>,
+    blinded: BigUint,
+    r: BigUint,
+}
+
+impl<D: HashFunction> SuiteBlindingState<D> {
+    /// Returns the suite this session uses
+    pub fn suite(&self) -> BlindSuite {
+        self.suite
+    }
+
+    /// Returns the prepared message the final signature will be over
+    pub fn prepared_message(&self) -> &[u8] {
+        &self.prepared_message
+    }
+
+    /// Checks the signer's response, unblinds it and verifies the result
+    ///
+    /// The response is checked against the blinded message before unblinding,
+    /// so a faulty or malicious signer is detected without using the blinding
+    /// factor on a bad value.
+    pub fn finalize(self, blind_signature: &BlindSignature) -> Result<Vec<u8>> {
+        if !self.public_key.verify_hash(&self.blinded, blind_signature.as_bytes())? {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+
+        let signature = self.public_key.unblind_signature(blind_signature.as_bytes(), &self.r)?;
+        if !self.public_key.verify_with_suite(self.suite, &self.prepared_message, &signature)? {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+        Ok(signature)
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Number of bits in an encoded message, one less than the modulus
+    fn suite_encoded_bits(&self) -> usize {
+        (self.n().bits() as usize).saturating_sub(1)
+    }
+
+    /// Encodes a message as the `RWBSSA-FDH-Deterministic` suite does
+    pub(crate) fn fdh_encode_message(&self, message: &[u8]) -> BigUint {
+        fdh_encode::<D>(message, self.suite_encoded_bits())
+    }
+
+    /// Starts a blind-signing session for a message prepared with `suite.prepare`
+    ///
+    /// Returns the blinded message to send to the signer and the state needed
+    /// to finalize the signature.
+    pub fn blind_with_suite(
+        &self,
+        suite: BlindSuite,
+        prepared_message: &[u8],
+    ) -> Result<(BlindedMessage, SuiteBlindingState<D>)> {
+        let mut salt = vec![0u8; if suite.is_pss() { D::default_bits() / 8 } else { 0 }];
+        rand::thread_rng().fill_bytes(&mut salt);
+        self.blind_with_suite_using(suite, prepared_message, &salt, self.blinding().0)
+    }
+
+    fn blind_with_suite_using(
+        &self,
+        suite: BlindSuite,
+        prepared_message: &[u8],
+        salt: &[u8],
+        r: BigUint,
+    ) -> Result<(BlindedMessage, SuiteBlindingState<D>)> {
+        let em_bits = self.suite_encoded_bits();
+        let encoded = if suite.is_pss() {
+            pss_encode::<D>(prepared_message, salt, em_bits)?
+        } else {
+            fdh_encode::<D>(prepared_message, em_bits)
+        };
+
+        // An encoding sharing a factor with n cannot be blinded safely
+        if !encoded.gcd(self.n()).is_one() {
+            return Err(RabinWilliamsError::ComputationError);
+        }
+
+        let blinded = &r * &r % self.n() * &encoded % self.n();
+        let state = SuiteBlindingState {
+            public_key: self.clone(),
+            suite,
+            prepared_message: prepared_message.to_vec(),
+            blinded: blinded.clone(),
+            r,
+        };
+        Ok((BlindedMessage::new(blinded), state))
+    }
+
+    /// Verifies a signature produced under `suite` over a prepared message
+    pub fn verify_with_suite(&self, suite: BlindSuite, prepared_message: &[u8], signature: &[u8]) -> Result<bool> {
+        let em_bits = self.suite_encoded_bits();
+        if suite.is_pss() {
+            let encoded = self.recover_hash(signature)?;
+            Ok(pss_verify::<D>(prepared_message, &encoded, em_bits))
+        } else {
+            self.verify_hash(&fdh_encode::<D>(prepared_message, em_bits), signature)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeyPair, PrivateKey};
+    use sha2::Sha256;
+
+    #[test]
+    fn test_all_suites() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let message = b"Hello, World!";
+
+        for suite in BlindSuite::ALL {
+            let prepared = suite.prepare(message);
+            let (blinded, state) = key_pair.public.blind_with_suite(suite, &prepared)?;
+            let signature = state.finalize(&key_pair.private.blind_sign(&blinded)?)?;
+
+            assert!(key_pair.public.verify_with_suite(suite, &prepared, &signature)?, "{}", suite);
+            assert!(!key_pair.public.verify_with_suite(suite, b"forged", &signature)?, "{}", suite);
+            assert_eq!(BlindSuite::from_name(suite.name()), Some(suite));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suite_message_preparation() {
+        let message = b"Hello, World!";
+
+        assert_eq!(BlindSuite::FdhDeterministic.prepare(message), message);
+        assert_eq!(BlindSuite::PssDeterministic.prepare(message), message);
+
+        let first = BlindSuite::PssRandomized.prepare(message);
+        let second = BlindSuite::PssRandomized.prepare(message);
+        assert_eq!(first.len(), PREFIX_LEN + message.len());
+        assert_eq!(&first[PREFIX_LEN..], message);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_finalize_rejects_bad_blind_signature() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let prepared = BlindSuite::PssRandomized.prepare(b"a");
+        let (_blinded, state) = key_pair.public.blind_with_suite(BlindSuite::PssRandomized, &prepared)?;
+
+        // A valid signature over a different blinded message is rejected before unblinding
+        let (other, _) = key_pair.public.blind_with_suite(BlindSuite::PssRandomized, &prepared)?;
+        let response = key_pair.private.blind_sign(&other)?;
+        assert!(matches!(state.finalize(&response), Err(RabinWilliamsError::InvalidSignature)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suites_are_not_interchangeable() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let prepared = b"Hello, World!";
+
+        let (blinded, state) = key_pair.public.blind_with_suite(BlindSuite::PssDeterministic, prepared)?;
+        let signature = state.finalize(&key_pair.private.blind_sign(&blinded)?)?;
+
+        assert!(!key_pair.public.verify_with_suite(BlindSuite::FdhDeterministic, prepared, &signature)?);
+        assert!(!key_pair.public.verify(prepared, &signature)?);
+
+        Ok(())
+    }
+
+    /// Key from `contracts/test/fixtures/private_key.hex`
+    fn fixture_key() -> PrivateKey<Sha256> {
+        let mut lines = include_str!("../contracts/test/fixtures/private_key.hex").lines();
+        let p = BigUint::parse_bytes(lines.next().unwrap().trim().as_bytes(), 16).unwrap();
+        let q = BigUint::parse_bytes(lines.next().unwrap().trim().as_bytes(), 16).unwrap();
+        PrivateKey::from_primes(p, q)
+    }
+
+    struct RegressionOutput {
+        suite: BlindSuite,
+        prepared: &'static str,
+        blinded: &'static str,
+        blind_signature: &'static str,
+        signature: &'static str,
+    }
+
+    const FIXTURE_MESSAGE: &[u8] = b"Hello, World!";
+    const FIXTURE_PREFIX: [u8; PREFIX_LEN] = [0x5A; PREFIX_LEN];
+    const FIXTURE_SALT: [u8; 32] = [0xA5; 32];
+    const FIXTURE_R: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    /// Outputs of this implementation, one per suite, for `FIXTURE_MESSAGE`
+    /// under the fixture key with fixed prefix, salt and blinding factor
+    ///
+    /// These are regression values recorded from this code, not independent
+    /// test vectors: they catch changes in the encoding, not mistakes in it.
+    const REGRESSION_OUTPUTS: &[RegressionOutput] = &[
+        RegressionOutput {
+            suite: BlindSuite::FdhDeterministic,
+            prepared: "48656c6c6f2c20576f726c6421",
+            blinded: "291d24407c4df6d3c7d73f987d6d16d34dd2a7ee16a80040730bd659339979ad94e0b06729a8af07f27091bb332ab8460c814a74dfebcd685a4e1c2fdb266b0ed98be2b8971033fbc635443fa5e123d5ba316f130aa1fabaf1049102ea7fca525e48e7f90b5e8ac8a8f22b0fe834bef66ae0c6676d325fad4d599d75186389f5",
+            blind_signature: "011b33bb8da6093fff8d78620fcdd00c4589bfd6c9516c03b176fc6c0386b02e02b7d2970800f28e3a47c033c6f304a4414d757626e529ad4b4a15e3ed9cfc7e0bb06df9721fb254b184c4f29d8e59955dcae024e1de695b71515e2ba2132979f3c7876b69fa4d40e0208e9dafbcc5f1c3f273b1ca4f74ec1a436ec1c594173feb",
+            signature: "011007138cf0bdd39f1480357ae4f156482d4d16c53ae07142ce38ff34bc7c91246dee362508b02391b0cc388ab05b96e461f4966e0717522e204a5f1433aba85d1fae75c2b100d7cb2b53f25738f7c6ffb0ea98b150e0df1f32c843a86eb6e1d5a83fe773901038e3305880d9602c7e8e9ccbc92a58da90f5dab1b8bacb3faee7",
+        },
+        RegressionOutput {
+            suite: BlindSuite::FdhRandomized,
+            prepared: "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a48656c6c6f2c20576f726c6421",
+            blinded: "4ad88ca41510a7913e1205c96b6e0db79c81e0c56134834b77b55a5d2c27b9a3481a4a1a353d4ab72fd03b09fce08b60da5c0ed6e2dff3a65890d1b16acea9642589083d06b7cf1a62e4690632946a4c88bb58ae4191bf68b65c04df613e2def5bb3364f5a4aa014470bb12b8a75cf4407e80b058a6b0495f05da52336e9c10d",
+            blind_signature: "031b5cb827c975db8f4308643a5b924db6289cb12a4cfd959f278a0a14c85959f47e351d41053ee67446a0e36bc56847fdb00b4b9ae0c7945244d1bc333379c6580ebb5b01e918b3332cf96c152d9068d06a160041180567f1b49010f93d28350b73b4d16ecdd18b68cbf4955191872c52fbb8374273826aedb5fd403c19322e4c",
+            signature: "03338271eebab9bd2fa6e3a301dc8c47c4a8b95afdc803b582dd45ad9dac120e220ef79ed00c0128f9ff2e6cbfa16a6ce15b34874339f6b81d9537e524e6fcc51d6d89189762e179f36b436b6e7a76dd31134077c638bdffb16e0d1225bd597cd76eac9e716cd3dd3f5e4f8ad172f4cee7fd4322f80e17040814fb3a9659e82f69",
+        },
+        RegressionOutput {
+            suite: BlindSuite::PssDeterministic,
+            prepared: "48656c6c6f2c20576f726c6421",
+            blinded: "3ca98195d8ac9748b2f4901088783d5d3f772b76502465e794f52c8951e69b36a95f7983e182e0fbb33f078a2ad58e2fdbc1ac82ce1c937cf8c3e5c82a5c5fa84761dbcdc19944c4807789f1a4850d60f90cb7904f16d2b1cacdd973da2939771572665e213efb4ae777646dd4e584414c991118adfedcbc59925c3f3c16025a",
+            blind_signature: "021d1f266d0731b66e617459f4ebb705c251a7ce081ee7e472895b725dc64fab06122d661741369d13ad7c9d0d4af60eab70490caad4c6187a7c998defa06cb26239bdf3960b13c0e7a10c8b1799502479e7bad6c37a25415f5260c73a333ef4e3a81cb1f994c71140599acd5a5bc4b53934107362c545afe6494f7bf3a9110c15",
+            signature: "0216ed16852fbf796d4e67adf455bf31aac5ec025ef490c36ebc2b4e7f0ffa90ee14a1bf0e6ee923224b42ad861480599401b1b29f2a84428c5383d78cd67aef9b564c0bd7f74217ba7d15476242de38a39936bb164959090bafdc25c2e02aac5d728ef4d409e02a558b5e2842cdc9cab66d3f93d75a228a05cbe763db8ac269a6",
+        },
+        RegressionOutput {
+            suite: BlindSuite::PssRandomized,
+            prepared: "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a48656c6c6f2c20576f726c6421",
+            blinded: "03cc6f427ad3c529c49e9c4502ca5b118172bf133c20117a5f46d7b935360169e05f76aa7a58a59375870815621aeee27da3d0a67e3c0a9fd5419ee2f8cee3f1789145001edc55442d898c9bacade20b7bfb849427c2372af57a84d9fae950054f7cf5169713143372035e0ca7d69c6402a0ecd729d49f95b5bb28d7658e826e",
+            blind_signature: "002fdffca9ed56ad28fc2cb3e784f34ec85f59cd6a367a336a0f26fe71bfa8ab6a197b33989fbc57b3f48ce79165cd84b04d2b851c747cef469cf292a65c101d6daf462a9aac77dc52a0ba44b0489abce4df259c3ec40152ff02c8ab391c50f211fb7f556c0c536f4e7d07e98d51a6c30279f497747617efbe0272cedabfe1ea15",
+            signature: "006162c822b2f899f753acdfb8bbebf3d3039362384ad53aa6c9dd35774cea1458b455d28c4662eeb85c82dbdd01ddbcbd61e05d91d25814f48af1c57be2452f2f35248089b935c374131b45359b1c7aac539249dee9db66aa973e6b31fa6a688a02ca47cf283c6b4c3ce79babd689cb6f91ee86b1195aa9d24f9c4464f2b6f482",
+        },
+    ];
+
+    #[test]
+    fn test_regression_outputs() -> Result<()> {
+        let private_key = fixture_key();
+        let public_key = PublicKey::<Sha256>::from_n(private_key.n());
+        let r = BigUint::parse_bytes(FIXTURE_R.as_bytes(), 16).unwrap();
+
+        for output in REGRESSION_OUTPUTS {
+            let prepared = if output.suite.is_randomized() {
+                prepare_with_prefix(&FIXTURE_PREFIX, FIXTURE_MESSAGE)
+            } else {
+                FIXTURE_MESSAGE.to_vec()
+            };
+            assert_eq!(hex::encode(&prepared), output.prepared, "{}", output.suite);
+
+            let salt: &[u8] = if output.suite.is_pss() { &FIXTURE_SALT } else { &[] };
+            let (blinded, state) = public_key.blind_with_suite_using(output.suite, &prepared, salt, r.clone())?;
+            assert_eq!(hex::encode(blinded.to_bytes()), output.blinded, "{}", output.suite);
+
+            let blind_signature = private_key.blind_sign(&blinded)?;
+            assert_eq!(hex::encode(blind_signature.as_bytes()), output.blind_signature, "{}", output.suite);
+
+            let signature = state.finalize(&blind_signature)?;
+            assert_eq!(hex::encode(&signature), output.signature, "{}", output.suite);
+            assert!(public_key.verify_with_suite(output.suite, &prepared, &signature)?);
+        }
+
+        Ok(())
+    }
+}
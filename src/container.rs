@@ -0,0 +1,547 @@
+//! Detached signature containers
+//!
+//! A bare signature says nothing about who made it or how. A container wraps
+//! the signature with the signer's key fingerprint, the key's hash function
+//! identifier, how the message was encoded before signing, an optional creation
+//! time and optional named attributes. All of these are signed along with the
+//! message, so none can be changed without invalidating the signature, and a
+//! verifier can pick the right key from a keyring by fingerprint.
+//!
+//! The binary encoding is
+//!
+//! ```text
+//! "RWSG" | version (1) | fingerprint (32) | hash algorithm id (8) | encoding (1)
+//!        | flags (1) | [creation time (8)] | attribute count (2)
+//!        | { name length (1) | name | value length (2) | value }*
+//!        | signature length (2) | signature
+//! ```
+//!
+//! with big-endian integers, where flag bit 0 marks a creation time in seconds
+//! since the Unix epoch. The armored encoding is the binary encoding in base64
+//! between `BEGIN` and `END` lines, preceded by a readable fingerprint header.
+//!
+//! The signed data is a label, everything before the signature length, the
+//! length-prefixed context and the encoded message. Container signatures are
+//! therefore not interchangeable with bare signatures of the same message.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::ethereum::eip191_personal_message;
+use crate::fingerprint::{hash_algorithm_id, Fingerprint, FINGERPRINT_LEN, HASH_ID_LEN};
+use crate::hash::HashFunction;
+use crate::keys::{PrivateKey, PublicKey};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the container format written by this crate
+pub const CONTAINER_VERSION: u8 = 1;
+
+const CONTAINER_MAGIC: &[u8; 4] = b"RWSG";
+
+/// Label at the start of the signed data
+const SIGNED_DATA_LABEL: &[u8] = b"rabin-williams detached signature";
+
+const FLAG_CREATED_AT: u8 = 1;
+
+const ARMOR_BEGIN: &str = "-----BEGIN RABIN-WILLIAMS SIGNATURE-----";
+const ARMOR_END: &str = "-----END RABIN-WILLIAMS SIGNATURE-----";
+const ARMOR_LINE_LEN: usize = 64;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// How the message is encoded before it is signed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MessageEncoding {
+    /// The message bytes as they are
+    #[default]
+    Raw,
+    /// An EIP-191 personal message, see `eip191_personal_message`
+    Eip191,
+}
+
+impl MessageEncoding {
+    fn id(self) -> u8 {
+        match self {
+            MessageEncoding::Raw => 0,
+            MessageEncoding::Eip191 => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(MessageEncoding::Raw),
+            1 => Ok(MessageEncoding::Eip191),
+            _ => Err(RabinWilliamsError::InvalidSignatureContainer),
+        }
+    }
+
+    /// Returns the message as it is signed
+    pub fn encode(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            MessageEncoding::Raw => message.to_vec(),
+            MessageEncoding::Eip191 => eip191_personal_message(message),
+        }
+    }
+}
+
+/// Metadata signed along with a message
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignatureMetadata {
+    encoding: MessageEncoding,
+    created_at: Option<u64>,
+    attributes: Vec<(String, Vec<u8>)>,
+}
+
+impl SignatureMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how the message is encoded before signing
+    pub fn with_encoding(mut self, encoding: MessageEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Records a creation time, in seconds since the Unix epoch
+    pub fn with_created_at(mut self, created_at: u64) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Adds a named attribute
+    ///
+    /// Names are at most 255 bytes and values at most 65535 bytes; signing
+    /// fails with `InvalidSignatureContainer` otherwise.
+    pub fn with_attribute(mut self, name: &str, value: &[u8]) -> Self {
+        self.attributes.push((name.to_string(), value.to_vec()));
+        self
+    }
+
+    pub fn encoding(&self) -> MessageEncoding {
+        self.encoding
+    }
+
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    /// Returns the attributes in the order they were added
+    pub fn attributes(&self) -> &[(String, Vec<u8>)] {
+        &self.attributes
+    }
+
+    /// Returns the value of the first attribute called `name`
+    pub fn attribute(&self, name: &str) -> Option<&[u8]> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_slice())
+    }
+}
+
+/// A signature with the signer's fingerprint and signed metadata
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureContainer {
+    fingerprint: Fingerprint,
+    hash_algorithm: [u8; HASH_ID_LEN],
+    metadata: SignatureMetadata,
+    signature: Vec<u8>,
+}
+
+impl SignatureContainer {
+    /// Returns the fingerprint of the key that made the signature
+    pub fn fingerprint(&self) -> &Fingerprint {
+        &self.fingerprint
+    }
+
+    /// Returns the identifier of the signing key's hash function
+    pub fn hash_algorithm(&self) -> &[u8; HASH_ID_LEN] {
+        &self.hash_algorithm
+    }
+
+    pub fn metadata(&self) -> &SignatureMetadata {
+        &self.metadata
+    }
+
+    /// Returns the bare signature over the signed data
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Encodes everything but the signature, as it is signed
+    fn header(&self) -> Result<Vec<u8>> {
+        let mut bytes = CONTAINER_MAGIC.to_vec();
+        bytes.push(CONTAINER_VERSION);
+        bytes.extend_from_slice(self.fingerprint.as_bytes());
+        bytes.extend_from_slice(&self.hash_algorithm);
+        bytes.push(self.metadata.encoding.id());
+        match self.metadata.created_at {
+            Some(created_at) => {
+                bytes.push(FLAG_CREATED_AT);
+                bytes.extend_from_slice(&created_at.to_be_bytes());
+            }
+            None => bytes.push(0),
+        }
+
+        let count = u16::try_from(self.metadata.attributes.len())
+            .map_err(|_| RabinWilliamsError::InvalidSignatureContainer)?;
+        bytes.extend_from_slice(&count.to_be_bytes());
+        for (name, value) in &self.metadata.attributes {
+            let name_len = u8::try_from(name.len()).map_err(|_| RabinWilliamsError::InvalidSignatureContainer)?;
+            let value_len = u16::try_from(value.len()).map_err(|_| RabinWilliamsError::InvalidSignatureContainer)?;
+            bytes.push(name_len);
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&value_len.to_be_bytes());
+            bytes.extend_from_slice(value);
+        }
+        Ok(bytes)
+    }
+
+    /// Returns the data the signature covers
+    fn signed_data(&self, context: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+        let mut data = SIGNED_DATA_LABEL.to_vec();
+        data.extend_from_slice(&self.header()?);
+        data.extend_from_slice(&(context.len() as u64).to_be_bytes());
+        data.extend_from_slice(context);
+        data.extend_from_slice(&self.metadata.encoding.encode(message));
+        Ok(data)
+    }
+
+    /// Encodes the container in the binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Containers are only built by signing or parsing, which both check that
+        // the header and signature fit their length fields
+        let mut bytes = self.header().expect("container fields fit the format");
+        bytes.extend_from_slice(&(self.signature.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+
+    /// Reads a container in the binary format
+    ///
+    /// Fails with `UnsupportedContainerVersion` for containers from a newer
+    /// version of the format and `InvalidSignatureContainer` for malformed ones.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(CONTAINER_MAGIC.len())? != CONTAINER_MAGIC {
+            return Err(RabinWilliamsError::InvalidSignatureContainer);
+        }
+        let version = reader.byte()?;
+        if version != CONTAINER_VERSION {
+            return Err(RabinWilliamsError::UnsupportedContainerVersion(version));
+        }
+
+        let fingerprint = Fingerprint::from_bytes(reader.take(FINGERPRINT_LEN)?)?;
+        let hash_algorithm = reader.take(HASH_ID_LEN)?.try_into().expect("slice has HASH_ID_LEN bytes");
+        let encoding = MessageEncoding::from_id(reader.byte()?)?;
+        let created_at = match reader.byte()? {
+            0 => None,
+            FLAG_CREATED_AT => Some(u64::from_be_bytes(reader.take(8)?.try_into().expect("slice has 8 bytes"))),
+            _ => return Err(RabinWilliamsError::InvalidSignatureContainer),
+        };
+
+        let count = reader.u16()?;
+        let mut attributes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let name_len = reader.byte()? as usize;
+            let name = String::from_utf8(reader.take(name_len)?.to_vec())
+                .map_err(|_| RabinWilliamsError::InvalidSignatureContainer)?;
+            let value_len = reader.u16()? as usize;
+            attributes.push((name, reader.take(value_len)?.to_vec()));
+        }
+
+        let signature_len = reader.u16()? as usize;
+        let signature = reader.take(signature_len)?.to_vec();
+        if !reader.0.is_empty() {
+            return Err(RabinWilliamsError::InvalidSignatureContainer);
+        }
+
+        Ok(Self {
+            fingerprint,
+            hash_algorithm,
+            metadata: SignatureMetadata { encoding, created_at, attributes },
+            signature,
+        })
+    }
+
+    /// Encodes the container as armored text
+    pub fn to_armored(&self) -> String {
+        let body = base64_encode(&self.to_bytes());
+        let mut lines = vec![ARMOR_BEGIN.to_string(), format!("Fingerprint: {}", self.fingerprint), String::new()];
+        lines.extend(body.as_bytes().chunks(ARMOR_LINE_LEN).map(|line| String::from_utf8_lossy(line).into_owned()));
+        lines.push(ARMOR_END.to_string());
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads a container from armored text
+    ///
+    /// Header lines before the first blank line are informational and ignored;
+    /// the fingerprint is taken from the encoded container.
+    pub fn from_armored(text: &str) -> Result<Self> {
+        let lines: Vec<&str> = text.lines().map(str::trim).collect();
+        let begin = lines.iter().position(|line| *line == ARMOR_BEGIN);
+        let end = lines.iter().position(|line| *line == ARMOR_END);
+        let (begin, end) = match (begin, end) {
+            (Some(begin), Some(end)) if begin < end => (begin, end),
+            _ => return Err(RabinWilliamsError::InvalidSignatureContainer),
+        };
+
+        let mut block = &lines[begin + 1..end];
+        if let Some(blank) = block.iter().position(|line| line.is_empty()) {
+            block = &block[blank + 1..];
+        }
+        Self::from_bytes(&base64_decode(&block.concat())?)
+    }
+
+    /// Returns true if `bytes` look like an armored or binary container rather
+    /// than a bare signature
+    pub fn is_container(bytes: &[u8]) -> bool {
+        bytes.starts_with(CONTAINER_MAGIC) || String::from_utf8_lossy(bytes).trim_start().starts_with(ARMOR_BEGIN)
+    }
+
+    /// Reads a container in either the armored or the binary format
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(CONTAINER_MAGIC) {
+            Self::from_bytes(bytes)
+        } else {
+            let text = std::str::from_utf8(bytes).map_err(|_| RabinWilliamsError::InvalidSignatureContainer)?;
+            Self::from_armored(text)
+        }
+    }
+}
+
+impl fmt::Display for SignatureContainer {
+    /// Formats the container as armored text
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_armored())
+    }
+}
+
+impl FromStr for SignatureContainer {
+    type Err = RabinWilliamsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_armored(s)
+    }
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Signs a message into a container carrying `metadata`
+    pub fn sign_container(&self, message: &[u8], metadata: SignatureMetadata) -> Result<SignatureContainer> {
+        self.sign_container_with_context(b"", message, metadata)
+    }
+
+    /// Signs a message into a container, binding a context string as
+    /// `sign_with_context` does
+    ///
+    /// The context is not stored in the container; verifiers supply it.
+    pub fn sign_container_with_context(
+        &self,
+        context: &[u8],
+        message: &[u8],
+        metadata: SignatureMetadata,
+    ) -> Result<SignatureContainer> {
+        let mut container = SignatureContainer {
+            fingerprint: self.public_key().fingerprint(),
+            hash_algorithm: hash_algorithm_id::<D>(),
+            metadata,
+            signature: Vec::new(),
+        };
+        let signature = self.sign(&container.signed_data(context, message)?)?;
+        if u16::try_from(signature.len()).is_err() {
+            return Err(RabinWilliamsError::InvalidKeySize);
+        }
+        container.signature = signature;
+        Ok(container)
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Verifies a signature container over a message
+    ///
+    /// Returns false if the container was made with another key.
+    pub fn verify_container(&self, message: &[u8], container: &SignatureContainer) -> Result<bool> {
+        self.verify_container_with_context(b"", message, container)
+    }
+
+    /// Verifies a signature container made with `sign_container_with_context`
+    pub fn verify_container_with_context(
+        &self,
+        context: &[u8],
+        message: &[u8],
+        container: &SignatureContainer,
+    ) -> Result<bool> {
+        if container.fingerprint != self.fingerprint() || container.hash_algorithm != hash_algorithm_id::<D>() {
+            return Ok(false);
+        }
+        self.verify(&container.signed_data(context, message)?, &container.signature)
+    }
+}
+
+/// Reads fields from the front of a byte slice
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(RabinWilliamsError::InvalidSignatureContainer);
+        }
+        let (field, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(field)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().expect("slice has 2 bytes")))
+    }
+}
+
+/// Standard base64 with padding (RFC 4648)
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |acc, (i, &byte)| acc | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64_ALPHABET[(buffer >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+fn base64_decode(text: &str) -> Result<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return Err(RabinWilliamsError::InvalidSignatureContainer);
+    }
+    let mut output = Vec::with_capacity(text.len() / 4 * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let last = index == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return Err(RabinWilliamsError::InvalidSignatureContainer);
+        }
+        let mut buffer = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(RabinWilliamsError::InvalidSignatureContainer)?;
+            buffer = (buffer << 6) | value as u32;
+        }
+        buffer <<= 6 * padding;
+        output.extend_from_slice(&buffer.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_sign_and_verify_container() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let message = b"Hello, World!";
+        let metadata = SignatureMetadata::new()
+            .with_created_at(1_700_000_000)
+            .with_attribute("purpose", b"release")
+            .with_attribute("build", &[0, 1, 2]);
+
+        let container = key_pair.private.sign_container(message, metadata.clone())?;
+        assert_eq!(container.fingerprint(), &key_pair.public.fingerprint());
+        assert_eq!(container.metadata(), &metadata);
+        assert_eq!(container.metadata().attribute("purpose"), Some(&b"release"[..]));
+        assert!(key_pair.public.verify_container(message, &container)?);
+
+        assert!(!key_pair.public.verify_container(b"Hello, World?", &container)?);
+        assert!(!other.public.verify_container(message, &container)?);
+        // The container signature does not verify as a bare signature
+        assert!(!key_pair.public.verify(message, container.signature())?);
+
+        // Round trips through both encodings
+        assert_eq!(SignatureContainer::from_bytes(&container.to_bytes())?, container);
+        assert_eq!(container.to_armored().parse::<SignatureContainer>()?, container);
+        assert_eq!(SignatureContainer::parse(&container.to_bytes())?, container);
+        assert_eq!(SignatureContainer::parse(container.to_armored().as_bytes())?, container);
+        assert!(SignatureContainer::is_container(container.to_armored().as_bytes()));
+        assert!(!SignatureContainer::is_container(hex::encode(container.signature()).as_bytes()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_is_signed() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let message = b"Hello, World!";
+        let container = key_pair.private.sign_container_with_context(
+            b"login",
+            message,
+            SignatureMetadata::new().with_encoding(MessageEncoding::Eip191).with_created_at(1),
+        )?;
+        assert!(key_pair.public.verify_container_with_context(b"login", message, &container)?);
+        assert!(!key_pair.public.verify_container(message, &container)?);
+
+        let mut tampered = container.clone();
+        tampered.metadata.created_at = Some(2);
+        assert!(!key_pair.public.verify_container_with_context(b"login", message, &tampered)?);
+
+        let mut tampered = container.clone();
+        tampered.metadata.encoding = MessageEncoding::Raw;
+        assert!(!key_pair.public.verify_container_with_context(b"login", message, &tampered)?);
+
+        let mut tampered = container;
+        tampered.metadata.attributes.push(("extra".to_string(), Vec::new()));
+        assert!(!key_pair.public.verify_container_with_context(b"login", message, &tampered)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_containers() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let bytes = key_pair.private.sign_container(b"message", SignatureMetadata::new())?.to_bytes();
+
+        for len in [0, 4, 20, bytes.len() - 1] {
+            assert!(matches!(
+                SignatureContainer::from_bytes(&bytes[..len]),
+                Err(RabinWilliamsError::InvalidSignatureContainer)
+            ));
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(SignatureContainer::from_bytes(&trailing).is_err());
+
+        let mut future = bytes.clone();
+        future[CONTAINER_MAGIC.len()] = CONTAINER_VERSION + 1;
+        assert!(matches!(
+            SignatureContainer::from_bytes(&future),
+            Err(RabinWilliamsError::UnsupportedContainerVersion(2))
+        ));
+
+        assert!("not a signature".parse::<SignatureContainer>().is_err());
+        let too_long = SignatureMetadata::new().with_attribute(&"x".repeat(256), b"");
+        assert!(key_pair.private.sign_container(b"message", too_long).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64() -> Result<()> {
+        // RFC 4648 test vectors
+        for (input, output) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(base64_encode(input.as_bytes()), output);
+            assert_eq!(base64_decode(output)?, input.as_bytes());
+        }
+        assert!(base64_decode("Zg=").is_err());
+        assert!(base64_decode("Zg==Zm9v").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,618 @@
+//! Distributed generation of a Rabin-Williams modulus without a trusted dealer
+//!
+//! The protocol of Boneh and Franklin ("Efficient Generation of Shared RSA
+//! Keys"), adapted to p ≡ 3 and q ≡ 7 (mod 8). Each of `N ≥ 3` parties picks
+//! additive shares `pᵢ` and `qᵢ`: party 1's are 3 and 7 (mod 8) and everyone
+//! else's are 0 (mod 8), so `p = Σpᵢ` and `q = Σqᵢ` have the right residues.
+//! No party ever learns p or q.
+//!
+//! For each candidate the parties
+//!
+//! 1. compute `n = p·q` with the BGW protocol: they Shamir-share their `pᵢ` and
+//!    `qᵢ` with degree `t = ⌊(N-1)/2⌋` polynomials over a prime field larger
+//!    than `n`, together with a random degree-`2t` sharing of zero, multiply
+//!    their summed shares locally, add their share of zero so that the
+//!    broadcast products reveal nothing but n, and interpolate;
+//! 2. reject `n` if it has a small factor; and
+//! 3. run the biprimality test: for a public `g` with Jacobi symbol 1, party 1
+//!    publishes `v₁ = g^((n + 1 - p₁ - q₁)/4)` and every other party
+//!    `vᵢ = g^((pᵢ + qᵢ)/4)`. If n is a product of two primes that are 3 (mod 4),
+//!    `v₁ / Πvᵢ = g^(φ(n)/4) = ±1`. Otherwise a random `g` fails the check
+//!    with probability at least 1/2, and `BIPRIMALITY_ROUNDS` values of `g`
+//!    derived from n are tried.
+//!
+//! The paper adds a second step to exclude moduli of the form `p^a·q^b`, which
+//! pass this test; with random shares they occur with negligible probability
+//! and the step is omitted. Parties are assumed to follow the protocol (the
+//! honest-but-curious model); up to `⌊(N-1)/2⌋` colluding parties learn
+//! nothing about the factors beyond n.
+//!
+//! Each party ends up with a [`BiprimeShare`], whose additive share of Williams'
+//! exponent lets the parties sign together without reconstructing the key.
+//! Parties talk over a [`Network`]: channels between threads with
+//! [`in_process_networks`], or TCP with [`TcpNetwork`].
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{mgf1, HashFunction};
+use crate::keys::PublicKey;
+use crate::shamir::{evaluate_polynomial, interpolate_at_zero};
+use crate::two_party::{williams_input, williams_signature};
+use crate::utils::{jacobi, mod_inverse, read_frame, to_fixed_bytes, write_frame};
+use num_bigint::{BigInt, BigUint, RandBigInt, Sign};
+use num_integer::Integer;
+use num_prime::nt_funcs::{next_prime, primes};
+use num_prime::PrimalityTestConfig;
+use num_traits::{One, Zero};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// Smallest number of parties; with two, BGW multiplication hides nothing
+pub const MIN_PARTIES: usize = 3;
+
+/// Number of biprimality test rounds, each passed by a bad modulus with
+/// probability at most 1/2
+pub const BIPRIMALITY_ROUNDS: u32 = 40;
+
+/// Candidate moduli with a prime factor below this bound are rejected
+const TRIAL_DIVISION_BOUND: u64 = 1 << 14;
+
+/// Domain separator for deriving biprimality test bases
+const BIPRIMALITY_CONTEXT: &[u8] = b"rabin-williams biprimality test";
+
+/// How long a TCP party keeps retrying to reach a peer that is not up yet
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest message a TCP party accepts from a peer
+///
+/// Messages are at most three field elements, so this allows moduli of well
+/// over 100,000 bits.
+const MAX_MESSAGE_LEN: usize = 1 << 16;
+
+/// Point-to-point channels between the parties of a protocol run
+pub trait Network {
+    /// Returns this party's index, from 1 to `parties()`
+    fn index(&self) -> usize;
+
+    /// Returns the number of parties
+    fn parties(&self) -> usize;
+
+    /// Sends a message to another party
+    fn send(&mut self, to: usize, message: &[u8]) -> Result<()>;
+
+    /// Receives the next message from another party
+    fn receive(&mut self, from: usize) -> Result<Vec<u8>>;
+}
+
+/// Sends a message to every other party and returns everyone's message for the
+/// same step, in index order and including this party's own
+fn broadcast<N: Network>(network: &mut N, message: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let index = network.index();
+    for peer in (1..=network.parties()).filter(|&peer| peer != index) {
+        network.send(peer, message)?;
+    }
+    (1..=network.parties())
+        .map(|peer| {
+            if peer == index {
+                Ok(message.to_vec())
+            } else {
+                network.receive(peer)
+            }
+        })
+        .collect()
+}
+
+/// A party's end of in-process channels to every other party
+#[derive(Debug)]
+pub struct InProcessNetwork {
+    index: usize,
+    /// Indexed by peer index - 1; `None` for this party
+    senders: Vec<Option<Sender<Vec<u8>>>>,
+    receivers: Vec<Option<Receiver<Vec<u8>>>>,
+}
+
+/// Creates connected networks for `parties` parties running in the same
+/// process, in index order
+pub fn in_process_networks(parties: usize) -> Vec<InProcessNetwork> {
+    let mut networks: Vec<InProcessNetwork> = (1..=parties)
+        .map(|index| InProcessNetwork {
+            index,
+            senders: (0..parties).map(|_| None).collect(),
+            receivers: (0..parties).map(|_| None).collect(),
+        })
+        .collect();
+    for from in 0..parties {
+        for to in (0..parties).filter(|&to| to != from) {
+            let (sender, receiver) = channel();
+            networks[from].senders[to] = Some(sender);
+            networks[to].receivers[from] = Some(receiver);
+        }
+    }
+    networks
+}
+
+impl Network for InProcessNetwork {
+    fn index(&self) -> usize {
+        self.index
+    }
+
+    fn parties(&self) -> usize {
+        self.senders.len()
+    }
+
+    fn send(&mut self, to: usize, message: &[u8]) -> Result<()> {
+        let sender = self.senders.get(to.wrapping_sub(1)).and_then(Option::as_ref);
+        sender
+            .ok_or(RabinWilliamsError::TransportError)?
+            .send(message.to_vec())
+            .map_err(|_| RabinWilliamsError::TransportError)
+    }
+
+    fn receive(&mut self, from: usize) -> Result<Vec<u8>> {
+        let receiver = self.receivers.get(from.wrapping_sub(1)).and_then(Option::as_ref);
+        receiver
+            .ok_or(RabinWilliamsError::TransportError)?
+            .recv()
+            .map_err(|_| RabinWilliamsError::TransportError)
+    }
+}
+
+/// A party's TCP connections to every other party
+///
+/// Messages are framed with a 4-byte big-endian length, and longer than
+/// `MAX_MESSAGE_LEN` are refused.
+#[derive(Debug)]
+pub struct TcpNetwork {
+    index: usize,
+    /// Indexed by peer index - 1; `None` for this party
+    streams: Vec<Option<TcpStream>>,
+}
+
+impl TcpNetwork {
+    /// Connects party `index` to the others
+    ///
+    /// `addresses` lists every party's listening address in index order, and
+    /// `listener` must be bound to this party's. Each party connects to the
+    /// parties after it, retrying until they are up, and accepts connections
+    /// from the parties before it.
+    pub fn connect(index: usize, listener: TcpListener, addresses: &[SocketAddr]) -> Result<Self> {
+        let parties = addresses.len();
+        if index == 0 || index > parties {
+            return Err(RabinWilliamsError::TransportError);
+        }
+        let mut streams: Vec<Option<TcpStream>> = (0..parties).map(|_| None).collect();
+
+        for peer in index + 1..=parties {
+            let deadline = Instant::now() + CONNECT_TIMEOUT;
+            let mut stream = loop {
+                match TcpStream::connect(addresses[peer - 1]) {
+                    Ok(stream) => break stream,
+                    Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(50)),
+                    Err(_) => return Err(RabinWilliamsError::TransportError),
+                }
+            };
+            write_frame(&mut stream, &(index as u32).to_be_bytes())?;
+            streams[peer - 1] = Some(stream);
+        }
+
+        for _ in 1..index {
+            let (mut stream, _) = listener.accept().map_err(|_| RabinWilliamsError::TransportError)?;
+            let peer = <[u8; 4]>::try_from(read_frame(&mut stream, MAX_MESSAGE_LEN)?).map_err(|_| RabinWilliamsError::TransportError)?;
+            let peer = u32::from_be_bytes(peer) as usize;
+            if peer == 0 || peer >= index || streams[peer - 1].is_some() {
+                return Err(RabinWilliamsError::TransportError);
+            }
+            streams[peer - 1] = Some(stream);
+        }
+
+        for stream in streams.iter().flatten() {
+            stream.set_nodelay(true).map_err(|_| RabinWilliamsError::TransportError)?;
+        }
+        Ok(Self { index, streams })
+    }
+
+    fn stream(&mut self, peer: usize) -> Result<&mut TcpStream> {
+        self.streams
+            .get_mut(peer.wrapping_sub(1))
+            .and_then(Option::as_mut)
+            .ok_or(RabinWilliamsError::TransportError)
+    }
+}
+
+impl Network for TcpNetwork {
+    fn index(&self) -> usize {
+        self.index
+    }
+
+    fn parties(&self) -> usize {
+        self.streams.len()
+    }
+
+    fn send(&mut self, to: usize, message: &[u8]) -> Result<()> {
+        write_frame(self.stream(to)?, message)
+    }
+
+    fn receive(&mut self, from: usize) -> Result<Vec<u8>> {
+        read_frame(self.stream(from)?, MAX_MESSAGE_LEN)
+    }
+}
+
+/// One party's share of a distributed key
+#[derive(Clone, Debug)]
+pub struct BiprimeShare {
+    index: usize,
+    n: BigUint,
+    p_share: BigUint,
+    q_share: BigUint,
+}
+
+impl BiprimeShare {
+    /// Returns the party's index
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the modulus
+    pub fn n(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Returns this party's additive share of Williams' exponent
+    /// `d = (φ(n)/4 + 1) / 2`
+    ///
+    /// Party 1's share is `((n + 1 - p₁ - q₁)/4 + 1) / 2` and every other party's
+    /// `-(pᵢ + qᵢ)/8`, which sum to `d`.
+    pub fn exponent_share(&self) -> BigInt {
+        let own = &self.p_share + &self.q_share;
+        if self.index == 1 {
+            BigInt::from(((&self.n + 1u32 - own) / 4u32 + 1u32) / 2u32)
+        } else {
+            -BigInt::from(own / 8u32)
+        }
+    }
+
+    /// Computes this party's partial signature on a message
+    ///
+    /// Combine every party's with [`combine_partial_signatures`].
+    pub fn partial_sign<D: HashFunction>(&self, public_key: &PublicKey<D>, message: &[u8]) -> Result<BigUint> {
+        if public_key.n() != &self.n {
+            return Err(RabinWilliamsError::ShareMismatch);
+        }
+        let (_, a) = williams_input(public_key, message)?;
+        let (sign, d) = self.exponent_share().into_parts();
+        let y = a.modpow(&d, &self.n);
+        if sign == Sign::Minus {
+            mod_inverse(&y, &self.n).ok_or(RabinWilliamsError::ComputationError)
+        } else {
+            Ok(y)
+        }
+    }
+}
+
+/// Combines every party's partial signature on a message into a signature
+///
+/// Fails with `InvalidSignature` if the result does not verify, which happens
+/// when a party is missing or sent a wrong partial signature.
+pub fn combine_partial_signatures<D: HashFunction>(
+    public_key: &PublicKey<D>,
+    message: &[u8],
+    partials: &[BigUint],
+) -> Result<Vec<u8>> {
+    let (f, a) = williams_input(public_key, message)?;
+    let n = public_key.n();
+    let x = partials.iter().fold(BigUint::one(), |x, partial| x * partial % n);
+    let signature = williams_signature::<D>(n, &a, f, &x).map_err(|_| RabinWilliamsError::InvalidSignature)?;
+    if !public_key.verify(message, &signature)? {
+        return Err(RabinWilliamsError::InvalidSignature);
+    }
+    Ok(signature)
+}
+
+/// Splits a message into `count` elements of `len` bytes each
+fn decode_elements(message: &[u8], count: usize, len: usize) -> Result<Vec<BigUint>> {
+    if message.len() != count * len {
+        return Err(RabinWilliamsError::TransportError);
+    }
+    Ok(message.chunks(len).map(BigUint::from_bytes_be).collect())
+}
+
+/// Picks this party's additive share of one prime
+///
+/// Party 1 adds `3·2^(h-2)` so that the prime has exactly `h` bits and the
+/// modulus exactly `2h`; the shares sum to less than `2^(h-2)`.
+fn pick_prime_share(index: usize, parties: usize, half_bits: usize, residue: u32) -> BigUint {
+    let bound = (BigUint::one() << (half_bits - 2)) / (8 * parties) - 1u32;
+    let share = rand::thread_rng().gen_biguint_below(&bound) * 8u32;
+    if index == 1 {
+        share + residue + (BigUint::from(3u32) << (half_bits - 2))
+    } else {
+        share
+    }
+}
+
+/// Derives the `round`-th biprimality test base from the modulus
+fn biprimality_base<D: HashFunction>(n: &BigUint, round: u32) -> BigUint {
+    // 16 extra bytes make the reduction modulo n close to uniform
+    let len = n.bits().div_ceil(8) as usize + 16;
+    (0u32..)
+        .map(|counter| {
+            let seed = [BIPRIMALITY_CONTEXT, &n.to_bytes_be(), &round.to_be_bytes(), &counter.to_be_bytes()].concat();
+            BigUint::from_bytes_be(&mgf1::<D>(&seed, len)) % n
+        })
+        .find(|g| jacobi(g, n) == 1)
+        .expect("half of all residues have Jacobi symbol 1")
+}
+
+/// Deals this party's BGW shares and sums the ones it receives
+///
+/// Each party Shamir-shares `pᵢ` and `qᵢ` with degree `t = ⌊(N-1)/2⌋`
+/// polynomials, and deals a random degree-`2t` polynomial with constant term 0.
+/// Returns this party's points `[P(j), Q(j), H(j)]` on the summed polynomials.
+fn share_factors<N: Network, R: rand::Rng>(
+    network: &mut N,
+    p_share: &BigUint,
+    q_share: &BigUint,
+    field: &BigUint,
+    rng: &mut R,
+) -> Result<[BigUint; 3]> {
+    let (index, parties) = (network.index(), network.parties());
+    let degree = (parties - 1) / 2;
+    let field_len = field.bits().div_ceil(8) as usize;
+
+    let mut polynomial = |secret: &BigUint, degree: usize| {
+        let mut coefficients = vec![secret.clone()];
+        coefficients.extend((0..degree).map(|_| rng.gen_biguint_below(field)));
+        coefficients
+    };
+    let polynomials = [
+        polynomial(p_share, degree),
+        polynomial(q_share, degree),
+        polynomial(&BigUint::zero(), 2 * degree),
+    ];
+    let evaluate = |peer: usize| -> Vec<u8> {
+        let x = BigUint::from(peer);
+        polynomials
+            .iter()
+            .flat_map(|coefficients| to_fixed_bytes(&evaluate_polynomial(coefficients, &x, field), field_len))
+            .collect()
+    };
+    for peer in (1..=parties).filter(|&peer| peer != index) {
+        network.send(peer, &evaluate(peer))?;
+    }
+    let mut sums = [BigUint::zero(), BigUint::zero(), BigUint::zero()];
+    for peer in 1..=parties {
+        let message = if peer == index { evaluate(index) } else { network.receive(peer)? };
+        for (sum, value) in sums.iter_mut().zip(decode_elements(&message, 3, field_len)?) {
+            *sum += value;
+        }
+    }
+    Ok(sums.map(|sum| sum % field))
+}
+
+/// Returns the point a party broadcasts in the BGW step, `P(j)·Q(j) + H(j)`
+///
+/// The points lie on a polynomial of degree `2t < N` with constant term `p·q`.
+/// Without `H` they would determine `P·Q`, whose factorisation into the
+/// degree-`t` polynomials `P` and `Q` reveals `p = P(0)` and `q = Q(0)`; `H`
+/// makes the polynomial random apart from its constant term.
+fn masked_product([p_value, q_value, zero_value]: &[BigUint; 3], field: &BigUint) -> BigUint {
+    (p_value * q_value + zero_value) % field
+}
+
+/// Runs the protocol for a `bits`-bit modulus without checking the key size
+fn run<D: HashFunction, N: Network>(bits: usize, network: &mut N) -> Result<(PublicKey<D>, BiprimeShare)> {
+    let (index, parties) = (network.index(), network.parties());
+    if parties < MIN_PARTIES {
+        return Err(RabinWilliamsError::NotEnoughParties);
+    }
+    if !bits.is_multiple_of(2) || bits < 64 {
+        return Err(RabinWilliamsError::InvalidKeySize);
+    }
+    let half_bits = bits / 2;
+
+    // Every product of shares is below 2^bits, so the field recovers n exactly
+    let field = next_prime(&(BigUint::one() << bits), Some(PrimalityTestConfig::default()))
+        .ok_or(RabinWilliamsError::ComputationError)?;
+    let field_len = field.bits().div_ceil(8) as usize;
+    let n_len = bits.div_ceil(8);
+    let primorial = primes(TRIAL_DIVISION_BOUND)
+        .into_iter()
+        .skip(1)
+        .fold(BigUint::one(), |product, prime| product * prime);
+    let xs: Vec<BigUint> = (1..=parties).map(BigUint::from).collect();
+
+    let mut rng = rand::thread_rng();
+    loop {
+        let p_share = pick_prime_share(index, parties, half_bits, 3);
+        let q_share = pick_prime_share(index, parties, half_bits, 7);
+
+        // BGW multiplication of the summed shares
+        let product = masked_product(&share_factors(network, &p_share, &q_share, &field, &mut rng)?, &field);
+        let points = broadcast(network, &to_fixed_bytes(&product, field_len))?
+            .iter()
+            .zip(&xs)
+            .map(|(message, x)| Ok((x.clone(), decode_elements(message, 1, field_len)?.remove(0))))
+            .collect::<Result<Vec<_>>>()?;
+        let n = interpolate_at_zero(&points, &field)?;
+        if n.bits() as usize != bits || !(&primorial % &n).gcd(&n).is_one() {
+            continue;
+        }
+
+        // Biprimality test; every party sees the same values, so all agree on the outcome
+        let exponent = if index == 1 {
+            (&n + 1u32 - &p_share - &q_share) / 4u32
+        } else {
+            (&p_share + &q_share) / 4u32
+        };
+        let mut biprime = true;
+        for round in 0..BIPRIMALITY_ROUNDS {
+            let g = biprimality_base::<D>(&n, round);
+            let v = g.modpow(&exponent, &n);
+            let values = broadcast(network, &to_fixed_bytes(&v, n_len))?
+                .iter()
+                .map(|message| Ok(decode_elements(message, 1, n_len)?.remove(0)))
+                .collect::<Result<Vec<_>>>()?;
+            let others = values[1..].iter().fold(BigUint::one(), |product, v| product * v % &n);
+            if others != values[0] && (&others + &values[0]) % &n != BigUint::zero() {
+                biprime = false;
+                break;
+            }
+        }
+        if biprime {
+            let share = BiprimeShare { index, n: n.clone(), p_share, q_share };
+            return Ok((PublicKey::from_n(n), share));
+        }
+    }
+}
+
+/// Generates a `bits`-bit modulus jointly with the other parties on `network`
+///
+/// Every party calls this with the same `bits` and gets the same public key and
+/// its own share. Fails with `NotEnoughParties` for fewer than [`MIN_PARTIES`]
+/// parties and with `InvalidKeySize` for keys below 1024 bits.
+pub fn generate_distributed<D: HashFunction, N: Network>(
+    bits: usize,
+    network: &mut N,
+) -> Result<(PublicKey<D>, BiprimeShare)> {
+    if bits < 1024 {
+        return Err(RabinWilliamsError::InvalidKeySize);
+    }
+    run(bits, network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::PrivateKey;
+    use num_prime::nt_funcs::is_prime;
+    use sha2::Sha256;
+
+    /// Runs every party in its own thread and returns their results in index order
+    fn run_parties<N: Network + Send + 'static>(
+        bits: usize,
+        networks: Vec<N>,
+    ) -> Result<Vec<(PublicKey<Sha256>, BiprimeShare)>> {
+        let threads: Vec<_> = networks
+            .into_iter()
+            .map(|mut network| std::thread::spawn(move || run::<Sha256, N>(bits, &mut network)))
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().map_err(|_| RabinWilliamsError::ComputationError)?)
+            .collect()
+    }
+
+    fn check_shares(results: &[(PublicKey<Sha256>, BiprimeShare)], bits: usize) -> Result<()> {
+        let public_key = &results[0].0;
+        let n = public_key.n();
+        assert_eq!(n.bits() as usize, bits);
+        assert!(results.iter().all(|(key, share)| key.n() == n && share.n() == n));
+
+        // The shares add up to two primes that are 3 and 7 (mod 8)
+        let p: BigUint = results.iter().map(|(_, share)| &share.p_share).sum();
+        let q: BigUint = results.iter().map(|(_, share)| &share.q_share).sum();
+        assert_eq!(&(&p * &q), n);
+        assert_eq!((&p % 8u32, &q % 8u32), (BigUint::from(3u32), BigUint::from(7u32)));
+        assert!(is_prime(&p, None).probably() && is_prime(&q, None).probably());
+
+        // The parties sign together, without the factors, exactly as the key would
+        let message = b"consortium statement";
+        let partials = results
+            .iter()
+            .map(|(_, share)| share.partial_sign(public_key, message))
+            .collect::<Result<Vec<_>>>()?;
+        let signature = combine_partial_signatures(public_key, message, &partials)?;
+        assert_eq!(signature, PrivateKey::<Sha256>::from_primes(p, q).sign(message)?);
+
+        // Every party is needed
+        assert!(combine_partial_signatures(public_key, message, &partials[1..]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_process_generation() -> Result<()> {
+        // 384-bit moduli keep the tests quick while still fitting SHA-256 hashes below n
+        let results = run_parties(384, in_process_networks(3))?;
+        check_shares(&results, 384)
+    }
+
+    #[test]
+    fn test_tcp_generation() -> Result<()> {
+        let listeners = (0..4)
+            .map(|_| TcpListener::bind("127.0.0.1:0").map_err(|_| RabinWilliamsError::TransportError))
+            .collect::<Result<Vec<_>>>()?;
+        let addresses = listeners
+            .iter()
+            .map(|listener| listener.local_addr().map_err(|_| RabinWilliamsError::TransportError))
+            .collect::<Result<Vec<_>>>()?;
+
+        let connecting: Vec<_> = listeners
+            .into_iter()
+            .enumerate()
+            .map(|(i, listener)| {
+                let addresses = addresses.clone();
+                std::thread::spawn(move || TcpNetwork::connect(i + 1, listener, &addresses))
+            })
+            .collect();
+        let networks = connecting
+            .into_iter()
+            .map(|thread| thread.join().map_err(|_| RabinWilliamsError::ComputationError)?)
+            .collect::<Result<Vec<_>>>()?;
+
+        let results = run_parties(384, networks)?;
+        check_shares(&results, 384)
+    }
+
+    #[test]
+    fn test_broadcast_products_are_masked() -> Result<()> {
+        let field: BigUint = next_prime(&(BigUint::one() << 384), None).ok_or(RabinWilliamsError::ComputationError)?;
+        let shares: Vec<_> = (1..=5)
+            .map(|index| (pick_prime_share(index, 5, 192, 3), pick_prime_share(index, 5, 192, 7)))
+            .collect();
+        let threads: Vec<_> = in_process_networks(5)
+            .into_iter()
+            .zip(shares.clone())
+            .map(|(mut network, (p_share, q_share))| {
+                let field = field.clone();
+                std::thread::spawn(move || {
+                    share_factors(&mut network, &p_share, &q_share, &field, &mut rand::thread_rng())
+                })
+            })
+            .collect();
+        let values = threads
+            .into_iter()
+            .map(|thread| thread.join().map_err(|_| RabinWilliamsError::ComputationError)?)
+            .collect::<Result<Vec<_>>>()?;
+        let points = |f: &dyn Fn(&[BigUint; 3]) -> BigUint| -> Vec<(BigUint, BigUint)> {
+            values.iter().enumerate().map(|(i, value)| (BigUint::from(i + 1), f(value))).collect()
+        };
+
+        // The broadcast points still interpolate to n = p·q
+        let p: BigUint = shares.iter().map(|(p_share, _)| p_share).sum();
+        let q: BigUint = shares.iter().map(|(_, q_share)| q_share).sum();
+        let broadcast = points(&|value| masked_product(value, &field));
+        assert_eq!(interpolate_at_zero(&broadcast, &field)?, &p * &q);
+
+        // but are not the products P(j)·Q(j) of the shared factor polynomials
+        let products = points(&|[p_value, q_value, _]| p_value * q_value % &field);
+        assert_eq!(interpolate_at_zero(&products, &field)?, &p * &q);
+        assert!(broadcast.iter().zip(&products).all(|(masked, product)| masked != product));
+
+        // and the mask is a sharing of zero
+        let zero = points(&|[_, _, zero_value]| zero_value.clone());
+        assert!(interpolate_at_zero(&zero, &field)?.is_zero());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_bad_parameters() {
+        let mut networks = in_process_networks(2);
+        let result = run::<Sha256, _>(256, &mut networks[0]);
+        assert!(matches!(result, Err(RabinWilliamsError::NotEnoughParties)));
+
+        let mut networks = in_process_networks(3);
+        let result = generate_distributed::<Sha256, _>(512, &mut networks[0]);
+        assert!(matches!(result, Err(RabinWilliamsError::InvalidKeySize)));
+    }
+}
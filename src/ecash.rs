@@ -0,0 +1,373 @@
+//! Chaumian e-cash built on blind signatures
+//!
+//! The mint holds one key pair per denomination, so the key that signed a coin
+//! fixes its value.
+//!
+//! - **Withdrawal**: the client draws a random serial number, blinds it under the
+//!   denomination's public key (`MintPublicKeys::withdraw`), the mint signs it
+//!   blindly (`Mint::withdraw`) and the client unblinds the result into a [`Coin`].
+//! - **Spending**: the client hands the coin to a merchant, who checks it with
+//!   `MintPublicKeys::verify_coin`.
+//! - **Deposit**: the merchant returns the coin to the mint (`Mint::deposit`),
+//!   which verifies it and records its serial in a double-spend ledger.
+//!
+//! The mint never sees a serial number before deposit, so it cannot link a
+//! deposited coin to the withdrawal that produced it. Serials are signed under
+//! the context string `COIN_CONTEXT`.
+//!
+//! **This design is insecure against malicious clients.** `Mint::withdraw` is
+//! a blind signer, so any client can factor a denomination's modulus (see
+//! [`crate::blind`]) and then mint coins of that denomination at will.
+
+use crate::blind::{BlindSignature, BlindedMessage, BlindingRequest, BlindingState};
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{HashFunction, HashWrapper};
+use crate::keys::{KeyPair, PrivateKey, PublicKey};
+use rand::RngCore;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Context string that coin serials are signed under
+pub const COIN_CONTEXT: &[u8] = b"rabin-williams ecash coin";
+
+/// Length of a coin serial number in bytes
+pub const SERIAL_LEN: usize = 32;
+
+/// A coin serial number
+pub type Serial = [u8; SERIAL_LEN];
+
+/// A coin: a serial number signed under the key of its denomination
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coin {
+    pub denomination: u64,
+    pub serial: Serial,
+    pub signature: Vec<u8>,
+}
+
+impl Coin {
+    /// Encodes the coin as its 8-byte big-endian denomination, serial and signature
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.denomination.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.serial);
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+
+    /// Decodes a coin produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 8 + SERIAL_LEN + 2 {
+            return Err(RabinWilliamsError::InvalidCoinEncoding);
+        }
+        let (denomination, rest) = bytes.split_at(8);
+        let (serial, signature) = rest.split_at(SERIAL_LEN);
+        Ok(Self {
+            denomination: u64::from_be_bytes(denomination.try_into().map_err(|_| RabinWilliamsError::InvalidCoinEncoding)?),
+            serial: serial.try_into().map_err(|_| RabinWilliamsError::InvalidCoinEncoding)?,
+            signature: signature.to_vec(),
+        })
+    }
+}
+
+/// A withdrawal request sent from the client to the mint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WithdrawalRequest {
+    pub denomination: u64,
+    pub blinded_message: BlindedMessage,
+}
+
+/// Client-side state for one withdrawal
+#[derive(Clone, Debug)]
+pub struct Withdrawal<D: HashFunction> {
+    denomination: u64,
+    serial: Serial,
+    state: BlindingState<D>,
+}
+
+impl<D: HashFunction> Withdrawal<D> {
+    /// Unblinds the mint's response into a coin
+    pub fn finalize(self, blind_signature: &BlindSignature) -> Result<Coin> {
+        let signature = self.state.finalize(blind_signature)?;
+        Ok(Coin {
+            denomination: self.denomination,
+            serial: self.serial,
+            signature,
+        })
+    }
+}
+
+/// The mint's public keys, one per denomination
+#[derive(Clone, Debug)]
+pub struct MintPublicKeys<D: HashFunction> {
+    keys: BTreeMap<u64, PublicKey<D>>,
+}
+
+impl<D: HashFunction> MintPublicKeys<D> {
+    pub fn new(keys: BTreeMap<u64, PublicKey<D>>) -> Self {
+        Self { keys }
+    }
+
+    /// Returns the denominations the mint issues, in ascending order
+    pub fn denominations(&self) -> impl Iterator<Item = u64> + '_ {
+        self.keys.keys().copied()
+    }
+
+    /// Returns the public key for a denomination
+    pub fn key(&self, denomination: u64) -> Result<&PublicKey<D>> {
+        self.keys.get(&denomination).ok_or(RabinWilliamsError::UnknownDenomination(denomination))
+    }
+
+    /// Starts withdrawing a coin of the given denomination
+    pub fn withdraw(&self, denomination: u64) -> Result<(WithdrawalRequest, Withdrawal<D>)> {
+        let mut serial = [0u8; SERIAL_LEN];
+        rand::thread_rng().fill_bytes(&mut serial);
+
+        let request = BlindingRequest::new(&serial).with_context(COIN_CONTEXT);
+        let (blinded_message, state) = self.key(denomination)?.blind(request);
+        Ok((
+            WithdrawalRequest { denomination, blinded_message },
+            Withdrawal { denomination, serial, state },
+        ))
+    }
+
+    /// Checks that a coin carries a valid signature for its denomination
+    ///
+    /// This does not check whether the coin was already spent; only the mint
+    /// can tell, at deposit.
+    pub fn verify_coin(&self, coin: &Coin) -> Result<bool> {
+        self.key(coin.denomination)?.verify_with_context(COIN_CONTEXT, &coin.serial, &coin.signature)
+    }
+}
+
+/// Records which coin serials have been deposited
+pub trait SpendStore {
+    /// Returns true if `serial` has been recorded as spent
+    fn is_spent(&self, serial: &Serial) -> Result<bool>;
+
+    /// Records `serial` as spent, returning false if it was already spent
+    fn mark_spent(&mut self, serial: &Serial) -> Result<bool>;
+}
+
+/// A spend store held in memory
+#[derive(Clone, Debug, Default)]
+pub struct MemorySpendStore {
+    spent: HashSet<Serial>,
+}
+
+impl MemorySpendStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SpendStore for MemorySpendStore {
+    fn is_spent(&self, serial: &Serial) -> Result<bool> {
+        Ok(self.spent.contains(serial))
+    }
+
+    fn mark_spent(&mut self, serial: &Serial) -> Result<bool> {
+        Ok(self.spent.insert(*serial))
+    }
+}
+
+/// A spend store backed by an append-only file of hex-encoded serials, one per line
+#[derive(Debug)]
+pub struct FileSpendStore {
+    path: PathBuf,
+    spent: HashSet<Serial>,
+}
+
+impl FileSpendStore {
+    /// Opens the store at `path`, creating it if it does not exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let spent = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                hex::decode(line.trim())
+                    .ok()
+                    .and_then(|bytes| Serial::try_from(bytes.as_slice()).ok())
+                    .ok_or(RabinWilliamsError::InvalidCoinEncoding)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { path, spent })
+    }
+
+    fn append(&self, serial: &Serial) -> std::io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", hex::encode(serial))?;
+        file.sync_data()
+    }
+}
+
+impl SpendStore for FileSpendStore {
+    fn is_spent(&self, serial: &Serial) -> Result<bool> {
+        Ok(self.spent.contains(serial))
+    }
+
+    fn mark_spent(&mut self, serial: &Serial) -> Result<bool> {
+        if self.spent.contains(serial) {
+            return Ok(false);
+        }
+        // Persist before acknowledging, so a crash cannot forget a deposit
+        self.append(serial)?;
+        self.spent.insert(*serial);
+        Ok(true)
+    }
+}
+
+/// The mint: signs withdrawals and accepts each coin for deposit once
+#[derive(Debug)]
+pub struct Mint<D: HashFunction, S: SpendStore> {
+    signers: BTreeMap<u64, PrivateKey<D>>,
+    public_keys: MintPublicKeys<D>,
+    ledger: S,
+}
+
+impl<D: HashFunction, S: SpendStore> Mint<D, S> {
+    /// Creates a mint from one private key per denomination and a double-spend ledger
+    pub fn new(keys: BTreeMap<u64, PrivateKey<D>>, ledger: S) -> Result<Self> {
+        let public_keys = keys
+            .iter()
+            .map(|(&denomination, key)| (denomination, PublicKey::from_n(key.n())))
+            .collect();
+        Ok(Self {
+            signers: keys,
+            public_keys: MintPublicKeys::new(public_keys),
+            ledger,
+        })
+    }
+
+    /// Generates a mint with a fresh `bits`-bit key pair for each denomination
+    pub fn generate(bits: usize, denominations: &[u64], ledger: S) -> Result<Self> {
+        let keys = denominations
+            .iter()
+            .map(|&denomination| Ok((denomination, KeyPair::<D>::generate_with_hash(bits, HashWrapper::default())?.private)))
+            .collect::<Result<_>>()?;
+        Self::new(keys, ledger)
+    }
+
+    /// Returns the public keys clients and merchants need
+    pub fn public_keys(&self) -> &MintPublicKeys<D> {
+        &self.public_keys
+    }
+
+    /// Signs a withdrawal request blindly
+    ///
+    /// The mint cannot tell a blinded serial from a value chosen to extract a
+    /// square root, so any caller can factor the denomination's key (see the
+    /// module documentation).
+    pub fn withdraw(&self, request: &WithdrawalRequest) -> Result<BlindSignature> {
+        self.signers
+            .get(&request.denomination)
+            .ok_or(RabinWilliamsError::UnknownDenomination(request.denomination))?
+            .blind_sign(&request.blinded_message)
+    }
+
+    /// Accepts a coin for deposit, returning its denomination
+    ///
+    /// Fails with `InvalidSignature` for forged coins and with
+    /// `CoinAlreadyDeposited` if the coin's serial is already in the ledger.
+    pub fn deposit(&mut self, coin: &Coin) -> Result<u64> {
+        if !self.public_keys.verify_coin(coin)? {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+        if !self.ledger.mark_spent(&coin.serial)? {
+            return Err(RabinWilliamsError::CoinAlreadyDeposited);
+        }
+        Ok(coin.denomination)
+    }
+
+    /// Returns the double-spend ledger
+    pub fn ledger(&self) -> &S {
+        &self.ledger
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha256;
+
+    fn withdraw(mint: &mut Mint<Sha256, MemorySpendStore>, denomination: u64) -> Result<Coin> {
+        let (request, withdrawal) = mint.public_keys().withdraw(denomination)?;
+        let response = mint.withdraw(&request)?;
+        withdrawal.finalize(&response)
+    }
+
+    #[test]
+    fn test_withdraw_spend_deposit() -> Result<()> {
+        let mut mint = Mint::<Sha256, _>::generate(1024, &[1, 5], MemorySpendStore::new())?;
+        assert_eq!(mint.public_keys().denominations().collect::<Vec<_>>(), vec![1, 5]);
+
+        let coin = withdraw(&mut mint, 5)?;
+        let coin = Coin::from_bytes(&coin.to_bytes())?;
+        assert!(mint.public_keys().verify_coin(&coin)?);
+
+        assert_eq!(mint.deposit(&coin)?, 5);
+        assert!(matches!(mint.deposit(&coin), Err(RabinWilliamsError::CoinAlreadyDeposited)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_denomination_is_bound_to_key() -> Result<()> {
+        let mut mint = Mint::<Sha256, _>::generate(1024, &[1, 5], MemorySpendStore::new())?;
+
+        // Relabeling a 1-unit coin as 5 units makes it invalid
+        let mut coin = withdraw(&mut mint, 1)?;
+        coin.denomination = 5;
+        assert!(!mint.public_keys().verify_coin(&coin)?);
+        assert!(matches!(mint.deposit(&coin), Err(RabinWilliamsError::InvalidSignature)));
+
+        // A forged coin is not recorded in the ledger
+        assert!(!mint.ledger().is_spent(&coin.serial)?);
+
+        coin.denomination = 1;
+        assert_eq!(mint.deposit(&coin)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_spend_store_persists() -> Result<()> {
+        let mut mint = Mint::<Sha256, _>::generate(1024, &[1], MemorySpendStore::new())?;
+        let first = withdraw(&mut mint, 1)?;
+        let second = withdraw(&mut mint, 1)?;
+
+        let path = std::env::temp_dir().join(format!("rw-spent-{}.txt", hex::encode(first.serial)));
+        {
+            let mut ledger = FileSpendStore::open(&path)?;
+            assert!(ledger.mark_spent(&first.serial)?);
+        }
+
+        // Reopening the same file still knows the spent serial
+        let mut ledger = FileSpendStore::open(&path)?;
+        let spent = ledger.is_spent(&first.serial)?;
+        let marked = ledger.mark_spent(&second.serial)?;
+        fs::remove_file(&path).ok();
+
+        assert!(spent);
+        assert!(marked);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_denomination() -> Result<()> {
+        let mint = Mint::<Sha256, _>::generate(1024, &[1], MemorySpendStore::new())?;
+
+        assert!(matches!(mint.public_keys().withdraw(2), Err(RabinWilliamsError::UnknownDenomination(2))));
+        assert!(Coin::from_bytes(&[0u8; 8 + SERIAL_LEN]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//! Rabin encryption with SAEP+ padding
+//!
+//! Encryption squares a padded message modulo `n`; decryption takes the four
+//! square roots with the private primes and keeps the one whose padding checks
+//! out. The padding is Boneh's SAEP+, which makes the scheme secure against
+//! chosen-ciphertext attacks and leaves exactly one valid root:
+//!
+//! ```text
+//! x = ((m || 0x80 || 0x00..) || H(m || 0x80 || 0x00.. || r)) XOR G(r) || r
+//! ```
+//!
+//! where `r` is 32 random bytes, `H` produces 32 bytes and both `G` and `H` are
+//! MGF1 over the key's hash function. `x` is kept below `2^(k-2)` for a `k`-bit
+//! modulus, so it is always smaller than `n / 2`.
+//!
+//! Only keys whose `KeyUsage` allows encryption can encrypt and decrypt.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{mgf1, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use num_bigint::BigUint;
+use rand::RngCore;
+
+/// Length of the SAEP+ randomness in bytes
+const RANDOM_LEN: usize = 32;
+
+/// Length of the SAEP+ redundancy tag in bytes
+const TAG_LEN: usize = 32;
+
+/// Domain separators for the two SAEP+ hash functions
+const G_DOMAIN: &[u8] = &[0x00];
+const H_DOMAIN: &[u8] = &[0x01];
+
+/// Length in bytes of the padded value x for a modulus of `modulus_bits` bits
+fn padded_len(modulus_bits: usize) -> usize {
+    modulus_bits.saturating_sub(2) / 8
+}
+
+/// Length of the message block, which holds the message and its 0x80 terminator
+fn block_len(modulus_bits: usize) -> Result<usize> {
+    padded_len(modulus_bits)
+        .checked_sub(RANDOM_LEN + TAG_LEN)
+        .filter(|&len| len > 0)
+        .ok_or(RabinWilliamsError::InvalidKeySize)
+}
+
+/// The SAEP+ redundancy tag binding the message block to the randomness
+fn tag<D: HashFunction>(block: &[u8], r: &[u8]) -> Vec<u8> {
+    mgf1::<D>(&[H_DOMAIN, block, r].concat(), TAG_LEN)
+}
+
+fn saep_encode<D: HashFunction>(message: &[u8], r: &[u8; RANDOM_LEN], modulus_bits: usize) -> Result<BigUint> {
+    let block_len = block_len(modulus_bits)?;
+    if message.len() >= block_len {
+        return Err(RabinWilliamsError::MessageTooLarge);
+    }
+
+    let mut v = message.to_vec();
+    v.push(0x80);
+    v.resize(block_len, 0x00);
+    let t = tag::<D>(&v, r);
+    v.extend_from_slice(&t);
+
+    let mask = mgf1::<D>(&[G_DOMAIN, r].concat(), v.len());
+    let mut x: Vec<u8> = v.iter().zip(mask).map(|(byte, mask)| byte ^ mask).collect();
+    x.extend_from_slice(r);
+    Ok(BigUint::from_bytes_be(&x))
+}
+
+/// Returns the message if `x` is a valid SAEP+ encoding
+fn saep_decode<D: HashFunction>(x: &BigUint, modulus_bits: usize) -> Option<Vec<u8>> {
+    let padded_len = padded_len(modulus_bits);
+    let block_len = block_len(modulus_bits).ok()?;
+    if x.bits() as usize > 8 * padded_len {
+        return None;
+    }
+
+    let bytes = x.to_bytes_be();
+    let mut padded = vec![0u8; padded_len - bytes.len()];
+    padded.extend_from_slice(&bytes);
+
+    let (masked, r) = padded.split_at(padded_len - RANDOM_LEN);
+    let mask = mgf1::<D>(&[G_DOMAIN, r].concat(), masked.len());
+    let v: Vec<u8> = masked.iter().zip(mask).map(|(byte, mask)| byte ^ mask).collect();
+    let (block, t) = v.split_at(block_len);
+    if tag::<D>(block, r) != t {
+        return None;
+    }
+
+    let end = block.iter().rposition(|&byte| byte != 0x00)?;
+    (block[end] == 0x80).then(|| block[..end].to_vec())
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Encrypts a short message to this key
+    ///
+    /// The message must be shorter than the key size in bytes minus 65 (62
+    /// bytes for a 1024-bit key); use `kem::seal` for longer data. The
+    /// ciphertext is as long as the modulus.
+    pub fn encrypt(&self, message: &[u8]) -> Result<Vec<u8>> {
+        self.require_encryption()?;
+
+        let modulus_bits = self.n().bits() as usize;
+        let mut r = [0u8; RANDOM_LEN];
+        rand::thread_rng().fill_bytes(&mut r);
+        let x = saep_encode::<D>(message, &r, modulus_bits)?;
+
+        let c = (&x * &x % self.n()).to_bytes_be();
+        let mut ciphertext = vec![0u8; modulus_bits.div_ceil(8) - c.len()];
+        ciphertext.extend_from_slice(&c);
+        Ok(ciphertext)
+    }
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Returns the four square roots of `c` modulo n
+    ///
+    /// Fails with `InvalidCiphertext` if `c` is not a square modulo both primes.
+    pub(crate) fn square_roots(&self, c: &BigUint) -> Result<[BigUint; 4]> {
+        // Both primes are 3 (mod 4), so the CRT exponents give square roots directly
+        let params = self.crt_params()?;
+        let (sp, sq) = if self.side_channel_blinding {
+            // Unblinding leaves the square of each root unchanged, so the check
+            // below still rejects non-squares
+            let pair = self.blinding_pair()?;
+            let c = c * &pair.r_squared % self.n();
+            (
+                self.prime_modpow(&(&c % &self.p), &params.p_exp, &self.p) * &pair.unblind_p % &self.p,
+                self.prime_modpow(&(&c % &self.q), &params.q_exp, &self.q) * &pair.unblind_q % &self.q,
+            )
+        } else {
+            ((c % &self.p).modpow(&params.p_exp, &self.p), (c % &self.q).modpow(&params.q_exp, &self.q))
+        };
+        if &sp * &sp % &self.p != c % &self.p || &sq * &sq % &self.q != c % &self.q {
+            return Err(RabinWilliamsError::InvalidCiphertext);
+        }
+
+        let neg_sp = (&self.p - &sp) % &self.p;
+        let neg_sq = (&self.q - &sq) % &self.q;
+        Ok([
+            self.crt_combine(&sp, &sq, &params),
+            self.crt_combine(&sp, &neg_sq, &params),
+            self.crt_combine(&neg_sp, &sq, &params),
+            self.crt_combine(&neg_sp, &neg_sq, &params),
+        ])
+    }
+
+    /// Decrypts a ciphertext produced by `PublicKey::encrypt`
+    ///
+    /// Fails with `InvalidCiphertext` if the ciphertext was not produced for
+    /// this key or has been modified.
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.require_encryption()?;
+
+        let n = self.n();
+        let modulus_bits = n.bits() as usize;
+        let c = BigUint::from_bytes_be(ciphertext);
+        if ciphertext.len() != modulus_bits.div_ceil(8) || c >= n {
+            return Err(RabinWilliamsError::InvalidCiphertext);
+        }
+
+        // Try all four roots so the work done does not depend on which one is valid
+        let mut valid: Vec<Vec<u8>> = self
+            .square_roots(&c)?
+            .iter()
+            .filter_map(|root| saep_decode::<D>(root, modulus_bits))
+            .collect();
+
+        match valid.len() {
+            1 => Ok(valid.remove(0)),
+            _ => Err(RabinWilliamsError::InvalidCiphertext),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeyPair, KeyUsage};
+    use sha2::Sha256;
+
+    fn encryption_keys() -> Result<KeyPair<Sha256>> {
+        Ok(KeyPair::generate(1024)?.with_usage(KeyUsage::Encryption))
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() -> Result<()> {
+        let key_pair = encryption_keys()?;
+
+        for message in [&b""[..], b"Hello, World!", &[0x80; 62], &[0x00; 62]] {
+            let ciphertext = key_pair.public.encrypt(message)?;
+            assert_eq!(ciphertext.len(), 128);
+            assert_eq!(key_pair.private.decrypt(&ciphertext)?, message);
+        }
+
+        // Encryption is randomized
+        assert_ne!(key_pair.public.encrypt(b"same")?, key_pair.public.encrypt(b"same")?);
+
+        assert!(matches!(key_pair.public.encrypt(&[0u8; 63]), Err(RabinWilliamsError::MessageTooLarge)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_modified_ciphertexts() -> Result<()> {
+        let key_pair = encryption_keys()?;
+        let ciphertext = key_pair.public.encrypt(b"Hello, World!")?;
+
+        let mut modified = ciphertext.clone();
+        modified[100] ^= 1;
+        assert!(matches!(key_pair.private.decrypt(&modified), Err(RabinWilliamsError::InvalidCiphertext)));
+
+        // Ciphertexts must be exactly as long as the modulus and smaller than it
+        assert!(key_pair.private.decrypt(&ciphertext[1..]).is_err());
+        let n = key_pair.public.n().to_bytes_be();
+        assert!(key_pair.private.decrypt(&n).is_err());
+
+        // A ciphertext for another key does not decrypt
+        let other = encryption_keys()?;
+        assert!(other.private.decrypt(&ciphertext).is_err());
+
+        // Blinded decryption accepts and rejects the same ciphertexts
+        let blinded = key_pair.private.clone().with_side_channel_blinding(true);
+        assert_eq!(blinded.decrypt(&ciphertext)?, b"Hello, World!");
+        assert!(matches!(blinded.decrypt(&modified), Err(RabinWilliamsError::InvalidCiphertext)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_usage_separation() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        // Keys are signing-only by default
+        assert!(matches!(key_pair.public.encrypt(b"secret"), Err(RabinWilliamsError::KeyUsageNotAllowed)));
+
+        // Encryption-only keys cannot sign or verify
+        let encryption = key_pair.clone().with_usage(KeyUsage::Encryption);
+        let signature = key_pair.private.sign(b"message")?;
+        assert!(matches!(encryption.private.sign(b"message"), Err(RabinWilliamsError::KeyUsageNotAllowed)));
+        assert!(matches!(encryption.public.verify(b"message", &signature), Err(RabinWilliamsError::KeyUsageNotAllowed)));
+
+        // Both uses must be allowed explicitly
+        let both = key_pair.with_usage(KeyUsage::SigningAndEncryption);
+        let ciphertext = both.public.encrypt(b"secret")?;
+        assert_eq!(both.private.decrypt(&ciphertext)?, b"secret");
+        assert!(both.public.verify(b"message", &both.private.sign(b"message")?)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_saep_decode_rejects_bad_padding() {
+        let r = [7u8; RANDOM_LEN];
+        let x = saep_encode::<Sha256>(b"message", &r, 1024).unwrap();
+        assert_eq!(saep_decode::<Sha256>(&x, 1024).unwrap(), b"message");
+
+        // Flipping any bit breaks the redundancy tag
+        for bit in [0u64, 300, 700, 1000] {
+            let mut flipped = x.clone();
+            flipped.set_bit(bit, !x.bit(bit));
+            assert!(saep_decode::<Sha256>(&flipped, 1024).is_none());
+        }
+
+        // Values too wide for the encoding are rejected
+        assert!(saep_decode::<Sha256>(&(BigUint::from(1u32) << 1016), 1024).is_none());
+    }
+}
@@ -19,6 +19,108 @@ pub enum RabinWilliamsError {
     
     #[error("Internal computation error")]
     ComputationError,
+    
+    #[error("Batch is empty")]
+    EmptyBatch,
+    
+    #[error("Invalid Merkle proof encoding")]
+    InvalidProof,
+    
+    #[error("Invalid blinded message")]
+    InvalidBlindedMessage,
+    
+    #[error("Unknown denomination {0}")]
+    UnknownDenomination(u64),
+    
+    #[error("Invalid coin encoding")]
+    InvalidCoinEncoding,
+    
+    #[error("Coin has already been deposited")]
+    CoinAlreadyDeposited,
+    
+    #[error("Key usage does not allow this operation")]
+    KeyUsageNotAllowed,
+    
+    #[error("Invalid ciphertext")]
+    InvalidCiphertext,
+    
+    #[error("Unknown key usage: {0}")]
+    UnknownKeyUsage(String),
+    
+    #[error("Invalid modulus proof")]
+    InvalidModulusProof,
+    
+    #[error("Signing key is not a member of the ring")]
+    SignerNotInRing,
+    
+    #[error("Blinding pool belongs to another key")]
+    BlindingPoolMismatch,
+    
+    #[error("Threshold must be between 1 and the number of shares")]
+    InvalidThreshold,
+    
+    #[error("Invalid key share")]
+    InvalidShare,
+    
+    #[error("Key shares belong to another key or do not match each other")]
+    ShareMismatch,
+    
+    #[error("Not enough key shares to recover the key")]
+    NotEnoughShares,
+    
+    #[error("Partial signature from party {0} is invalid")]
+    InvalidPartialSignature(u8),
+    
+    #[error("Transport error")]
+    TransportError,
+    
+    #[error("Distributed key generation needs at least 3 parties")]
+    NotEnoughParties,
+    
+    #[error("Invalid key fingerprint")]
+    InvalidFingerprint,
+    
+    #[error("Key store could not be read or written")]
+    KeyStoreError,
+    
+    #[error("Invalid key metadata in key store")]
+    InvalidKeyMetadata,
+    
+    #[error("Key names may only contain ASCII letters, digits, '.', '_' and '-', and may not look like a fingerprint prefix")]
+    InvalidKeyName,
+    
+    #[error("No key with this name or fingerprint")]
+    KeyNotFound,
+    
+    #[error("More than one key matches this fingerprint")]
+    AmbiguousKey,
+    
+    #[error("A key with this name or fingerprint is already in the key store")]
+    DuplicateKey,
+    
+    #[error("Key store holds only the public key")]
+    NoPrivateKey,
+    
+    #[error("Private key does not match the public key")]
+    KeyMismatch,
+    
+    #[error("Key was stored for a different hash function")]
+    WrongHashFunction,
+    
+    #[error("Invalid signature container")]
+    InvalidSignatureContainer,
+    
+    #[error("Unsupported signature container version {0}")]
+    UnsupportedContainerVersion(u8),
+    
+    #[error("Key file could not be read or written")]
+    KeyFileError,
+    
+    #[error("Invalid key file")]
+    InvalidKeyFile,
+    
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, RabinWilliamsError>;
@@ -0,0 +1,137 @@
+//! Message encoders for Ethereum-native verification
+//!
+//! These produce the exact byte strings that Ethereum contracts hash with
+//! `keccak256`. Signing an encoded message with a [`Keccak256Hash`](crate::hash::Keccak256Hash)
+//! key therefore signs the same digest a contract already computes, and the
+//! signature can be checked on-chain with `RabinWilliamsVerifier.verifyDigest`.
+
+use sha3::{Digest, Keccak256};
+
+/// Prefix for EIP-191 version 0x45 ("personal_sign") messages
+const EIP191_PERSONAL_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
+
+/// Prefix for EIP-191 version 0x01 (EIP-712 structured data) messages
+const EIP712_PREFIX: &[u8] = b"\x19\x01";
+
+/// Type string of the EIP-712 domain encoded by [`Eip712Domain`]
+const EIP712_DOMAIN_TYPE: &[u8] =
+    b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// Computes keccak256 of `data`
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// Encodes a message as an EIP-191 personal message
+///
+/// The result is `"\x19Ethereum Signed Message:\n" || len(message) || message`,
+/// where the length is written in decimal. Its keccak256 hash matches what
+/// `eth_sign`/`personal_sign` and OpenZeppelin's `toEthSignedMessageHash` compute.
+pub fn eip191_personal_message(message: &[u8]) -> Vec<u8> {
+    let length = message.len().to_string();
+    let mut encoded = Vec::with_capacity(EIP191_PERSONAL_PREFIX.len() + length.len() + message.len());
+    encoded.extend_from_slice(EIP191_PERSONAL_PREFIX);
+    encoded.extend_from_slice(length.as_bytes());
+    encoded.extend_from_slice(message);
+    encoded
+}
+
+/// Encodes EIP-712 typed data for signing
+///
+/// The result is `"\x19\x01" || domainSeparator || hashStruct(message)`. Its
+/// keccak256 hash is the EIP-712 digest (`_hashTypedDataV4` in OpenZeppelin).
+pub fn eip712_typed_data(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(EIP712_PREFIX.len() + 64);
+    encoded.extend_from_slice(EIP712_PREFIX);
+    encoded.extend_from_slice(domain_separator);
+    encoded.extend_from_slice(struct_hash);
+    encoded
+}
+
+/// An EIP-712 domain with name, version, chain ID and verifying contract
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: [u8; 20],
+}
+
+impl Eip712Domain {
+    /// Computes the domain separator, `hashStruct(EIP712Domain)`
+    pub fn separator(&self) -> [u8; 32] {
+        let mut encoded = Vec::with_capacity(5 * 32);
+        encoded.extend_from_slice(&keccak256(EIP712_DOMAIN_TYPE));
+        encoded.extend_from_slice(&keccak256(self.name.as_bytes()));
+        encoded.extend_from_slice(&keccak256(self.version.as_bytes()));
+        encoded.extend_from_slice(&[0u8; 24]);
+        encoded.extend_from_slice(&self.chain_id.to_be_bytes());
+        encoded.extend_from_slice(&[0u8; 12]);
+        encoded.extend_from_slice(&self.verifying_contract);
+        keccak256(&encoded)
+    }
+
+    /// Encodes typed data under this domain for signing
+    pub fn typed_data(&self, struct_hash: &[u8; 32]) -> Vec<u8> {
+        eip712_typed_data(&self.separator(), struct_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Keccak256Hash;
+    use crate::keys::KeyPair;
+    use num_bigint::BigUint;
+
+    fn decode32(s: &str) -> [u8; 32] {
+        hex::decode(s).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_eip191_personal_message() {
+        let encoded = eip191_personal_message(b"Hello World");
+        assert_eq!(encoded, b"\x19Ethereum Signed Message:\n11Hello World");
+        assert_eq!(
+            hex::encode(keccak256(&encoded)),
+            "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+        );
+    }
+
+    #[test]
+    fn test_eip712_typed_data() {
+        // The "Ether Mail" example from the EIP-712 specification
+        let domain = Eip712Domain {
+            name: "Ether Mail".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            verifying_contract: [0xCC; 20],
+        };
+        assert_eq!(
+            domain.separator(),
+            decode32("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        let struct_hash = decode32("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e");
+        assert_eq!(
+            keccak256(&domain.typed_data(&struct_hash)),
+            decode32("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn test_sign_eip191_with_keccak() -> crate::errors::Result<()> {
+        let keypair: KeyPair<Keccak256> = KeyPair::generate_with_hash(1024, Keccak256Hash::default())?;
+        let encoded = eip191_personal_message(b"Hello, World!");
+
+        // The key hashes the encoded message to the same digest a contract computes
+        let digest = Keccak256Hash::default().hash_for_modulus(&encoded, keypair.public.n());
+        assert_eq!(digest, BigUint::from_bytes_be(&keccak256(&encoded)));
+
+        let signature = keypair.private.sign(&encoded)?;
+        assert!(keypair.public.verify(&encoded, &signature)?);
+        assert!(!keypair.public.verify(&eip191_personal_message(b"Goodbye, World!"), &signature)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+//! Public key fingerprints
+//!
+//! A fingerprint is the SHA-256 hash of the key's canonical encoding: a label,
+//! an identifier for the key's hash function and the modulus, each prefixed with
+//! its 2-byte big-endian length. Keys with the same modulus but different hash
+//! functions produce signatures that do not verify under each other, so they get
+//! different fingerprints.
+//!
+//! The hash function identifier is the first bytes of the function's own hash of
+//! a fixed label, which distinguishes algorithms without a registry of names.
+//!
+//! Fingerprints can be shown as hex, as unpadded RFC 4648 base32, as words (one
+//! per byte of the first eight) or as OpenSSH-style randomart for comparing by eye.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, HashFunction};
+use crate::keys::PublicKey;
+use sha2::Sha256;
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of a fingerprint in bytes
+pub const FINGERPRINT_LEN: usize = 32;
+
+/// Length of the hash function identifier in the canonical encoding
+pub const HASH_ID_LEN: usize = 8;
+
+/// Number of leading fingerprint bytes shown as words
+pub const FINGERPRINT_WORDS: usize = 8;
+
+/// Label at the start of the canonical encoding
+const KEY_ENCODING_LABEL: &[u8] = b"rabin-williams public key";
+
+/// Label hashed with the key's hash function to identify it
+const HASH_ID_LABEL: &[u8] = b"rabin-williams hash algorithm";
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// One word for every byte value
+const WORDS: [&str; 256] = [
+    "acorn", "adobe", "agent", "alarm", "album", "alpha", "amber", "anchor",
+    "angle", "apple", "apron", "arena", "arrow", "aspen", "atlas", "attic",
+    "autumn", "badge", "bagel", "baker", "bamboo", "banjo", "barley", "barn",
+    "basin", "basket", "beach", "beacon", "beaver", "bell", "berry", "bicycle",
+    "birch", "bison", "blanket", "blossom", "boat", "bonnet", "border", "bottle",
+    "boulder", "bramble", "bread", "breeze", "brick", "bridge", "brook", "broom",
+    "bubble", "bucket", "buffalo", "butter", "button", "cabin", "cactus", "camel",
+    "camera", "candle", "canoe", "canyon", "carpet", "carrot", "castle", "cedar",
+    "cello", "chalk", "cherry", "chimney", "circle", "cliff", "clock", "cloud",
+    "clover", "coast", "cobalt", "comet", "compass", "copper", "coral", "cotton",
+    "cradle", "crane", "crayon", "cricket", "crystal", "dagger", "daisy", "delta",
+    "desert", "diamond", "dolphin", "donkey", "dragon", "drum", "eagle", "easel",
+    "echo", "elbow", "ember", "engine", "falcon", "feather", "fence", "fern",
+    "ferry", "fiddle", "field", "finch", "flame", "flute", "forest", "fossil",
+    "fountain", "fox", "garden", "garlic", "geyser", "ginger", "glacier", "globe",
+    "goblet", "granite", "grape", "gravel", "guitar", "hammer", "harbor", "harp",
+    "hazel", "helmet", "heron", "hollow", "honey", "horizon", "igloo", "iris",
+    "island", "ivory", "jacket", "jaguar", "jasmine", "jelly", "jigsaw", "jungle",
+    "kettle", "kiwi", "koala", "ladder", "lagoon", "lantern", "lemon", "lily",
+    "linen", "lizard", "lobster", "locket", "lotus", "magnet", "mango", "maple",
+    "marble", "meadow", "melon", "mirror", "mitten", "monkey", "moose", "mosaic",
+    "muffin", "needle", "nest", "nickel", "noodle", "oasis", "ocean", "olive",
+    "onion", "orange", "orchid", "otter", "owl", "paddle", "panda", "paper",
+    "parrot", "peach", "pebble", "pencil", "pepper", "piano", "pillow", "pine",
+    "planet", "plum", "pocket", "pony", "poppy", "prism", "puzzle", "quartz",
+    "quill", "rabbit", "radish", "raven", "reef", "ribbon", "river", "robin",
+    "rocket", "saddle", "salmon", "satin", "scarf", "shadow", "shell", "silver",
+    "sketch", "sparrow", "spider", "spruce", "squash", "statue", "stone", "summit",
+    "sunset", "swan", "tablet", "teapot", "thistle", "thunder", "tiger", "timber",
+    "toast", "tomato", "topaz", "torch", "tulip", "tunnel", "turtle", "valley",
+    "velvet", "violet", "violin", "walnut", "walrus", "wagon", "wheat", "whistle",
+    "willow", "window", "winter", "wizard", "wolf", "yarn", "zebra", "zephyr",
+];
+
+/// Randomart board size, as in OpenSSH
+const RANDOMART_WIDTH: usize = 17;
+const RANDOMART_HEIGHT: usize = 9;
+
+/// Randomart symbols by number of visits; the start and end are drawn as S and E
+const RANDOMART_SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^";
+
+/// Returns the identifier of a hash function used in canonical key encodings
+pub fn hash_algorithm_id<D: HashFunction>() -> [u8; HASH_ID_LEN] {
+    let mut id = [0u8; HASH_ID_LEN];
+    id.copy_from_slice(&digest::<D>(&[HASH_ID_LABEL])[..HASH_ID_LEN]);
+    id
+}
+
+/// A public key fingerprint
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint([u8; FINGERPRINT_LEN]);
+
+impl Fingerprint {
+    /// Returns the raw fingerprint bytes
+    pub fn as_bytes(&self) -> &[u8; FINGERPRINT_LEN] {
+        &self.0
+    }
+
+    /// Reads a fingerprint from its raw bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        bytes.try_into().map(Self).map_err(|_| RabinWilliamsError::InvalidFingerprint)
+    }
+
+    /// Returns the fingerprint as lowercase hex
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Returns the fingerprint as unpadded RFC 4648 base32
+    pub fn to_base32(&self) -> String {
+        let mut output = String::with_capacity((FINGERPRINT_LEN * 8).div_ceil(5));
+        let (mut buffer, mut bits) = (0u16, 0);
+        for &byte in &self.0 {
+            buffer = (buffer << 8) | byte as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                output.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+            }
+        }
+        if bits > 0 {
+            output.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+        }
+        output
+    }
+
+    /// Returns the first `FINGERPRINT_WORDS` bytes as words joined by hyphens
+    pub fn to_words(&self) -> String {
+        self.0[..FINGERPRINT_WORDS]
+            .iter()
+            .map(|&byte| WORDS[byte as usize])
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Draws the fingerprint as randomart with OpenSSH's "drunken bishop" walk
+    ///
+    /// The bishop starts in the middle of the board and each pair of bits, least
+    /// significant first, moves it one square diagonally.
+    pub fn randomart(&self) -> String {
+        let mut board = [[0usize; RANDOMART_WIDTH]; RANDOMART_HEIGHT];
+        let start = (RANDOMART_WIDTH / 2, RANDOMART_HEIGHT / 2);
+        let (mut x, mut y) = start;
+        for &byte in &self.0 {
+            for step in 0..4 {
+                let bits = byte >> (2 * step);
+                x = if bits & 1 == 1 { (x + 1).min(RANDOMART_WIDTH - 1) } else { x.saturating_sub(1) };
+                y = if bits & 2 == 2 { (y + 1).min(RANDOMART_HEIGHT - 1) } else { y.saturating_sub(1) };
+                board[y][x] += 1;
+            }
+        }
+
+        let border = format!("+{}+", "-".repeat(RANDOMART_WIDTH));
+        let mut lines = vec![format!("+{:-^width$}+", "[RW]", width = RANDOMART_WIDTH)];
+        for (row, cells) in board.iter().enumerate() {
+            let line: String = cells
+                .iter()
+                .enumerate()
+                .map(|(column, &visits)| match (column, row) {
+                    position if position == start => 'S',
+                    position if position == (x, y) => 'E',
+                    _ => RANDOMART_SYMBOLS[visits.min(RANDOMART_SYMBOLS.len() - 1)] as char,
+                })
+                .collect();
+            lines.push(format!("|{}|", line));
+        }
+        lines.push(border);
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    /// Formats the fingerprint as hex
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = RabinWilliamsError;
+
+    /// Parses a hex fingerprint
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s.trim()).map_err(|_| RabinWilliamsError::InvalidFingerprint)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Returns the canonical encoding that fingerprints are computed over
+    pub fn canonical_encoding(&self) -> Vec<u8> {
+        let mut encoding = Vec::new();
+        for field in [KEY_ENCODING_LABEL, &hash_algorithm_id::<D>(), &self.n().to_bytes_be()] {
+            encoding.extend_from_slice(&(field.len() as u16).to_be_bytes());
+            encoding.extend_from_slice(field);
+        }
+        encoding
+    }
+
+    /// Returns the key's fingerprint
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut fingerprint = [0u8; FINGERPRINT_LEN];
+        fingerprint.copy_from_slice(&digest::<Sha256>(&[&self.canonical_encoding()]));
+        Fingerprint(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha3::Keccak256;
+
+    #[test]
+    fn test_fingerprint() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let fingerprint = key_pair.public.fingerprint();
+
+        // Stable, and the same for the public key derived from the private key
+        assert_eq!(key_pair.private.public_key().fingerprint(), fingerprint);
+        assert_eq!(fingerprint.to_string().parse::<Fingerprint>()?, fingerprint);
+        assert_eq!(Fingerprint::from_bytes(fingerprint.as_bytes())?, fingerprint);
+
+        // The hash function is part of the key
+        let keccak = PublicKey::<Keccak256>::from_n(key_pair.public.n().clone());
+        assert_ne!(keccak.fingerprint(), fingerprint);
+        assert_ne!(hash_algorithm_id::<Keccak256>(), hash_algorithm_id::<Sha256>());
+
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        assert_ne!(other.public.fingerprint(), fingerprint);
+
+        assert!("abcd".parse::<Fingerprint>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_representations() {
+        let mut bytes = [0u8; FINGERPRINT_LEN];
+        bytes[..5].copy_from_slice(b"hello");
+        let fingerprint = Fingerprint(bytes);
+
+        assert_eq!(fingerprint.to_hex().len(), 64);
+        // RFC 4648 test vector for the leading bytes
+        assert!(fingerprint.to_base32().starts_with("NBSWY3DP"));
+        assert_eq!(fingerprint.to_base32().len(), 52);
+
+        let words = fingerprint.to_words();
+        assert_eq!(words.split('-').count(), FINGERPRINT_WORDS);
+        assert!(words.starts_with(WORDS[b'h' as usize]));
+
+        let art = fingerprint.randomart();
+        let lines: Vec<&str> = art.lines().collect();
+        assert_eq!(lines.len(), RANDOMART_HEIGHT + 2);
+        assert!(lines.iter().all(|line| line.chars().count() == RANDOMART_WIDTH + 2));
+        assert!(art.contains('S') && art.contains('E'));
+    }
+
+    #[test]
+    fn test_word_list_is_unambiguous() {
+        let mut words = WORDS.to_vec();
+        words.sort_unstable();
+        words.dedup();
+        assert_eq!(words.len(), 256);
+    }
+}
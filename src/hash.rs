@@ -1,24 +1,158 @@
-use digest::Digest;
+use digest::{Digest, ExtendableOutput, Update, XofReader};
 use num_bigint::BigUint;
 use sha2::Sha256;
+use sha3::{Keccak256, Shake128, Shake256};
 
-/// A wrapper around a Digest implementation that provides BigUint conversion
+/// Number of output bits an extendable-output function produces when no length is requested
+pub const DEFAULT_XOF_BITS: usize = 256;
+
+/// A hash function that can be used to map messages to integers
+///
+/// This is implemented for every fixed-output `Digest` and for extendable-output
+/// functions wrapped in [`Xof`].
+pub trait HashFunction: Clone {
+    /// Creates a fresh hasher state
+    fn init() -> Self;
+
+    /// Absorbs `data` into the hasher state
+    fn absorb(&mut self, data: &[u8]);
+
+    /// Finalizes the hasher into an integer
+    ///
+    /// Extendable-output functions produce exactly `bits` bits. Fixed-output digests
+    /// ignore `bits` and return their natural output.
+    fn finish(self, bits: usize) -> BigUint;
+
+    /// Number of bits produced when the caller does not ask for a specific length
+    fn default_bits() -> usize;
+}
+
+impl<D: Digest + Clone> HashFunction for D {
+    fn init() -> Self {
+        D::new()
+    }
+
+    fn absorb(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finish(self, _bits: usize) -> BigUint {
+        BigUint::from_bytes_be(&self.finalize())
+    }
+
+    fn default_bits() -> usize {
+        <D as Digest>::output_size() * 8
+    }
+}
+
+/// Adapter that turns an extendable-output function into a [`HashFunction`]
+///
+/// The output is truncated to exactly the requested number of bits, which lets
+/// keys hash messages into (almost) the full domain of their modulus.
+#[derive(Clone, Debug, Default)]
+pub struct Xof<X>(X);
+
+impl<X: ExtendableOutput + Update + Default + Clone> HashFunction for Xof<X> {
+    fn init() -> Self {
+        Self(X::default())
+    }
+
+    fn absorb(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finish(self, bits: usize) -> BigUint {
+        let mut output = vec![0u8; bits.div_ceil(8)];
+        self.0.finalize_xof().read(&mut output);
+        // Clear the excess high bits so the result is exactly `bits` bits wide
+        if !bits.is_multiple_of(8) {
+            output[0] &= 0xFF >> (8 - bits % 8);
+        }
+        BigUint::from_bytes_be(&output)
+    }
+
+    fn default_bits() -> usize {
+        DEFAULT_XOF_BITS
+    }
+}
+
+/// Hashes the concatenation of `parts` into the hash function's natural output length
+pub(crate) fn digest<D: HashFunction>(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = D::init();
+    for part in parts {
+        hasher.absorb(part);
+    }
+    let bytes = hasher.finish(D::default_bits()).to_bytes_be();
+    let mut output = vec![0u8; D::default_bits() / 8 - bytes.len()];
+    output.extend_from_slice(&bytes);
+    output
+}
+
+/// Mask generation function MGF1 from RFC 8017
+pub(crate) fn mgf1<D: HashFunction>(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len);
+    let mut counter = 0u32;
+    while output.len() < len {
+        output.extend(digest::<D>(&[seed, &counter.to_be_bytes()]));
+        counter += 1;
+    }
+    output.truncate(len);
+    output
+}
+
+/// A wrapper around a hash function that provides BigUint conversion
 #[derive(Clone, Debug)]
-pub struct HashWrapper<D: Digest + Clone>(D);
+pub struct HashWrapper<D: HashFunction>(D);
 
-impl<D: Digest + Clone> HashWrapper<D> {
+impl<D: HashFunction> HashWrapper<D> {
     pub fn new() -> Self {
-        Self(D::new())
+        Self(D::init())
     }
 
     pub fn hash(&self, message: &[u8]) -> BigUint {
+        self.hash_to_bits(message, D::default_bits())
+    }
+
+    /// Hashes a message into at most `bits` bits
+    ///
+    /// Only extendable-output functions honour `bits`; fixed-output digests always
+    /// return their natural output.
+    pub fn hash_to_bits(&self, message: &[u8], bits: usize) -> BigUint {
         let mut hasher = self.0.clone();
-        hasher.update(message);
-        BigUint::from_bytes_be(&hasher.finalize())
+        hasher.absorb(message);
+        hasher.finish(bits)
+    }
+
+    /// Hashes a message bound to a context string into at most `bits` bits
+    ///
+    /// The context and message are each prefixed with their length as a 64-bit
+    /// big-endian integer, so no two (context, message) pairs share an encoding.
+    pub fn hash_with_context_to_bits(&self, context: &[u8], message: &[u8], bits: usize) -> BigUint {
+        let mut hasher = self.0.clone();
+        hasher.absorb(&(context.len() as u64).to_be_bytes());
+        hasher.absorb(context);
+        hasher.absorb(&(message.len() as u64).to_be_bytes());
+        hasher.absorb(message);
+        hasher.finish(bits)
+    }
+
+    /// Hashes a message for use with the modulus `n`
+    ///
+    /// Extendable-output functions produce one bit less than `n` is wide, the
+    /// largest length that is always smaller than `n` (full-domain hashing).
+    pub fn hash_for_modulus(&self, message: &[u8], n: &BigUint) -> BigUint {
+        let bits = (n.bits() as usize).saturating_sub(1);
+        self.hash_to_bits(message, bits)
+    }
+
+    /// Hashes a message bound to a context string for use with the modulus `n`
+    pub fn hash_with_context_for_modulus(&self, context: &[u8], message: &[u8], n: &BigUint) -> BigUint {
+        let bits = (n.bits() as usize).saturating_sub(1);
+        self.hash_with_context_to_bits(context, message, bits)
     }
 }
 
-impl<D: Digest + Clone> Default for HashWrapper<D> {
+impl<D: HashFunction> Default for HashWrapper<D> {
     fn default() -> Self {
         Self::new()
     }
@@ -27,6 +161,18 @@ impl<D: Digest + Clone> Default for HashWrapper<D> {
 /// Type alias for SHA-256 hash function
 pub type Sha256Hash = HashWrapper<Sha256>;
 
+/// Type alias for Keccak-256, the hash function used natively by Ethereum
+pub type Keccak256Hash = HashWrapper<Keccak256>;
+
+/// Type alias for the SHAKE128 extendable-output function
+pub type Shake128Hash = HashWrapper<Xof<Shake128>>;
+
+/// Type alias for the SHAKE256 extendable-output function
+pub type Shake256Hash = HashWrapper<Xof<Shake256>>;
+
+/// Type alias for BLAKE3 used as an extendable-output function
+pub type Blake3Hash = HashWrapper<Xof<blake3::Hasher>>;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,4 +200,66 @@ mod tests {
         // Different hash functions should produce different outputs
         assert_ne!(hash256, hash512);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_xof_output_length() {
+        let message = b"Hello, World!";
+        for bits in [1usize, 7, 8, 255, 1023, 2047] {
+            assert!(Shake128Hash::default().hash_to_bits(message, bits).bits() as usize <= bits);
+            assert!(Shake256Hash::default().hash_to_bits(message, bits).bits() as usize <= bits);
+            assert!(Blake3Hash::default().hash_to_bits(message, bits).bits() as usize <= bits);
+        }
+
+        // Fixed-output digests ignore the requested length
+        let sha256 = Sha256Hash::default();
+        assert_eq!(sha256.hash_to_bits(message, 1023), sha256.hash(message));
+    }
+
+    #[test]
+    fn test_xof_prefix_consistency() {
+        // A longer XOF output extends the shorter one rather than replacing it
+        let shake = Shake256Hash::default();
+        let message = b"Hello, World!";
+        let short = shake.hash_to_bits(message, 256);
+        let long = shake.hash_to_bits(message, 512);
+        assert_eq!(long >> 256u32, short);
+    }
+
+    #[test]
+    fn test_xof_known_answer() {
+        // SHAKE128("") from FIPS 202, first 256 bits
+        let expected = "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26";
+        let hash = Shake128Hash::default().hash(b"");
+        assert_eq!(hex::encode(hash.to_bytes_be()), expected);
+    }
+
+    #[test]
+    fn test_hash_with_context() {
+        let sha256 = Sha256Hash::default();
+        let message = b"Hello, World!";
+
+        // Different contexts separate otherwise identical messages
+        let a = sha256.hash_with_context_to_bits(b"login", message, 256);
+        let b = sha256.hash_with_context_to_bits(b"payment", message, 256);
+        assert_ne!(a, b);
+
+        // Moving bytes between context and message changes the encoding
+        let c = sha256.hash_with_context_to_bits(b"ab", b"c", 256);
+        let d = sha256.hash_with_context_to_bits(b"a", b"bc", 256);
+        assert_ne!(c, d);
+
+        // An empty context is still distinct from the plain hash
+        assert_ne!(sha256.hash_with_context_to_bits(b"", message, 256), sha256.hash(message));
+    }
+
+    #[test]
+    fn test_hash_for_modulus() {
+        let n = (BigUint::from(1u32) << 1024u32) - 1u32;
+        let blake3 = Blake3Hash::default();
+        for i in 0u32..16 {
+            let hash = blake3.hash_for_modulus(&i.to_be_bytes(), &n);
+            assert!(hash < n);
+            assert!(hash.bits() <= 1023);
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Key encapsulation with the squaring trapdoor
+//!
+//! The sender picks a random `x`, sends `c = x^2 mod n` and derives a shared
+//! secret from `x` with a KDF. The recipient recovers `x` as one of the four
+//! square roots of `c`. To make the root unique, `x` is drawn from the values
+//! below `n / 2` with Jacobi symbol `(x | n) = 1`: since `-1` has Jacobi symbol 1
+//! modulo a Blum integer, exactly one of the four roots lies in that set.
+//!
+//! The KEM is exposed through the [`Encapsulate`] and [`Decapsulate`] traits,
+//! and [`seal`] and [`open`] build hybrid encryption of arbitrary data on top of
+//! them with ChaCha20-Poly1305.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use crate::utils::{jacobi, to_fixed_bytes};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use num_bigint::{BigUint, RandBigInt};
+
+/// Length of a shared secret in bytes
+pub const SHARED_SECRET_LEN: usize = 32;
+
+/// A secret shared between the encapsulating and decapsulating parties
+pub type SharedSecret = [u8; SHARED_SECRET_LEN];
+
+/// KDF info that binds shared secrets to this KEM
+const KEM_INFO: &[u8] = b"rabin-williams kem";
+
+/// KDF info for the key of the symmetric layer in `seal`
+const HYBRID_INFO: &[u8] = b"rabin-williams hybrid encryption";
+
+/// Header that starts every sealed message, including the format version
+const HYBRID_MAGIC: &[u8] = b"RWH\x01";
+
+/// The sending side of a key encapsulation mechanism
+pub trait Encapsulate {
+    /// Generates a fresh shared secret
+    ///
+    /// Returns the encapsulated key to send to the recipient and the secret it carries.
+    fn encapsulate(&self) -> Result<(Vec<u8>, SharedSecret)>;
+}
+
+/// The receiving side of a key encapsulation mechanism
+pub trait Decapsulate {
+    /// Recovers the shared secret carried by an encapsulated key
+    fn decapsulate(&self, encapsulated_key: &[u8]) -> Result<SharedSecret>;
+}
+
+/// Single-step key derivation from NIST SP 800-56C over any [`HashFunction`]
+///
+/// Output block `i` is `H(i || secret || info)` with a 32-bit big-endian
+/// counter starting at 1.
+pub fn kdf<D: HashFunction>(secret: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len);
+    let mut counter = 1u32;
+    while output.len() < len {
+        output.extend(digest::<D>(&[&counter.to_be_bytes(), secret, info]));
+        counter += 1;
+    }
+    output.truncate(len);
+    output
+}
+
+/// Derives the shared secret from the root and the encapsulated key
+fn derive_secret<D: HashFunction>(x: &BigUint, encapsulated_key: &[u8]) -> SharedSecret {
+    let secret = [to_fixed_bytes(x, encapsulated_key.len()), encapsulated_key.to_vec()].concat();
+    let mut shared_secret = [0u8; SHARED_SECRET_LEN];
+    shared_secret.copy_from_slice(&kdf::<D>(&secret, KEM_INFO, SHARED_SECRET_LEN));
+    shared_secret
+}
+
+/// Returns true for the root that encapsulation picks: below n / 2 with Jacobi symbol 1
+fn is_canonical_root(x: &BigUint, n: &BigUint) -> bool {
+    x << 1 < *n && jacobi(x, n) == 1
+}
+
+impl<D: HashFunction> Encapsulate for PublicKey<D> {
+    fn encapsulate(&self) -> Result<(Vec<u8>, SharedSecret)> {
+        self.require_encryption()?;
+
+        let n = self.n();
+        let mut rng = rand::thread_rng();
+        // About a quarter of the candidates are canonical
+        let x = loop {
+            let candidate = rng.gen_biguint_range(&BigUint::from(1u32), n);
+            if is_canonical_root(&candidate, n) {
+                break candidate;
+            }
+        };
+
+        let encapsulated_key = to_fixed_bytes(&(&x * &x % n), n.bits().div_ceil(8) as usize);
+        let shared_secret = derive_secret::<D>(&x, &encapsulated_key);
+        Ok((encapsulated_key, shared_secret))
+    }
+}
+
+impl<D: HashFunction> Decapsulate for PrivateKey<D> {
+    /// Fails with `InvalidCiphertext` if the encapsulated key is not a square
+    /// modulo n; any square decapsulates to some secret.
+    fn decapsulate(&self, encapsulated_key: &[u8]) -> Result<SharedSecret> {
+        self.require_encryption()?;
+
+        let n = self.n();
+        let c = BigUint::from_bytes_be(encapsulated_key);
+        if encapsulated_key.len() != n.bits().div_ceil(8) as usize || c >= n {
+            return Err(RabinWilliamsError::InvalidCiphertext);
+        }
+
+        let x = self
+            .square_roots(&c)?
+            .into_iter()
+            .find(|root| is_canonical_root(root, &n))
+            .ok_or(RabinWilliamsError::InvalidCiphertext)?;
+        Ok(derive_secret::<D>(&x, encapsulated_key))
+    }
+}
+
+/// Builds the cipher for the symmetric layer from a shared secret
+fn hybrid_cipher(shared_secret: &SharedSecret) -> ChaCha20Poly1305 {
+    // The default hash is enough here: the shared secret is already uniform
+    let key = kdf::<sha2::Sha256>(shared_secret, HYBRID_INFO, 32);
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// Encrypts data of any length to the holder of a decapsulation key
+///
+/// The output is the header `RWH\x01`, the 2-byte big-endian length of the
+/// encapsulated key, the encapsulated key and the ChaCha20-Poly1305 ciphertext.
+/// Each message uses a fresh key, so the AEAD nonce is fixed at zero; the
+/// header is authenticated as associated data.
+pub fn seal<E: Encapsulate>(recipient: &E, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let (encapsulated_key, shared_secret) = recipient.encapsulate()?;
+    let key_len = u16::try_from(encapsulated_key.len()).map_err(|_| RabinWilliamsError::InvalidKeySize)?;
+
+    let mut sealed = HYBRID_MAGIC.to_vec();
+    sealed.extend_from_slice(&key_len.to_be_bytes());
+    sealed.extend_from_slice(&encapsulated_key);
+
+    let ciphertext = hybrid_cipher(&shared_secret)
+        .encrypt(Nonce::from_slice(&[0u8; 12]), Payload { msg: plaintext, aad: &sealed })
+        .map_err(|_| RabinWilliamsError::ComputationError)?;
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts the output of [`seal`]
+///
+/// Fails with `InvalidCiphertext` if the data is malformed, was sealed for
+/// another key or has been modified.
+pub fn open<K: Decapsulate>(recipient: &K, sealed: &[u8]) -> Result<Vec<u8>> {
+    let rest = sealed.strip_prefix(HYBRID_MAGIC).ok_or(RabinWilliamsError::InvalidCiphertext)?;
+    if rest.len() < 2 {
+        return Err(RabinWilliamsError::InvalidCiphertext);
+    }
+    let key_len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+    if rest.len() < 2 + key_len {
+        return Err(RabinWilliamsError::InvalidCiphertext);
+    }
+    let (header, ciphertext) = sealed.split_at(HYBRID_MAGIC.len() + 2 + key_len);
+
+    let shared_secret = recipient.decapsulate(&header[HYBRID_MAGIC.len() + 2..])?;
+    hybrid_cipher(&shared_secret)
+        .decrypt(Nonce::from_slice(&[0u8; 12]), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| RabinWilliamsError::InvalidCiphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeyPair, KeyUsage};
+    use sha2::Sha256;
+
+    fn encryption_keys() -> Result<KeyPair<Sha256>> {
+        Ok(KeyPair::generate(1024)?.with_usage(KeyUsage::Encryption))
+    }
+
+    #[test]
+    fn test_encapsulate_decapsulate() -> Result<()> {
+        let key_pair = encryption_keys()?;
+
+        for _ in 0..8 {
+            let (encapsulated_key, shared_secret) = key_pair.public.encapsulate()?;
+            assert_eq!(encapsulated_key.len(), 128);
+            assert_eq!(key_pair.private.decapsulate(&encapsulated_key)?, shared_secret);
+        }
+
+        let (first, _) = key_pair.public.encapsulate()?;
+        let (second, _) = key_pair.public.encapsulate()?;
+        assert_ne!(first, second);
+
+        // Signing keys cannot be used for key encapsulation
+        let signing: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        assert!(matches!(signing.public.encapsulate(), Err(RabinWilliamsError::KeyUsageNotAllowed)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exactly_one_canonical_root() -> Result<()> {
+        let key_pair = encryption_keys()?;
+        let n = key_pair.public.n();
+
+        for _ in 0..8 {
+            let (encapsulated_key, _) = key_pair.public.encapsulate()?;
+            let roots = key_pair.private.square_roots(&BigUint::from_bytes_be(&encapsulated_key))?;
+            assert_eq!(roots.iter().filter(|root| is_canonical_root(root, n)).count(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decapsulate_rejects_invalid_keys() -> Result<()> {
+        let key_pair = encryption_keys()?;
+        let (encapsulated_key, shared_secret) = key_pair.public.encapsulate()?;
+
+        // A modified key either is not a square or decapsulates to another secret
+        let mut modified = encapsulated_key.clone();
+        modified[64] ^= 1;
+        assert!(key_pair.private.decapsulate(&modified).map_or(true, |secret| secret != shared_secret));
+
+        assert!(key_pair.private.decapsulate(&encapsulated_key[1..]).is_err());
+        assert!(key_pair.private.decapsulate(&key_pair.public.n().to_bytes_be()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seal_open() -> Result<()> {
+        let key_pair = encryption_keys()?;
+        let plaintext = vec![0x42u8; 10_000];
+
+        let sealed = seal(&key_pair.public, &plaintext)?;
+        assert_eq!(open(&key_pair.private, &sealed)?, plaintext);
+        assert_eq!(open(&key_pair.private, &seal(&key_pair.public, b"")?)?, b"");
+
+        // Any modification is detected, in the header or the body
+        for index in [0, 10, sealed.len() - 1] {
+            let mut modified = sealed.clone();
+            modified[index] ^= 1;
+            assert!(open(&key_pair.private, &modified).is_err());
+        }
+        assert!(open(&key_pair.private, &sealed[..100]).is_err());
+
+        // Another key cannot open it
+        let other = encryption_keys()?;
+        assert!(open(&other.private, &sealed).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kdf() {
+        let output = kdf::<Sha256>(b"secret", b"info", 80);
+        assert_eq!(output.len(), 80);
+        assert_eq!(output[..32], digest::<Sha256>(&[&1u32.to_be_bytes(), b"secret", b"info"])[..]);
+        assert_eq!(kdf::<Sha256>(b"secret", b"info", 16), output[..16]);
+        assert_ne!(kdf::<Sha256>(b"secret", b"other", 32), output[..32]);
+    }
+}
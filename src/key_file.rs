@@ -0,0 +1,175 @@
+//! Key files, as written by the CLI and the key store
+//!
+//! A public key file holds `n` in hex, and a private key file `p` and `q` in
+//! hex, one number per line. Either is followed by a `usage=<name>` line unless
+//! the key is for signing only.
+//!
+//! Files are written through a temporary file and a rename, and private key
+//! files are readable only by their owner on Unix.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::HashFunction;
+use crate::keys::{KeyUsage, PrivateKey, PublicKey};
+use num_bigint::BigUint;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Formats a public key as the contents of a key file
+pub fn public_key_to_text<D: HashFunction>(public_key: &PublicKey<D>) -> String {
+    hex::encode(public_key.n().to_bytes_be()) + &usage_line(public_key.usage())
+}
+
+/// Formats a private key as the contents of a key file
+pub fn private_key_to_text<D: HashFunction>(private_key: &PrivateKey<D>) -> String {
+    format!(
+        "{}\n{}{}",
+        hex::encode(private_key.p.to_bytes_be()),
+        hex::encode(private_key.q.to_bytes_be()),
+        usage_line(private_key.usage())
+    )
+}
+
+/// Parses the contents of a public key file
+///
+/// Fails with `InvalidKeyFile` if the file is malformed.
+pub fn public_key_from_text<D: HashFunction>(text: &str) -> Result<PublicKey<D>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let n = lines.first().and_then(|line| parse_hex(line)).ok_or(RabinWilliamsError::InvalidKeyFile)?;
+    Ok(PublicKey::from_n(n).with_usage(parse_usage(&lines[1..])?))
+}
+
+/// Parses the contents of a private key file
+///
+/// Fails with `InvalidKeyFile` if the file is malformed.
+pub fn private_key_from_text<D: HashFunction>(text: &str) -> Result<PrivateKey<D>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let (p, q) = match lines.as_slice() {
+        [p, q, ..] => parse_hex(p).zip(parse_hex(q)).ok_or(RabinWilliamsError::InvalidKeyFile)?,
+        _ => return Err(RabinWilliamsError::InvalidKeyFile),
+    };
+    Ok(PrivateKey::from_primes(p, q).with_usage(parse_usage(&lines[2..])?))
+}
+
+/// Writes a public key file
+pub fn save_public_key<D: HashFunction>(public_key: &PublicKey<D>, path: &Path) -> Result<()> {
+    write_file(path, &public_key_to_text(public_key), false).map_err(|_| RabinWilliamsError::KeyFileError)
+}
+
+/// Writes a private key file, readable only by its owner on Unix
+pub fn save_private_key<D: HashFunction>(private_key: &PrivateKey<D>, path: &Path) -> Result<()> {
+    write_file(path, &private_key_to_text(private_key), true).map_err(|_| RabinWilliamsError::KeyFileError)
+}
+
+/// Writes other secret material, such as a key share, the way private key
+/// files are written: readable only by its owner on Unix
+///
+/// Fails with `Io` if the file cannot be written.
+pub fn save_secret_file(path: &Path, contents: &str) -> Result<()> {
+    Ok(write_file(path, contents, true)?)
+}
+
+/// Reads a public key file
+pub fn load_public_key<D: HashFunction>(path: &Path) -> Result<PublicKey<D>> {
+    public_key_from_text(&fs::read_to_string(path).map_err(|_| RabinWilliamsError::KeyFileError)?)
+}
+
+/// Reads a private key file
+pub fn load_private_key<D: HashFunction>(path: &Path) -> Result<PrivateKey<D>> {
+    private_key_from_text(&fs::read_to_string(path).map_err(|_| RabinWilliamsError::KeyFileError)?)
+}
+
+fn parse_hex(line: &str) -> Option<BigUint> {
+    hex::decode(line.trim()).ok().map(|bytes| BigUint::from_bytes_be(&bytes))
+}
+
+fn usage_line(usage: KeyUsage) -> String {
+    match usage {
+        KeyUsage::Signing => String::new(),
+        usage => format!("\nusage={}", usage),
+    }
+}
+
+/// Parses the optional usage line that follows the key material
+fn parse_usage(lines: &[&str]) -> Result<KeyUsage> {
+    match lines.iter().find(|line| !line.trim().is_empty()) {
+        Some(line) => line.trim().strip_prefix("usage=").ok_or(RabinWilliamsError::InvalidKeyFile)?.parse(),
+        None => Ok(KeyUsage::Signing),
+    }
+}
+
+/// Writes a file through a temporary file and a rename, so readers never see
+/// it half written
+///
+/// With `private` set, the file is created readable only by its owner on Unix.
+pub(crate) fn write_file(path: &Path, contents: &str, private: bool) -> std::io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    // A leftover temporary file would keep its old permissions
+    let _ = fs::remove_file(&temporary);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let written = options.open(&temporary).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    written.and_then(|()| fs::rename(&temporary, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_key_file_round_trip() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?.with_usage(KeyUsage::SigningAndEncryption);
+
+        let public_key = public_key_from_text::<Sha256>(&public_key_to_text(&key_pair.public))?;
+        assert_eq!((public_key.n(), public_key.usage()), (key_pair.public.n(), KeyUsage::SigningAndEncryption));
+        let private_key = private_key_from_text::<Sha256>(&private_key_to_text(&key_pair.private))?;
+        assert_eq!((&private_key.p, &private_key.q), (&key_pair.private.p, &key_pair.private.q));
+        assert_eq!(private_key.usage(), KeyUsage::SigningAndEncryption);
+
+        // Signing keys have no usage line
+        let signing: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        assert_eq!(public_key_to_text(&signing.public).lines().count(), 1);
+
+        for text in ["", "zz", "00\n", "ab\ncd\nusage=nonsense", "ab\ncd\nother"] {
+            assert!(private_key_from_text::<Sha256>(text).is_err());
+        }
+        assert!(public_key_from_text::<Sha256>("not hex").is_err());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_key_file_is_owner_only() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let nonce: u64 = rand::random();
+        let path = std::env::temp_dir().join(format!("rw-key-file-{:016x}.hex", nonce));
+        save_private_key(&key_pair.private, &path)?;
+        let mode = fs::metadata(&path).map_err(|_| RabinWilliamsError::KeyFileError)?.permissions().mode();
+        let loaded = load_private_key::<Sha256>(&path)?;
+        save_secret_file(&path, "share")?;
+        let secret_mode = fs::metadata(&path)?.permissions().mode();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(secret_mode & 0o777, 0o600);
+        assert_eq!(loaded.n(), key_pair.private.n());
+        Ok(())
+    }
+}
@@ -2,31 +2,101 @@ use crate::errors::{RabinWilliamsError, Result};
 use num_bigint::{BigUint, RandBigInt};
 use num_integer::Integer;
 use num_prime::{nt_funcs::is_prime, Primality, PrimalityTestConfig};
-use digest::Digest;
 use sha2::Sha256;
-use crate::utils::{chinese_remainder_theorem, make_quadratic_residue, mod_inverse};
-use crate::hash::HashWrapper;
+use crate::utils::mod_inverse;
+use crate::hash::{HashFunction, HashWrapper};
+use crate::pool::BlindingPair;
+use std::fmt;
+use std::str::FromStr;
+
+/// Size in bits of the random multiplier used to blind exponents
+const EXPONENT_BLINDING_BITS: u64 = 64;
+
+/// What a key pair may be used for
+///
+/// Keys are for signing unless created otherwise. Using one key for both
+/// signing and encryption must be asked for explicitly with `SigningAndEncryption`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyUsage {
+    #[default]
+    Signing,
+    Encryption,
+    SigningAndEncryption,
+}
+
+impl KeyUsage {
+    /// Returns true if keys with this usage may sign and verify
+    pub fn allows_signing(self) -> bool {
+        matches!(self, KeyUsage::Signing | KeyUsage::SigningAndEncryption)
+    }
+
+    /// Returns true if keys with this usage may encrypt and decrypt
+    pub fn allows_encryption(self) -> bool {
+        matches!(self, KeyUsage::Encryption | KeyUsage::SigningAndEncryption)
+    }
+
+    /// Returns the usage's name, as accepted by `from_str`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyUsage::Signing => "signing",
+            KeyUsage::Encryption => "encryption",
+            KeyUsage::SigningAndEncryption => "signing-and-encryption",
+        }
+    }
+}
+
+impl fmt::Display for KeyUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for KeyUsage {
+    type Err = RabinWilliamsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [KeyUsage::Signing, KeyUsage::Encryption, KeyUsage::SigningAndEncryption]
+            .into_iter()
+            .find(|usage| usage.as_str() == s)
+            .ok_or_else(|| RabinWilliamsError::UnknownKeyUsage(s.to_string()))
+    }
+}
 
 #[derive(Clone, Debug)]
-pub struct PublicKey<D: Digest + Clone = Sha256> {
+pub struct PublicKey<D: HashFunction = Sha256> {
     pub n: BigUint,
-    hash_fn: HashWrapper<D>,
+    pub(crate) hash_fn: HashWrapper<D>,
+    pub(crate) usage: KeyUsage,
 }
 
 #[derive(Clone, Debug)]
-pub struct PrivateKey<D: Digest + Clone = Sha256> {
+pub struct PrivateKey<D: HashFunction = Sha256> {
     pub p: BigUint,
     pub q: BigUint,
-    hash_fn: HashWrapper<D>,
+    pub(crate) hash_fn: HashWrapper<D>,
+    pub(crate) usage: KeyUsage,
+    /// Whether private-key exponentiations are blinded against side channels
+    pub(crate) side_channel_blinding: bool,
+}
+
+/// Values derived from the private primes that are reused across signatures
+#[derive(Clone, Debug)]
+pub struct CrtParams {
+    /// (p + 1) / 4, the square-root exponent modulo p
+    pub(crate) p_exp: BigUint,
+    /// (q + 1) / 4, the square-root exponent modulo q
+    pub(crate) q_exp: BigUint,
+    /// q⁻¹ mod p
+    pub(crate) q_inv_p: BigUint,
 }
 
 #[derive(Clone, Debug)]
-pub struct KeyPair<D: Digest + Clone = Sha256> {
+pub struct KeyPair<D: HashFunction = Sha256> {
     pub public: PublicKey<D>,
     pub private: PrivateKey<D>,
 }
 
-impl<D: Digest + Clone> KeyPair<D> {
+impl<D: HashFunction> KeyPair<D> {
     /// Generates a new Rabin-Williams key pair with the specified hash function
     pub fn generate_with_hash(bits: usize, hash_fn: HashWrapper<D>) -> Result<Self> {
         if bits < 1024 {
@@ -42,10 +112,18 @@ impl<D: Digest + Clone> KeyPair<D> {
         let n = &p * &q;
 
         Ok(KeyPair {
-            public: PublicKey { n: n.clone(), hash_fn: hash_fn.clone() },
-            private: PrivateKey { p, q, hash_fn },
+            public: PublicKey { n: n.clone(), hash_fn: hash_fn.clone(), usage: KeyUsage::default() },
+            private: PrivateKey { p, q, hash_fn, usage: KeyUsage::default(), side_channel_blinding: false },
         })
     }
+
+    /// Sets what both keys may be used for
+    pub fn with_usage(self, usage: KeyUsage) -> Self {
+        Self {
+            public: self.public.with_usage(usage),
+            private: self.private.with_usage(usage),
+        }
+    }
 }
 
 impl KeyPair<Sha256> {
@@ -82,12 +160,13 @@ fn generate_prime_congruent(bits: usize, remainder: u32, modulus: u32) -> Result
     Err(RabinWilliamsError::InvalidPrime)
 }
 
-impl<D: Digest + Clone> PublicKey<D> {
+impl<D: HashFunction> PublicKey<D> {
     /// Creates a new PublicKey from a modulus n
     pub fn from_n(n: BigUint) -> Self {
         Self {
             n,
             hash_fn: HashWrapper::default(),
+            usage: KeyUsage::default(),
         }
     }
 
@@ -96,6 +175,33 @@ impl<D: Digest + Clone> PublicKey<D> {
         &self.n
     }
 
+    /// Sets what the key may be used for
+    pub fn with_usage(mut self, usage: KeyUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Returns what the key may be used for
+    pub fn usage(&self) -> KeyUsage {
+        self.usage
+    }
+
+    /// Fails with `KeyUsageNotAllowed` unless the key may verify signatures
+    pub(crate) fn require_signing(&self) -> Result<()> {
+        if !self.usage.allows_signing() {
+            return Err(RabinWilliamsError::KeyUsageNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Fails with `KeyUsageNotAllowed` unless the key may encrypt
+    pub(crate) fn require_encryption(&self) -> Result<()> {
+        if !self.usage.allows_encryption() {
+            return Err(RabinWilliamsError::KeyUsageNotAllowed);
+        }
+        Ok(())
+    }
+
     // Generate a random coprime to n
     pub fn coprime(&self) -> BigUint {
         let mut rng = rand::thread_rng();
@@ -107,13 +213,23 @@ impl<D: Digest + Clone> PublicKey<D> {
         }
     }
 
+    /// Generates a random blinding factor r and its square
+    ///
+    /// Factors whose square is 1 (such as 1 and n - 1) would leave the message
+    /// unblinded, so they are never returned.
     pub fn blinding(&self) -> (BigUint, BigUint) {
-        let r = self.coprime();
-        let r_squared = &r * &r % self.n.clone();
-        (r, r_squared)
+        loop {
+            let r = self.coprime();
+            let r_squared = &r * &r % self.n.clone();
+            if r_squared != BigUint::from(1u32) {
+                return (r, r_squared);
+            }
+        }
     }
 
     pub fn extract_signature(&self, signature: &[u8]) -> Result<(i32, u32, BigUint)> {
+        self.require_signing()?;
+
         if signature.is_empty() {
             return Err(RabinWilliamsError::InvalidSignature);
         }
@@ -140,7 +256,25 @@ impl<D: Digest + Clone> PublicKey<D> {
     }
 
     pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<bool> {
-        let m = self.hash_fn.hash(message);
+        let m = self.hash_fn.hash_for_modulus(message, self.n());
+        self.verify_hash(&m, signature)
+    }
+
+    /// Verifies a signature produced by `PrivateKey::sign_with_context`
+    ///
+    /// The signature only verifies under the same context it was created with.
+    pub fn verify_with_context(&self, context: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+        let m = self.hash_fn.hash_with_context_for_modulus(context, message, self.n());
+        self.verify_hash(&m, signature)
+    }
+
+    /// Checks that a signature is a valid root of an already hashed message
+    pub(crate) fn verify_hash(&self, m: &BigUint, signature: &[u8]) -> Result<bool> {
+        Ok(&self.recover_hash(signature)? == m)
+    }
+
+    /// Recovers the value f⁻¹·e·x² (mod n) that a signature is a root of
+    pub(crate) fn recover_hash(&self, signature: &[u8]) -> Result<BigUint> {
         let (e, f, x) = self.extract_signature(signature)?;
 
         // Compute x² mod n
@@ -161,15 +295,36 @@ impl<D: Digest + Clone> PublicKey<D> {
             _ => panic!("unreachable"),
         };
 
-        Ok(result == m)
+        Ok(result)
     }
 
     /// Blinds a message using a random coprime r
-    /// Returns the blinded message hash and the blinding factor r
+    /// Returns the blinded message and the blinding factor r
+    ///
+    /// The message is first encoded with the full-domain hash of the
+    /// `RWBSSA-FDH-Deterministic` suite, so the unblinded signature verifies with
+    /// `verify_with_suite(BlindSuite::FdhDeterministic, message, signature)`.
     pub fn blind_message(&self, message: &[u8]) -> (BigUint, BigUint) {
-        let m = self.hash_fn.hash(message);
+        self.blind_hash(&self.fdh_encode_message(message))
+    }
+
+    /// Blinds `hash_for_modulus` of a message, for `PublicKey::blind`
+    pub(crate) fn blind_unencoded(&self, message: &[u8]) -> (BigUint, BigUint) {
+        let m = self.hash_fn.hash_for_modulus(message, self.n());
+        self.blind_hash(&m)
+    }
+
+    /// Blinds a message bound to a context string
+    ///
+    /// The unblinded signature verifies with `verify_with_context` under the same context.
+    pub fn blind_message_with_context(&self, context: &[u8], message: &[u8]) -> (BigUint, BigUint) {
+        let m = self.hash_fn.hash_with_context_for_modulus(context, message, self.n());
+        self.blind_hash(&m)
+    }
+
+    fn blind_hash(&self, m: &BigUint) -> (BigUint, BigUint) {
         let (r, r_squared) = self.blinding();
-        let blinded_message = &r_squared * &m % self.n();
+        let blinded_message = &r_squared * m % self.n();
         (blinded_message, r)
     }
 
@@ -182,16 +337,113 @@ impl<D: Digest + Clone> PublicKey<D> {
     }
 }
 
-impl<D: Digest + Clone> PrivateKey<D> {
+impl<D: HashFunction> PrivateKey<D> {
     /// Creates a new PrivateKey from primes p and q
     pub fn from_primes(p: BigUint, q: BigUint) -> Self {
         Self {
             p,
             q,
             hash_fn: HashWrapper::default(),
+            usage: KeyUsage::default(),
+            side_channel_blinding: false,
+        }
+    }
+
+    /// Sets what the key may be used for
+    pub fn with_usage(mut self, usage: KeyUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Returns what the key may be used for
+    pub fn usage(&self) -> KeyUsage {
+        self.usage
+    }
+
+    /// Turns blinding of private-key exponentiations on or off
+    ///
+    /// With blinding on, every signature multiplies the input by a fresh random
+    /// square before taking roots, and adds a random multiple of `p - 1` (or
+    /// `q - 1`) to each exponent, including the `(p - 1)/2` and `(q - 1)/2`
+    /// exponents of the residuosity tests that pick the tweak, so the time an
+    /// exponentiation takes no longer depends on the attacker's input or on a
+    /// fixed secret exponent. Signatures are unchanged. Blinding is off by
+    /// default and costs roughly one extra exponentiation per signature; turn
+    /// it on for keys that sign attacker-chosen values.
+    pub fn with_side_channel_blinding(mut self, enabled: bool) -> Self {
+        self.side_channel_blinding = enabled;
+        self
+    }
+
+    /// Returns whether private-key exponentiations are blinded
+    pub fn side_channel_blinding(&self) -> bool {
+        self.side_channel_blinding
+    }
+
+    /// Raises `base` to `exponent` modulo one of the primes
+    ///
+    /// With side-channel blinding on, the exponent is offset by a random multiple
+    /// of `prime - 1`, which does not change the result by Fermat's little theorem.
+    pub(crate) fn prime_modpow(&self, base: &BigUint, exponent: &BigUint, prime: &BigUint) -> BigUint {
+        if !self.side_channel_blinding {
+            return base.modpow(exponent, prime);
+        }
+        let k = rand::thread_rng().gen_biguint(EXPONENT_BLINDING_BITS);
+        base.modpow(&(exponent + k * (prime - 1u32)), prime)
+    }
+
+    /// Returns whether `a` is a square modulo `prime`, by Euler's criterion
+    /// through `prime_modpow`
+    fn is_residue(&self, a: &BigUint, prime: &BigUint) -> bool {
+        self.prime_modpow(&(a % prime), &((prime - 1u32) / 2u32), prime) == BigUint::from(1u32)
+    }
+
+    /// Picks the tweak `(e, f)` that makes `e·f·a` a square modulo both primes
+    ///
+    /// Like `utils::make_quadratic_residue`, but its residuosity tests are
+    /// exponent-blinded whenever side-channel blinding is on.
+    pub(crate) fn make_quadratic_residue(&self, a: &BigUint) -> (BigUint, (i32, u32)) {
+        let n = self.n();
+        let a = a % &n;
+        let negated = (&n - &a) % &n;
+        let candidates = [
+            (a.clone(), 1, 1),
+            (negated.clone(), -1, 1),
+            (a * 2u32 % &n, 1, 2),
+            (negated * 2u32 % &n, -1, 2),
+        ];
+        candidates
+            .into_iter()
+            .find(|(candidate, _, _)| self.is_residue(candidate, &self.p) && self.is_residue(candidate, &self.q))
+            .map(|(candidate, e, f)| (candidate, (e, f)))
+            .expect("No quadratic residue found for given a, p, q")
+    }
+
+    /// Returns the public key, with the same hash function and usage
+    pub fn public_key(&self) -> PublicKey<D> {
+        PublicKey {
+            n: self.n(),
+            hash_fn: self.hash_fn.clone(),
+            usage: self.usage,
         }
     }
 
+    /// Fails with `KeyUsageNotAllowed` unless the key may sign
+    pub(crate) fn require_signing(&self) -> Result<()> {
+        if !self.usage.allows_signing() {
+            return Err(RabinWilliamsError::KeyUsageNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Fails with `KeyUsageNotAllowed` unless the key may decrypt
+    pub(crate) fn require_encryption(&self) -> Result<()> {
+        if !self.usage.allows_encryption() {
+            return Err(RabinWilliamsError::KeyUsageNotAllowed);
+        }
+        Ok(())
+    }
+
     pub fn n(&self) -> BigUint {
         self.p.clone() * self.q.clone()
     }
@@ -208,43 +460,96 @@ impl<D: Digest + Clone> PrivateKey<D> {
     /// - x is the signature
     /// - H(m) is the hash of the message using the configured hash function
     pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
-        let hash = self.hash_fn.hash(message).to_bytes_be();
+        let hash = self.hash_fn.hash_for_modulus(message, &self.n()).to_bytes_be();
+        self.raw_sign(&hash)
+    }
+
+    /// Signs a message bound to a context string
+    ///
+    /// The context and message are length-prefixed before hashing, so a signature
+    /// made under one context never verifies under another. A signature made with
+    /// an empty context is distinct from one made with `sign`.
+    pub fn sign_with_context(&self, context: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+        let hash = self.hash_fn.hash_with_context_for_modulus(context, message, &self.n()).to_bytes_be();
         self.raw_sign(&hash)
     }
 
+    /// Signs a raw message residue without hashing it
+    ///
+    /// The input is not validated. `blind_sign` checks blinded values first, but
+    /// neither is safe to offer to untrusted clients (see [`crate::blind`]).
     pub fn raw_sign(&self, message: &[u8]) -> Result<Vec<u8>> {
-        let m = BigUint::from_bytes_be(&message);
+        let params = self.crt_params()?;
+        self.raw_sign_with_params(message, &params)
+    }
+
+    /// Precomputes the CRT parameters used by every signing operation
+    ///
+    /// Computing these once and passing them to `raw_sign_with_params` avoids
+    /// repeating the modular inverse when signing many messages.
+    pub fn crt_params(&self) -> Result<CrtParams> {
+        let q_inv_p = mod_inverse(&self.q, &self.p).ok_or(RabinWilliamsError::ComputationError)?;
+        Ok(CrtParams {
+            p_exp: (&self.p + 1u32) / 4u32,
+            q_exp: (&self.q + 1u32) / 4u32,
+            q_inv_p,
+        })
+    }
+
+    /// Combines residues modulo p and q into the value modulo n
+    ///
+    /// Uses Garner's form of the CRT: x = rq + q·((rp - rq)·q⁻¹ mod p).
+    pub(crate) fn crt_combine(&self, rp: &BigUint, rq: &BigUint, params: &CrtParams) -> BigUint {
+        let diff = (rp + &self.p - (rq % &self.p)) % &self.p;
+        let h = diff * &params.q_inv_p % &self.p;
+        rq + &self.q * h
+    }
+
+    /// Signs a raw message residue using precomputed CRT parameters
+    pub fn raw_sign_with_params(&self, message: &[u8], params: &CrtParams) -> Result<Vec<u8>> {
+        self.require_signing()?;
+
+        let m = BigUint::from_bytes_be(message);
+        let pair = if self.side_channel_blinding { Some(self.blinding_pair()?) } else { None };
+        Ok(self.sign_residue(&m, params, pair.as_ref()))
+    }
+
+    /// Computes the signature on a residue, blinded with `pair` if one is given
+    ///
+    /// This is the one place that picks the tweak, takes the roots modulo p and
+    /// q and combines them; `raw_sign_with_params` and `sign_with_pool` differ
+    /// only in where the blinding pair comes from.
+    pub(crate) fn sign_residue(&self, m: &BigUint, params: &CrtParams, pair: Option<&BlindingPair>) -> Vec<u8> {
+        // Multiplying by a random square keeps the residuosity of m, so the
+        // tweak below is the same with and without blinding
+        let m = match pair {
+            Some(pair) => m * &pair.r_squared % self.n(),
+            None => m.clone(),
+        };
         
-        let (m, (e, f)) = make_quadratic_residue(&m, &self.p, &self.q);
+        let (m, (e, f)) = self.make_quadratic_residue(&m);
         
         // Compute square roots modulo p and q
         let mp = &m % &self.p;
         let mq = &m % &self.q;
         
         // For p ≡ 3 mod 8, the square root is deterministic
-        let p_plus_1_div_4 = (&self.p + 1u32) / 4u32;
-        let sp = mp.modpow(&p_plus_1_div_4, &self.p);
+        let mut sp = self.prime_modpow(&mp, &params.p_exp, &self.p);
         
         // For q ≡ 7 mod 8, the square root is deterministic
-        let q_plus_1_div_4 = (&self.q + 1u32) / 4u32;
-        let sq = mq.modpow(&q_plus_1_div_4, &self.q);
+        let mut sq = self.prime_modpow(&mq, &params.q_exp, &self.q);
         
-        tracing::debug!("Computed square roots modulo p and q");
+        if let Some(pair) = pair {
+            sp = sp * &pair.unblind_p % &self.p;
+            sq = sq * &pair.unblind_q % &self.q;
+        }
         
-        // Use CRT to combine the results
-        let remainders = vec![
-            sp,
-            sq
-        ];
-        let moduli = vec![
-            self.p.clone(),
-            self.q.clone()
-        ];
+        tracing::debug!("Computed square roots modulo p and q");
         
-        let signature = chinese_remainder_theorem(&remainders, &moduli)?;
+        let signature = self.crt_combine(&sp, &sq, params);
         
         tracing::info!("Successfully generated Rabin-Williams signature with e={}, f={}", e, f);
-        Ok(Self::pack_signature(e, f, &signature))
+        Self::pack_signature(e, f, &signature)
     }
 
     pub fn pack_signature(e: i32, f: u32, x: &BigUint) -> Vec<u8> {
@@ -263,6 +568,10 @@ mod tests {
     use super::*;
     use rand::{thread_rng, Rng};
     use sha2::Sha512;
+    use sha3::Shake256;
+    use crate::blind_suite::BlindSuite;
+    use crate::hash::Xof;
+    use crate::utils::make_quadratic_residue;
 
     // Helper function to generate random message
     fn generate_random_message() -> Vec<u8> {
@@ -315,6 +624,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_crt_params_match_general_crt() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let params = key_pair.private.crt_params()?;
+
+        for _ in 0..10 {
+            let message = generate_random_message();
+            let signature = key_pair.private.raw_sign_with_params(&message, &params)?;
+            let (_e, _f, x) = key_pair.public.extract_signature(&signature)?;
+
+            // The result is the unique root with these residues modulo p and q
+            let residues = [&x % &key_pair.private.p, &x % &key_pair.private.q];
+            let moduli = [key_pair.private.p.clone(), key_pair.private.q.clone()];
+            assert_eq!(crate::utils::chinese_remainder_theorem(&residues, &moduli)?, x);
+            assert_eq!(signature, key_pair.private.raw_sign(&message)?);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_blind_sign_verify() -> Result<()> {
         let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
@@ -329,9 +658,10 @@ mod tests {
         // Unblind the signature
         let unblinded_signature = key_pair.public.unblind_signature(&blinded_signature, &r)?;
 
-        // Verify the unblinded signature
-        let is_valid = key_pair.public.verify(&message, &unblinded_signature)?;
+        // The message was encoded with the FDH suite before blinding
+        let is_valid = key_pair.public.verify_with_suite(BlindSuite::FdhDeterministic, &message, &unblinded_signature)?;
         assert!(is_valid);
+        assert!(!key_pair.public.verify(&message, &unblinded_signature)?);
 
         Ok(())
     }
@@ -364,4 +694,92 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_sign_verify_with_context() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let message = b"Hello, World!";
+
+        let signature = key_pair.private.sign_with_context(b"login", message)?;
+        assert!(key_pair.public.verify_with_context(b"login", message, &signature)?);
+
+        // The signature is bound to its context
+        assert!(!key_pair.public.verify_with_context(b"payment", message, &signature)?);
+        assert!(!key_pair.public.verify(message, &signature)?);
+
+        // Blind signing supports the same separation
+        let (blinded_message, r) = key_pair.public.blind_message_with_context(b"login", message);
+        let blinded_signature = key_pair.private.raw_sign(&blinded_message.to_bytes_be())?;
+        let unblinded_signature = key_pair.public.unblind_signature(&blinded_signature, &r)?;
+        assert!(key_pair.public.verify_with_context(b"login", message, &unblinded_signature)?);
+        assert!(!key_pair.public.verify_with_context(b"payment", message, &unblinded_signature)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_xof_hash() -> Result<()> {
+        let hash_fn = HashWrapper::<Xof<Shake256>>::default();
+        let keypair = KeyPair::generate_with_hash(1024, hash_fn)?;
+
+        // The XOF output should cover the full domain of the modulus
+        let message = b"Hello, World!";
+        let m = keypair.public.hash_fn.hash_for_modulus(message, keypair.public.n());
+        assert!(m.bits() > 1000);
+        assert!(&m < keypair.public.n());
+
+        let signature = keypair.private.sign(message)?;
+        assert!(keypair.public.verify(message, &signature)?);
+        assert!(!keypair.public.verify(b"Goodbye, World!", &signature)?);
+
+        // Blind signing also hashes into the full domain
+        let (blinded_message, r) = keypair.public.blind_unencoded(message);
+        let blinded_signature = keypair.private.raw_sign(&blinded_message.to_bytes_be())?;
+        let unblinded_signature = keypair.public.unblind_signature(&blinded_signature, &r)?;
+        assert!(keypair.public.verify(message, &unblinded_signature)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_side_channel_blinding() -> Result<()> {
+        let keypair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        assert!(!keypair.private.side_channel_blinding());
+        let blinded = keypair.private.clone().with_side_channel_blinding(true);
+        assert!(blinded.side_channel_blinding());
+
+        // Blinding changes how signatures are computed, not what they are
+        for i in 0..8u32 {
+            let message = i.to_be_bytes();
+            assert_eq!(blinded.sign(&message)?, keypair.private.sign(&message)?);
+        }
+
+        // Blinded exponentiation agrees with plain exponentiation
+        let base = BigUint::from(12345u32);
+        let params = keypair.private.crt_params()?;
+        assert_eq!(
+            blinded.prime_modpow(&base, &params.p_exp, &keypair.private.p),
+            base.modpow(&params.p_exp, &keypair.private.p)
+        );
+
+        // and so do the blinded residuosity tests that pick the tweak
+        for a in 1..32u32 {
+            let a = BigUint::from(a);
+            assert_eq!(
+                blinded.make_quadratic_residue(&a),
+                make_quadratic_residue(&a, &keypair.private.p, &keypair.private.q)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_usage_names() {
+        for usage in [KeyUsage::Signing, KeyUsage::Encryption, KeyUsage::SigningAndEncryption] {
+            assert_eq!(usage.to_string().parse::<KeyUsage>().unwrap(), usage);
+        }
+        assert!(matches!("sign".parse::<KeyUsage>(), Err(RabinWilliamsError::UnknownKeyUsage(_))));
+        assert_eq!(KeyUsage::default(), KeyUsage::Signing);
+    }
 }
@@ -0,0 +1,477 @@
+//! File-backed key store
+//!
+//! Keys live in a directory, by default `$XDG_DATA_HOME/rabin-williams/keys`
+//! (falling back to `~/.local/share/rabin-williams/keys`), with one
+//! subdirectory per key named by its hex fingerprint:
+//!
+//! ```text
+//! keys/
+//!   <fingerprint>/
+//!     metadata          name, creation time, size, hash function, usage, status
+//!     public_key.hex    n, in the key file format of [`crate::key_file`]
+//!     private_key.hex   p and q, likewise (absent for imported public keys)
+//! ```
+//!
+//! Keys are looked up by name or by a prefix of their hex fingerprint. A name
+//! refers to the one active key with that name; rotating a key generates a
+//! replacement under the same name and retires the old key, which stays
+//! available by fingerprint for verifying old signatures.
+//!
+//! Private keys are stored unencrypted, readable only by their owner on Unix.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::fingerprint::{hash_algorithm_id, Fingerprint, HASH_ID_LEN};
+use crate::hash::{HashFunction, HashWrapper};
+use crate::key_file::{self, write_file};
+use crate::keys::{KeyPair, KeyUsage, PrivateKey, PublicKey};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Fewest hex digits of a fingerprint accepted as a key reference
+pub const MIN_FINGERPRINT_PREFIX: usize = 8;
+
+const METADATA_FILE: &str = "metadata";
+const PUBLIC_KEY_FILE: &str = "public_key.hex";
+const PRIVATE_KEY_FILE: &str = "private_key.hex";
+
+/// Whether a key is the current key for its name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyStatus {
+    Active,
+    /// Replaced by a rotation; kept for verifying old signatures
+    Retired,
+}
+
+impl KeyStatus {
+    /// Returns the status's name, as written to the metadata file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeyStatus::Active => "active",
+            KeyStatus::Retired => "retired",
+        }
+    }
+}
+
+impl fmt::Display for KeyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What the key store records about a key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyMetadata {
+    name: String,
+    created_at: u64,
+    bits: u64,
+    hash_algorithm: [u8; HASH_ID_LEN],
+    usage: KeyUsage,
+    fingerprint: Fingerprint,
+    status: KeyStatus,
+    has_private_key: bool,
+}
+
+impl KeyMetadata {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns when the key was added to the store, in seconds since the Unix epoch
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// Returns the key size: the bits of the modulus, rounded up to whole bytes
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// Returns the identifier of the key's hash function, see `hash_algorithm_id`
+    pub fn hash_algorithm(&self) -> &[u8; HASH_ID_LEN] {
+        &self.hash_algorithm
+    }
+
+    pub fn usage(&self) -> KeyUsage {
+        self.usage
+    }
+
+    pub fn fingerprint(&self) -> &Fingerprint {
+        &self.fingerprint
+    }
+
+    pub fn status(&self) -> KeyStatus {
+        self.status
+    }
+
+    /// Returns true if the store holds the private key as well as the public key
+    pub fn has_private_key(&self) -> bool {
+        self.has_private_key
+    }
+
+    /// Returns true if the key uses the hash function `D`
+    pub fn uses_hash<D: HashFunction>(&self) -> bool {
+        self.hash_algorithm == hash_algorithm_id::<D>()
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "name={}\ncreated={}\nbits={}\nhash={}\nusage={}\nfingerprint={}\nstatus={}\n",
+            self.name,
+            self.created_at,
+            self.bits,
+            hex::encode(self.hash_algorithm),
+            self.usage,
+            self.fingerprint,
+            self.status,
+        )
+    }
+
+    fn from_text(text: &str, has_private_key: bool) -> Result<Self> {
+        let field = |key: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                .ok_or(RabinWilliamsError::InvalidKeyMetadata)
+        };
+        let number = |key: &str| field(key)?.parse().map_err(|_| RabinWilliamsError::InvalidKeyMetadata);
+
+        let hash_algorithm = hex::decode(field("hash")?)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(RabinWilliamsError::InvalidKeyMetadata)?;
+        let status = match field("status")? {
+            "active" => KeyStatus::Active,
+            "retired" => KeyStatus::Retired,
+            _ => return Err(RabinWilliamsError::InvalidKeyMetadata),
+        };
+
+        Ok(Self {
+            name: field("name")?.to_string(),
+            created_at: number("created")?,
+            bits: number("bits")?,
+            hash_algorithm,
+            usage: field("usage")?.parse().map_err(|_| RabinWilliamsError::InvalidKeyMetadata)?,
+            fingerprint: field("fingerprint")?.parse().map_err(|_| RabinWilliamsError::InvalidKeyMetadata)?,
+            status,
+            has_private_key,
+        })
+    }
+}
+
+/// A directory of named keys
+#[derive(Clone, Debug)]
+pub struct KeyStore {
+    root: PathBuf,
+}
+
+impl KeyStore {
+    /// Opens the key store at `root`, creating the directory if needed
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|_| RabinWilliamsError::KeyStoreError)?;
+        Ok(Self { root })
+    }
+
+    /// Opens the key store at `default_root()`
+    pub fn open_default() -> Result<Self> {
+        Self::open(Self::default_root()?)
+    }
+
+    /// Returns `$XDG_DATA_HOME/rabin-williams/keys`, or
+    /// `$HOME/.local/share/rabin-williams/keys` if `XDG_DATA_HOME` is unset or relative
+    pub fn default_root() -> Result<PathBuf> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+            .ok_or(RabinWilliamsError::KeyStoreError)?;
+        Ok(data_home.join("rabin-williams").join("keys"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns every key in the store, sorted by name and then by creation time
+    pub fn list(&self) -> Result<Vec<KeyMetadata>> {
+        let entries = fs::read_dir(&self.root).map_err(|_| RabinWilliamsError::KeyStoreError)?;
+        let mut keys = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|_| RabinWilliamsError::KeyStoreError)?.path();
+            if path.join(METADATA_FILE).is_file() {
+                keys.push(read_metadata(&path)?);
+            }
+        }
+        keys.sort_by(|a, b| (&a.name, a.created_at).cmp(&(&b.name, b.created_at)));
+        Ok(keys)
+    }
+
+    /// Finds a key by the name of an active key or by a fingerprint prefix
+    ///
+    /// Fingerprint prefixes must have at least `MIN_FINGERPRINT_PREFIX` hex
+    /// digits. Fails with `KeyNotFound` if nothing matches and `AmbiguousKey`
+    /// if more than one key does, including when a name and another key's
+    /// fingerprint both match.
+    pub fn find(&self, query: &str) -> Result<KeyMetadata> {
+        let keys = self.list()?;
+        let prefix = is_fingerprint_prefix(query).then(|| query.to_ascii_lowercase());
+        let matches: Vec<&KeyMetadata> = keys
+            .iter()
+            .filter(|key| {
+                (key.status == KeyStatus::Active && key.name == query)
+                    || prefix.as_ref().is_some_and(|prefix| key.fingerprint.to_hex().starts_with(prefix))
+            })
+            .collect();
+
+        match matches.as_slice() {
+            [key] => Ok((*key).clone()),
+            [] => Err(RabinWilliamsError::KeyNotFound),
+            _ => Err(RabinWilliamsError::AmbiguousKey),
+        }
+    }
+
+    /// Generates a key pair and stores it under `name`
+    pub fn generate<D: HashFunction>(&self, name: &str, bits: usize, usage: KeyUsage) -> Result<KeyMetadata> {
+        let key_pair = KeyPair::<D>::generate_with_hash(bits, HashWrapper::default())?.with_usage(usage);
+        self.import(name, &key_pair.public, Some(&key_pair.private))
+    }
+
+    /// Stores a public key, and optionally its private key, under `name`
+    ///
+    /// Fails with `InvalidKeyName` unless the name is non-empty and made of ASCII
+    /// letters, digits, `.`, `_` and `-` and could not be read as a fingerprint
+    /// prefix, with `DuplicateKey` if an active key
+    /// already has the name or the key is already stored, and with
+    /// `KeyMismatch` if the private key does not match the public key.
+    pub fn import<D: HashFunction>(
+        &self,
+        name: &str,
+        public_key: &PublicKey<D>,
+        private_key: Option<&PrivateKey<D>>,
+    ) -> Result<KeyMetadata> {
+        validate_name(name)?;
+        if self.list()?.iter().any(|key| key.status == KeyStatus::Active && key.name == name) {
+            return Err(RabinWilliamsError::DuplicateKey);
+        }
+        self.store(name, public_key, private_key)
+    }
+
+    /// Returns the public key of a stored key
+    ///
+    /// Fails with `WrongHashFunction` if the key was stored for another hash function.
+    pub fn public_key<D: HashFunction>(&self, query: &str) -> Result<PublicKey<D>> {
+        let metadata = self.find_for_hash::<D>(query)?;
+        key_file::load_public_key(&self.key_dir(&metadata).join(PUBLIC_KEY_FILE))
+    }
+
+    /// Returns the private key of a stored key
+    ///
+    /// Fails with `NoPrivateKey` if only the public key is stored and with
+    /// `WrongHashFunction` if the key was stored for another hash function.
+    pub fn private_key<D: HashFunction>(&self, query: &str) -> Result<PrivateKey<D>> {
+        let metadata = self.find_for_hash::<D>(query)?;
+        if !metadata.has_private_key {
+            return Err(RabinWilliamsError::NoPrivateKey);
+        }
+        key_file::load_private_key(&self.key_dir(&metadata).join(PRIVATE_KEY_FILE))
+    }
+
+    /// Removes a key from the store and returns what was recorded about it
+    pub fn delete(&self, query: &str) -> Result<KeyMetadata> {
+        let metadata = self.find(query)?;
+        fs::remove_dir_all(self.key_dir(&metadata)).map_err(|_| RabinWilliamsError::KeyStoreError)?;
+        Ok(metadata)
+    }
+
+    /// Replaces the active key called `name` with a new key of the same size
+    /// and usage, retiring the old key
+    ///
+    /// Returns the new key's metadata.
+    pub fn rotate<D: HashFunction>(&self, name: &str) -> Result<KeyMetadata> {
+        let old = self.find_for_hash::<D>(name)?;
+        if old.name != name || old.status != KeyStatus::Active {
+            return Err(RabinWilliamsError::KeyNotFound);
+        }
+        let key_pair = KeyPair::<D>::generate_with_hash(old.bits as usize, HashWrapper::default())?
+            .with_usage(old.usage);
+
+        self.write_metadata(&KeyMetadata { status: KeyStatus::Retired, ..old.clone() })?;
+        self.store(name, &key_pair.public, Some(&key_pair.private)).inspect_err(|_| {
+            // Leave the old key active if the new one could not be stored
+            let _ = self.write_metadata(&old);
+        })
+    }
+
+    fn find_for_hash<D: HashFunction>(&self, query: &str) -> Result<KeyMetadata> {
+        let metadata = self.find(query)?;
+        if !metadata.uses_hash::<D>() {
+            return Err(RabinWilliamsError::WrongHashFunction);
+        }
+        Ok(metadata)
+    }
+
+    fn key_dir(&self, metadata: &KeyMetadata) -> PathBuf {
+        self.root.join(metadata.fingerprint.to_hex())
+    }
+
+    fn store<D: HashFunction>(
+        &self,
+        name: &str,
+        public_key: &PublicKey<D>,
+        private_key: Option<&PrivateKey<D>>,
+    ) -> Result<KeyMetadata> {
+        if private_key.is_some_and(|private_key| &private_key.n() != public_key.n()) {
+            return Err(RabinWilliamsError::KeyMismatch);
+        }
+        let metadata = KeyMetadata {
+            name: name.to_string(),
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs()),
+            bits: public_key.n().bits().div_ceil(8) * 8,
+            hash_algorithm: hash_algorithm_id::<D>(),
+            usage: public_key.usage(),
+            fingerprint: public_key.fingerprint(),
+            status: KeyStatus::Active,
+            has_private_key: private_key.is_some(),
+        };
+
+        let dir = self.key_dir(&metadata);
+        match fs::create_dir(&dir) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Err(RabinWilliamsError::DuplicateKey),
+            Err(_) => return Err(RabinWilliamsError::KeyStoreError),
+        }
+        let written = (|| {
+            key_file::save_public_key(public_key, &dir.join(PUBLIC_KEY_FILE))?;
+            if let Some(private_key) = private_key {
+                key_file::save_private_key(private_key, &dir.join(PRIVATE_KEY_FILE))?;
+            }
+            // Written last, so a key without metadata is never listed
+            self.write_metadata(&metadata)
+        })();
+        if written.is_err() {
+            let _ = fs::remove_dir_all(&dir);
+        }
+        written.map(|()| metadata)
+    }
+
+    fn write_metadata(&self, metadata: &KeyMetadata) -> Result<()> {
+        write_file(&self.key_dir(metadata).join(METADATA_FILE), &metadata.to_text(), false)
+            .map_err(|_| RabinWilliamsError::KeyStoreError)
+    }
+}
+
+fn read_metadata(dir: &Path) -> Result<KeyMetadata> {
+    let text = fs::read_to_string(dir.join(METADATA_FILE)).map_err(|_| RabinWilliamsError::KeyStoreError)?;
+    KeyMetadata::from_text(&text, dir.join(PRIVATE_KEY_FILE).is_file())
+}
+
+fn is_fingerprint_prefix(query: &str) -> bool {
+    query.len() >= MIN_FINGERPRINT_PREFIX && query.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && !is_fingerprint_prefix(name)
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"._-".contains(&b));
+    if valid {
+        Ok(())
+    } else {
+        Err(RabinWilliamsError::InvalidKeyName)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha256;
+    use sha3::Keccak256;
+
+    fn temporary_store(label: &str) -> Result<KeyStore> {
+        let nonce: u64 = rand::random();
+        KeyStore::open(std::env::temp_dir().join(format!("rw-keystore-{}-{:016x}", label, nonce)))
+    }
+
+    #[test]
+    fn test_generate_find_and_load() -> Result<()> {
+        let store = temporary_store("load")?;
+        let metadata = store.generate::<Sha256>("alice", 1024, KeyUsage::SigningAndEncryption)?;
+        assert_eq!(metadata.bits(), 1024);
+        assert!(metadata.has_private_key() && metadata.uses_hash::<Sha256>());
+
+        // By name and by fingerprint prefix
+        assert_eq!(store.find("alice")?, metadata);
+        assert_eq!(store.find(&metadata.fingerprint().to_hex()[..MIN_FINGERPRINT_PREFIX])?, metadata);
+        assert!(matches!(store.find(&metadata.fingerprint().to_hex()[..4]), Err(RabinWilliamsError::KeyNotFound)));
+        assert!(matches!(store.find("bob"), Err(RabinWilliamsError::KeyNotFound)));
+
+        let private_key = store.private_key::<Sha256>("alice")?;
+        let public_key = store.public_key::<Sha256>("alice")?;
+        assert_eq!(public_key.fingerprint(), *metadata.fingerprint());
+        assert_eq!(public_key.usage(), KeyUsage::SigningAndEncryption);
+        let signature = private_key.sign(b"message")?;
+        assert!(public_key.verify(b"message", &signature)?);
+
+        assert!(matches!(store.public_key::<Keccak256>("alice"), Err(RabinWilliamsError::WrongHashFunction)));
+
+        // The key survives reopening the store
+        let reopened = KeyStore::open(store.root())?;
+        assert_eq!(reopened.list()?, vec![metadata]);
+
+        fs::remove_dir_all(store.root()).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_and_delete() -> Result<()> {
+        let store = temporary_store("import")?;
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let metadata = store.import("bob", &key_pair.public, None)?;
+        assert!(!metadata.has_private_key());
+        assert!(matches!(store.private_key::<Sha256>("bob"), Err(RabinWilliamsError::NoPrivateKey)));
+
+        assert!(matches!(store.import("bob", &other.public, None), Err(RabinWilliamsError::DuplicateKey)));
+        assert!(matches!(store.import("carol", &key_pair.public, None), Err(RabinWilliamsError::DuplicateKey)));
+        assert!(matches!(
+            store.import("carol", &other.public, Some(&key_pair.private)),
+            Err(RabinWilliamsError::KeyMismatch)
+        ));
+        // Names that could be read as a fingerprint prefix are rejected
+        let prefix = &key_pair.public.fingerprint().to_hex()[..MIN_FINGERPRINT_PREFIX];
+        for name in ["", ".hidden", "a/b", "a b", prefix, "DEADBEEF"] {
+            assert!(matches!(store.import(name, &other.public, None), Err(RabinWilliamsError::InvalidKeyName)));
+        }
+
+        assert_eq!(store.delete("bob")?, metadata);
+        assert!(store.list()?.is_empty());
+
+        fs::remove_dir_all(store.root()).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotate() -> Result<()> {
+        let store = temporary_store("rotate")?;
+        let old = store.generate::<Sha256>("alice", 1024, KeyUsage::Signing)?;
+        let signature = store.private_key::<Sha256>("alice")?.sign(b"message")?;
+
+        let new = store.rotate::<Sha256>("alice")?;
+        assert_ne!(new.fingerprint(), old.fingerprint());
+        assert_eq!((new.bits(), new.usage()), (old.bits(), old.usage()));
+
+        // The name now refers to the new key; the old one is kept, retired
+        assert_eq!(store.find("alice")?, new);
+        let retired = store.find(&old.fingerprint().to_hex())?;
+        assert_eq!(retired.status(), KeyStatus::Retired);
+        assert!(store.public_key::<Sha256>(&old.fingerprint().to_hex())?.verify(b"message", &signature)?);
+        assert_eq!(store.list()?.len(), 2);
+
+        assert!(matches!(store.rotate::<Sha256>("bob"), Err(RabinWilliamsError::KeyNotFound)));
+
+        fs::remove_dir_all(store.root()).ok();
+        Ok(())
+    }
+}
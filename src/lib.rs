@@ -6,10 +6,33 @@ pub mod errors;
 pub mod keys;
 pub mod utils;
 pub mod hash;
+pub mod ethereum;
+pub mod batch;
+pub mod merkle;
+pub mod blind;
+pub mod blind_suite;
+#[cfg(feature = "insecure-demo")]
+pub mod ecash;
+pub mod encryption;
+pub mod kem;
+pub mod modulus_proof;
+pub mod ring;
+pub mod vrf;
+pub mod pool;
+pub mod shamir;
+pub mod two_party;
+pub mod dkg;
+pub mod fingerprint;
+pub mod key_file;
+pub mod keystore;
+pub mod container;
 
-pub use keys::{PublicKey, PrivateKey, KeyPair};
-pub use hash::{HashWrapper, Sha256Hash};
+pub use keys::{PublicKey, PrivateKey, KeyPair, KeyUsage, CrtParams};
+pub use hash::{HashFunction, HashWrapper, Xof, Sha256Hash, Keccak256Hash, Shake128Hash, Shake256Hash, Blake3Hash};
+pub use blind::{BlindingRequest, BlindedMessage, BlindSignature, BlindingState};
+pub use blind_suite::{BlindSuite, SuiteBlindingState};
 pub use errors::RabinWilliamsError;
+pub use fingerprint::Fingerprint;
 
 /// Re-export commonly used types from num-bigint
 pub use num_bigint::{BigUint, BigInt};
@@ -1,10 +1,22 @@
-use clap::{Parser, Subcommand};
-use rabin_williams::{KeyPair, PublicKey, PrivateKey, HashWrapper};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use rabin_williams::{BigUint, KeyPair, KeyUsage, PublicKey, PrivateKey, HashFunction, HashWrapper, BlindedMessage, BlindSuite};
+use rabin_williams::container::{MessageEncoding, SignatureContainer, SignatureMetadata};
+#[cfg(feature = "insecure-demo")]
+use rabin_williams::ecash::{Coin, FileSpendStore, Mint, MintPublicKeys};
+use rabin_williams::ethereum::eip191_personal_message;
+use rabin_williams::fingerprint::{hash_algorithm_id, HASH_ID_LEN};
+use rabin_williams::kem;
+use rabin_williams::key_file::{load_private_key, load_public_key, save_private_key, save_public_key, save_secret_file};
+use rabin_williams::keystore::{KeyMetadata, KeyStore};
+use rabin_williams::modulus_proof::ModulusProof;
+use rabin_williams::shamir::KeyShare;
 use rabin_williams::errors::Result;
 use sha2::Sha256;
+use sha3::Keccak256;
 use std::fs;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Parser)]
 #[command(name = "rabin-williams")]
@@ -14,6 +26,46 @@ struct Cli {
     command: Commands,
 }
 
+/// Hash functions selectable from the command line
+#[derive(Clone, Copy, ValueEnum)]
+enum HashAlgorithm {
+    /// SHA-256, matching `RabinWilliamsVerifier.verify`
+    Sha256,
+    /// Keccak-256, matching digests computed with Solidity's `keccak256`
+    Keccak256,
+}
+
+impl HashAlgorithm {
+    /// Finds the hash function with a key encoding identifier
+    fn from_id(id: &[u8; HASH_ID_LEN]) -> Result<Self> {
+        if *id == hash_algorithm_id::<Sha256>() {
+            Ok(HashAlgorithm::Sha256)
+        } else if *id == hash_algorithm_id::<Keccak256>() {
+            Ok(HashAlgorithm::Keccak256)
+        } else {
+            Err(rabin_williams::RabinWilliamsError::WrongHashFunction)
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Keccak256 => "keccak256",
+        }
+    }
+}
+
+/// Formats `sign` can write signatures in
+#[derive(Clone, Copy, ValueEnum)]
+enum SignatureFormat {
+    /// Signature container as armored text
+    Armored,
+    /// Signature container in the binary format
+    Binary,
+    /// Bare hex signature without metadata, as verified on-chain
+    Hex,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Generate a new key pair
@@ -29,13 +81,30 @@ enum Commands {
         /// Output file for the private key (hex-encoded p and q, one per line)
         #[arg(long, default_value = "private_key.hex")]
         private_key: PathBuf,
+        
+        /// What the key pair may be used for: signing, encryption or signing-and-encryption
+        #[arg(short, long, default_value_t = KeyUsage::Signing)]
+        usage: KeyUsage,
+        
+        /// Also write a proof that the modulus is well formed to this file (hex-encoded)
+        #[arg(long)]
+        modulus_proof: Option<PathBuf>,
     },
     
     /// Sign a message
+    #[command(group(ArgGroup::new("signing_key").required(true).args(["private_key", "key"])))]
     Sign {
         /// Path to the private key file
         #[arg(short = 'k', long)]
-        private_key: PathBuf,
+        private_key: Option<PathBuf>,
+        
+        /// Name or fingerprint of a key in the key store
+        #[arg(long)]
+        key: Option<String>,
+        
+        /// Key store directory (defaults to $XDG_DATA_HOME/rabin-williams/keys)
+        #[arg(long)]
+        keystore: Option<PathBuf>,
         
         /// Message to sign (if not provided, reads from stdin)
         #[arg(short, long)]
@@ -44,13 +113,93 @@ enum Commands {
         /// Output file for the signature (if not provided, writes to stdout)
         #[arg(short = 'o', long)]
         output: Option<PathBuf>,
+        
+        /// Context string that domain-separates this signature from other message types
+        #[arg(short = 'c', long)]
+        context: Option<String>,
+        
+        /// Hash function applied to the message (defaults to the stored key's, or SHA-256 for key files)
+        #[arg(long, value_enum)]
+        hash: Option<HashAlgorithm>,
+        
+        /// Encode the message as an EIP-191 personal message before hashing
+        #[arg(long)]
+        eip191: bool,
+        
+        /// Signature format
+        #[arg(short = 'f', long, value_enum, default_value_t = SignatureFormat::Armored)]
+        format: SignatureFormat,
+        
+        /// Leave the creation time out of the signature container
+        #[arg(long)]
+        no_timestamp: bool,
+        
+        /// Signed attribute for the signature container, as name=value (repeatable)
+        #[arg(short = 'a', long = "attribute", value_parser = parse_attribute)]
+        attributes: Vec<(String, String)>,
+    },
+    
+    /// Sign every file in a directory or every line of a file
+    #[command(group(ArgGroup::new("input").required(true).args(["dir", "lines"])))]
+    SignBatch {
+        /// Path to the private key file
+        #[arg(short = 'k', long)]
+        private_key: PathBuf,
+        
+        /// Sign every file in this directory, writing `<name>.sig` for each
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        
+        /// Sign every line of this file, writing one signature per line
+        #[arg(long)]
+        lines: Option<PathBuf>,
+        
+        /// Output directory (with --dir, defaults to the input directory) or
+        /// output file (with --lines, defaults to stdout)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+        
+        /// Hash function applied to each message
+        #[arg(long, value_enum, default_value_t = HashAlgorithm::Sha256)]
+        hash: HashAlgorithm,
+    },
+    
+    /// Sign every line of a file with one signature over their Merkle root
+    SignMerkle {
+        /// Path to the private key file
+        #[arg(short = 'k', long)]
+        private_key: PathBuf,
+        
+        /// File whose lines are the messages to sign
+        #[arg(long)]
+        lines: PathBuf,
+        
+        /// Output file for the root signature (hex-encoded)
+        #[arg(short = 's', long, default_value = "merkle_signature.hex")]
+        signature: PathBuf,
+        
+        /// Output file for the inclusion proofs (one hex-encoded proof per line)
+        #[arg(short = 'p', long, default_value = "merkle_proofs.txt")]
+        proofs: PathBuf,
     },
     
     /// Verify a signature
+    ///
+    /// Signature containers name their signing key, which is looked up in the
+    /// key store unless a key file or key is given.
+    #[command(group(ArgGroup::new("verifying_key").args(["public_key", "key"])))]
     Verify {
         /// Path to the public key file
         #[arg(short = 'k', long)]
-        public_key: PathBuf,
+        public_key: Option<PathBuf>,
+        
+        /// Name or fingerprint of a key in the key store
+        #[arg(long)]
+        key: Option<String>,
+        
+        /// Key store directory (defaults to $XDG_DATA_HOME/rabin-williams/keys)
+        #[arg(long)]
+        keystore: Option<PathBuf>,
         
         /// Path to the signature file
         #[arg(short = 's', long)]
@@ -59,6 +208,24 @@ enum Commands {
         /// Message to verify (if not provided, reads from stdin)
         #[arg(short, long)]
         message: Option<String>,
+        
+        /// Context string that domain-separates this signature from other message types
+        #[arg(short = 'c', long)]
+        context: Option<String>,
+        
+        /// Hash function applied to the message, for hex signatures (defaults to the stored
+        /// key's, or SHA-256 for key files; containers record it)
+        #[arg(long, value_enum)]
+        hash: Option<HashAlgorithm>,
+        
+        /// Encode the message as an EIP-191 personal message before hashing (hex signatures only)
+        #[arg(long)]
+        eip191: bool,
+        
+        /// Verify a blind signature made under this suite, such as RWBSSA-FDH-Deterministic
+        /// for signatures unblinded from `blind` without a context (hex signatures only)
+        #[arg(long, value_parser = parse_suite, conflicts_with_all = ["context", "eip191"])]
+        suite: Option<BlindSuite>,
     },
     
     /// Perform blind signing
@@ -93,6 +260,14 @@ enum Commands {
         /// Output file for the blinding factor r (hex-encoded)
         #[arg(short = 'r', long, default_value = "blinding_factor.hex")]
         blinding_factor: PathBuf,
+        
+        /// Context string that domain-separates this signature from other message types
+        #[arg(short = 'c', long)]
+        context: Option<String>,
+        
+        /// Refuse to blind unless this proof shows the signer's modulus is well formed
+        #[arg(long)]
+        modulus_proof: Option<PathBuf>,
     },
     
     /// Unblind a signature after blind signing
@@ -113,85 +288,434 @@ enum Commands {
         #[arg(short = 'o', long)]
         output: Option<PathBuf>,
     },
+    
+    /// Show a public key's fingerprint
+    Fingerprint {
+        /// Path to the public key file
+        #[arg(short = 'k', long)]
+        public_key: PathBuf,
+        
+        /// Hash function the key signs with, which is part of the fingerprint
+        #[arg(long, value_enum, default_value_t = HashAlgorithm::Sha256)]
+        hash: HashAlgorithm,
+    },
+    
+    /// Prove that a key's modulus is a well-formed Rabin-Williams modulus
+    ProveModulus {
+        /// Path to the private key file
+        #[arg(short = 'k', long)]
+        private_key: PathBuf,
+        
+        /// Output file for the proof (hex-encoded)
+        #[arg(short = 'o', long, default_value = "modulus_proof.hex")]
+        output: PathBuf,
+    },
+    
+    /// Check a proof that a public key's modulus is well formed
+    VerifyModulus {
+        /// Path to the public key file
+        #[arg(short = 'k', long)]
+        public_key: PathBuf,
+        
+        /// Path to the proof file
+        #[arg(short = 'p', long, default_value = "modulus_proof.hex")]
+        proof: PathBuf,
+    },
+    
+    /// Encrypt a short message to a public key created with `--usage encryption`
+    Encrypt {
+        /// Path to the public key file
+        #[arg(short = 'k', long)]
+        public_key: PathBuf,
+        
+        /// Message to encrypt (if not provided, reads from stdin)
+        #[arg(short, long)]
+        message: Option<String>,
+        
+        /// Output file for the ciphertext (if not provided, writes to stdout)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Decrypt a ciphertext produced by `encrypt`
+    Decrypt {
+        /// Path to the private key file
+        #[arg(short = 'k', long)]
+        private_key: PathBuf,
+        
+        /// Path to the ciphertext file (hex-encoded)
+        #[arg(short = 'c', long)]
+        ciphertext: PathBuf,
+        
+        /// Output file for the plaintext (if not provided, writes to stdout)
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Encrypt a file of any size to a public key created with `--usage encryption`
+    EncryptFile {
+        /// Path to the public key file
+        #[arg(short = 'k', long)]
+        public_key: PathBuf,
+        
+        /// File to encrypt
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Output file for the encrypted data (binary)
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    
+    /// Decrypt a file produced by `encrypt-file`
+    DecryptFile {
+        /// Path to the private key file
+        #[arg(short = 'k', long)]
+        private_key: PathBuf,
+        
+        /// File to decrypt
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Output file for the decrypted data
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    
+    /// Split a private key into Shamir shares for offline backup
+    SplitKey {
+        /// Path to the private key file
+        #[arg(short = 'k', long)]
+        private_key: PathBuf,
+        
+        /// Number of shares needed to recover the key
+        #[arg(short, long)]
+        threshold: u8,
+        
+        /// Number of shares to create
+        #[arg(short = 'n', long)]
+        shares: u8,
+        
+        /// Directory for the share files (share-<index>.hex)
+        #[arg(short = 'o', long, default_value = ".")]
+        output_dir: PathBuf,
+    },
+    
+    /// Recover a private key from Shamir shares created by `split-key`
+    RecoverKey {
+        /// Path to the public key file the shares belong to
+        #[arg(short = 'k', long)]
+        public_key: PathBuf,
+        
+        /// Share files (hex-encoded)
+        #[arg(required = true)]
+        shares: Vec<PathBuf>,
+        
+        /// Output file for the recovered private key
+        #[arg(short = 'o', long, default_value = "private_key.hex")]
+        output: PathBuf,
+    },
+    
+    /// Run a local e-cash demonstration mint: withdraw, spend and deposit coins
+    ///
+    /// Not secure against malicious clients: any caller can factor a
+    /// denomination's key through withdrawals. Built only with the
+    /// `insecure-demo` feature.
+    #[cfg(feature = "insecure-demo")]
+    Ecash {
+        #[command(subcommand)]
+        command: EcashCommands,
+    },
+    
+    /// Manage named keys in the key store
+    Keys {
+        /// Key store directory (defaults to $XDG_DATA_HOME/rabin-williams/keys)
+        #[arg(long, global = true)]
+        keystore: Option<PathBuf>,
+        
+        #[command(subcommand)]
+        command: KeysCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeysCommands {
+    /// List stored keys with their metadata
+    List,
+    
+    /// Generate a key pair and store it under a name
+    Generate {
+        /// Name for the key
+        name: String,
+        
+        /// Bit size for the key (minimum 1024)
+        #[arg(short, long, default_value_t = 1024)]
+        bits: usize,
+        
+        /// What the key pair may be used for: signing, encryption or signing-and-encryption
+        #[arg(short, long, default_value_t = KeyUsage::Signing)]
+        usage: KeyUsage,
+        
+        /// Hash function the key signs with
+        #[arg(long, value_enum, default_value_t = HashAlgorithm::Sha256)]
+        hash: HashAlgorithm,
+    },
+    
+    /// Store key files under a name
+    Import {
+        /// Name for the key
+        name: String,
+        
+        /// Path to the public key file
+        #[arg(long)]
+        public_key: PathBuf,
+        
+        /// Path to the private key file (omit to store only the public key)
+        #[arg(long)]
+        private_key: Option<PathBuf>,
+        
+        /// Hash function the key signs with
+        #[arg(long, value_enum, default_value_t = HashAlgorithm::Sha256)]
+        hash: HashAlgorithm,
+    },
+    
+    /// Write a stored key to key files
+    Export {
+        /// Name or fingerprint of the key
+        key: String,
+        
+        /// Output file for the public key
+        #[arg(long, default_value = "public_key.hex")]
+        public_key: PathBuf,
+        
+        /// Also write the private key to this file
+        #[arg(long)]
+        private_key: Option<PathBuf>,
+    },
+    
+    /// Remove a key from the store
+    Delete {
+        /// Name or fingerprint of the key
+        key: String,
+    },
+    
+    /// Replace a named key with a new one of the same size and usage, retiring the old key
+    Rotate {
+        /// Name of the key
+        name: String,
+    },
+}
+
+#[cfg(feature = "insecure-demo")]
+#[derive(Subcommand)]
+enum EcashCommands {
+    /// Create a mint directory with one key pair per denomination
+    Init {
+        /// Mint directory
+        #[arg(long, default_value = "mint")]
+        mint: PathBuf,
+        
+        /// Denominations the mint issues, comma-separated
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        denominations: Vec<u64>,
+        
+        /// Bit size for each key (minimum 1024)
+        #[arg(short, long, default_value_t = 1024)]
+        bits: usize,
+    },
+    
+    /// Withdraw a coin: blind a fresh serial, have the mint sign it and unblind the result
+    Withdraw {
+        /// Mint directory
+        #[arg(long, default_value = "mint")]
+        mint: PathBuf,
+        
+        /// Denomination of the coin
+        #[arg(short, long)]
+        denomination: u64,
+        
+        /// Output file for the coin (hex-encoded)
+        #[arg(short = 'o', long, default_value = "coin.hex")]
+        coin: PathBuf,
+    },
+    
+    /// Check a coin as a merchant would before accepting it
+    Spend {
+        /// Mint directory (only the public keys are read)
+        #[arg(long, default_value = "mint")]
+        mint: PathBuf,
+        
+        /// Path to the coin file
+        #[arg(short, long, default_value = "coin.hex")]
+        coin: PathBuf,
+    },
+    
+    /// Deposit a coin at the mint, rejecting coins that were already deposited
+    Deposit {
+        /// Mint directory
+        #[arg(long, default_value = "mint")]
+        mint: PathBuf,
+        
+        /// Path to the coin file
+        #[arg(short, long, default_value = "coin.hex")]
+        coin: PathBuf,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
     
     if let Err(e) = match cli.command {
-        Commands::Generate { bits, public_key, private_key } => {
-            generate_keypair(bits, &public_key, &private_key)
+        Commands::Generate { bits, public_key, private_key, usage, modulus_proof } => {
+            generate_keypair(bits, &public_key, &private_key, usage, modulus_proof.as_deref())
         }
-        Commands::Sign { private_key, message, output } => {
-            sign_message(&private_key, message.as_deref(), output.as_ref())
+        Commands::Sign {
+            private_key, key, keystore, message, output, context, hash, eip191, format, no_timestamp, attributes,
+        } => {
+            let source = KeySource::new(private_key.as_ref(), key.as_deref(), keystore.as_deref())
+                .expect("clap requires a key file or a key name");
+            let (message, output, context) = (message.as_deref(), output.as_ref(), context.as_deref());
+            let options = SignOptions { eip191, format, timestamp: !no_timestamp, attributes: &attributes };
+            match hash.map_or_else(|| source.default_hash(), Ok) {
+                Ok(HashAlgorithm::Sha256) => sign_message::<Sha256>(source, message, output, context, options),
+                Ok(HashAlgorithm::Keccak256) => sign_message::<Keccak256>(source, message, output, context, options),
+                Err(e) => Err(e),
+            }
         }
-        Commands::Verify { public_key, signature, message } => {
-            verify_signature(&public_key, &signature, message.as_deref())
+        Commands::SignBatch { private_key, dir, lines, output, hash } => match (dir, lines, hash) {
+            (Some(dir), _, HashAlgorithm::Sha256) => sign_directory::<Sha256>(&private_key, &dir, output.as_ref()),
+            (Some(dir), _, HashAlgorithm::Keccak256) => sign_directory::<Keccak256>(&private_key, &dir, output.as_ref()),
+            (None, Some(lines), HashAlgorithm::Sha256) => sign_lines::<Sha256>(&private_key, &lines, output.as_ref()),
+            (None, Some(lines), HashAlgorithm::Keccak256) => sign_lines::<Keccak256>(&private_key, &lines, output.as_ref()),
+            (None, None, _) => unreachable!("clap requires --dir or --lines"),
+        },
+        Commands::SignMerkle { private_key, lines, signature, proofs } => {
+            sign_merkle(&private_key, &lines, &signature, &proofs)
+        }
+        Commands::Verify { public_key, key, keystore, signature, message, context, hash, eip191, suite } => {
+            let source = KeySource::new(public_key.as_ref(), key.as_deref(), keystore.as_deref());
+            let (message, context) = (message.as_deref(), context.as_deref());
+            let options = VerifyOptions { hash, eip191, suite };
+            verify_signature(source, keystore.as_deref(), &signature, message, context, options)
         }
         Commands::BlindSign { private_key, blinded_message, output } => {
             blind_sign(&private_key, &blinded_message, output.as_ref())
         }
-        Commands::Blind { public_key, message, blinded_message, blinding_factor } => {
-            blind_message(&public_key, message.as_deref(), &blinded_message, &blinding_factor)
+        Commands::Blind { public_key, message, blinded_message, blinding_factor, context, modulus_proof } => {
+            blind_message(&public_key, message.as_deref(), &blinded_message, &blinding_factor, context.as_deref(), modulus_proof.as_deref())
         }
         Commands::Unblind { public_key, blinded_signature, blinding_factor, output } => {
             unblind_signature(&public_key, &blinded_signature, &blinding_factor, output.as_ref())
         }
+        Commands::Fingerprint { public_key, hash } => match hash {
+            HashAlgorithm::Sha256 => show_fingerprint::<Sha256>(&public_key),
+            HashAlgorithm::Keccak256 => show_fingerprint::<Keccak256>(&public_key),
+        },
+        Commands::ProveModulus { private_key, output } => prove_modulus(&private_key, &output),
+        Commands::VerifyModulus { public_key, proof } => verify_modulus(&public_key, &proof),
+        Commands::Encrypt { public_key, message, output } => {
+            encrypt_message(&public_key, message.as_deref(), output.as_ref())
+        }
+        Commands::Decrypt { private_key, ciphertext, output } => {
+            decrypt_message(&private_key, &ciphertext, output.as_ref())
+        }
+        Commands::EncryptFile { public_key, input, output } => encrypt_file(&public_key, &input, &output),
+        Commands::DecryptFile { private_key, input, output } => decrypt_file(&private_key, &input, &output),
+        Commands::SplitKey { private_key, threshold, shares, output_dir } => {
+            split_key(&private_key, threshold, shares, &output_dir)
+        }
+        Commands::RecoverKey { public_key, shares, output } => recover_key(&public_key, &shares, &output),
+        #[cfg(feature = "insecure-demo")]
+        Commands::Ecash { command } => match command {
+            EcashCommands::Init { mint, denominations, bits } => ecash_init(&mint, &denominations, bits),
+            EcashCommands::Withdraw { mint, denomination, coin } => ecash_withdraw(&mint, denomination, &coin),
+            EcashCommands::Spend { mint, coin } => ecash_spend(&mint, &coin),
+            EcashCommands::Deposit { mint, coin } => ecash_deposit(&mint, &coin),
+        },
+        Commands::Keys { keystore, command } => keys_command(keystore.as_deref(), command),
     } {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
 }
 
-fn generate_keypair(bits: usize, public_key_path: &PathBuf, private_key_path: &PathBuf) -> Result<()> {
+fn generate_keypair(
+    bits: usize,
+    public_key_path: &Path,
+    private_key_path: &Path,
+    usage: KeyUsage,
+    modulus_proof_path: Option<&Path>,
+) -> Result<()> {
     println!("Generating {}-bit key pair...", bits);
-    let keypair = KeyPair::generate(bits)?;
+    let keypair = KeyPair::generate(bits)?.with_usage(usage);
     
-    // Save public key (modulus n)
-    let n_hex = hex::encode(keypair.public.n().to_bytes_be());
-    fs::write(public_key_path, n_hex)
-        .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+    save_public_key(&keypair.public, public_key_path)?;
     println!("Public key saved to: {}", public_key_path.display());
     
-    // Save private key (p and q, one per line)
-    let p_hex = hex::encode(keypair.private.p.to_bytes_be());
-    let q_hex = hex::encode(keypair.private.q.to_bytes_be());
-    let private_key_content = format!("{}\n{}", p_hex, q_hex);
-    fs::write(private_key_path, private_key_content)
-        .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+    save_private_key(&keypair.private, private_key_path)?;
     println!("Private key saved to: {}", private_key_path.display());
+    print_fingerprint(&keypair.public);
+    
+    if let Some(path) = modulus_proof_path {
+        fs::write(path, hex::encode(keypair.private.prove_modulus()?.to_bytes()))?;
+        println!("Modulus proof saved to: {}", path.display());
+    }
     
     println!("Key pair generated successfully!");
     Ok(())
 }
 
-fn load_private_key(path: &PathBuf) -> Result<PrivateKey<Sha256>> {
-    let content = fs::read_to_string(path)
-        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidKeySize)?;
-    let lines: Vec<&str> = content.lines().collect();
-    if lines.len() < 2 {
-        return Err(rabin_williams::RabinWilliamsError::InvalidKeySize);
-    }
-    
-    let p_bytes: Vec<u8> = hex::decode(lines[0])
-        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidKeySize)?;
-    let q_bytes: Vec<u8> = hex::decode(lines[1])
-        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidKeySize)?;
-    
-    let p = num_bigint::BigUint::from_bytes_be(&p_bytes);
-    let q = num_bigint::BigUint::from_bytes_be(&q_bytes);
-    
-    Ok(PrivateKey::from_primes(p, q))
+/// Prints a public key's fingerprint as hex, base32, words and randomart
+fn print_fingerprint<D: HashFunction>(public_key: &PublicKey<D>) {
+    let fingerprint = public_key.fingerprint();
+    println!("Fingerprint: {}", fingerprint);
+    println!("Base32: {}", fingerprint.to_base32());
+    println!("Words: {}", fingerprint.to_words());
+    println!("{}", fingerprint.randomart());
 }
 
-fn load_public_key(path: &PathBuf) -> Result<PublicKey<Sha256>> {
-    let content = fs::read_to_string(path)
-        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidKeySize)?;
-    let n_bytes: Vec<u8> = hex::decode(content.trim())
-        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidKeySize)?;
-    let n = num_bigint::BigUint::from_bytes_be(&n_bytes);
-    
-    Ok(PublicKey::from_n(n))
+fn show_fingerprint<D: HashFunction>(public_key_path: &Path) -> Result<()> {
+    print_fingerprint(&load_public_key::<D>(public_key_path)?);
+    Ok(())
+}
+
+/// Where a command takes its key from: a key file or the key store
+enum KeySource<'a> {
+    File(&'a PathBuf),
+    Store { key: &'a str, keystore: Option<&'a Path> },
+}
+
+impl<'a> KeySource<'a> {
+    /// clap allows at most one of `path` and `key`
+    fn new(path: Option<&'a PathBuf>, key: Option<&'a str>, keystore: Option<&'a Path>) -> Option<Self> {
+        match (path, key) {
+            (Some(path), _) => Some(KeySource::File(path)),
+            (None, Some(key)) => Some(KeySource::Store { key, keystore }),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns a stored key's hash function, or SHA-256 for key files
+    fn default_hash(&self) -> Result<HashAlgorithm> {
+        match self {
+            KeySource::File(_) => Ok(HashAlgorithm::Sha256),
+            KeySource::Store { key, keystore } => stored_hash(&open_keystore(*keystore)?.find(key)?),
+        }
+    }
+
+    fn private_key<D: HashFunction>(&self) -> Result<PrivateKey<D>> {
+        match self {
+            KeySource::File(path) => load_private_key(path),
+            KeySource::Store { key, keystore } => open_keystore(*keystore)?.private_key(key),
+        }
+    }
+
+    fn public_key<D: HashFunction>(&self) -> Result<PublicKey<D>> {
+        match self {
+            KeySource::File(path) => load_public_key(path),
+            KeySource::Store { key, keystore } => open_keystore(*keystore)?.public_key(key),
+        }
+    }
 }
 
 fn read_message(message: Option<&str>) -> Result<Vec<u8>> {
@@ -206,49 +730,301 @@ fn read_message(message: Option<&str>) -> Result<Vec<u8>> {
     }
 }
 
-fn sign_message(private_key_path: &PathBuf, message: Option<&str>, output: Option<&PathBuf>) -> Result<()> {
-    let private_key = load_private_key(private_key_path)?;
-    let message_bytes = read_message(message)?;
-    
-    // Compute and display message hash for debugging
-    let hash_wrapper = HashWrapper::<Sha256>::default();
-    let message_hash = hash_wrapper.hash(&message_bytes);
-    let message_hash_bytes = message_hash.to_bytes_be();
-    eprintln!("DEBUG: Message hash (SHA-256): {}", hex::encode(&message_hash_bytes));
-    eprintln!("DEBUG: Message hash length: {} bytes", message_hash_bytes.len());
+/// Applies the EIP-191 personal message encoding when requested
+fn encode_message(message: Vec<u8>, eip191: bool) -> Vec<u8> {
+    if eip191 {
+        eip191_personal_message(&message)
+    } else {
+        message
+    }
+}
+
+/// Computes the message hash the key will sign, for the debug output
+fn debug_message_hash<D: HashFunction>(n: &BigUint, message: &[u8], context: Option<&str>) -> BigUint {
+    let hash_wrapper = HashWrapper::<D>::default();
+    match context {
+        Some(context) => hash_wrapper.hash_with_context_for_modulus(context.as_bytes(), message, n),
+        None => hash_wrapper.hash_for_modulus(message, n),
+    }
+}
+
+/// How `sign` writes its signature
+struct SignOptions<'a> {
+    eip191: bool,
+    format: SignatureFormat,
+    timestamp: bool,
+    attributes: &'a [(String, String)],
+}
+
+/// How `verify` checks a bare hex signature; signature containers record all of these
+struct VerifyOptions {
+    hash: Option<HashAlgorithm>,
+    eip191: bool,
+    suite: Option<BlindSuite>,
+}
+
+fn parse_suite(name: &str) -> std::result::Result<BlindSuite, String> {
+    BlindSuite::from_name(name).ok_or_else(|| format!("unknown blind signature suite '{}'", name))
+}
+
+fn parse_attribute(value: &str) -> std::result::Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected name=value, got '{}'", value))
+}
+
+fn sign_message<D: HashFunction>(
+    source: KeySource,
+    message: Option<&str>,
+    output: Option<&PathBuf>,
+    context: Option<&str>,
+    options: SignOptions,
+) -> Result<()> {
+    let private_key = source.private_key::<D>()?;
+    let message = read_message(message)?;
     
-    let signature = private_key.sign(&message_bytes)?;
-    let signature_hex = hex::encode(&signature);
+    let signature = match options.format {
+        SignatureFormat::Hex => {
+            let message_bytes = encode_message(message, options.eip191);
+            
+            // Compute and display message hash for debugging
+            let message_hash = debug_message_hash::<D>(&private_key.n(), &message_bytes, context);
+            let message_hash_bytes = message_hash.to_bytes_be();
+            eprintln!("DEBUG: Message hash: {}", hex::encode(&message_hash_bytes));
+            eprintln!("DEBUG: Message hash length: {} bytes", message_hash_bytes.len());
+            
+            let signature = match context {
+                Some(context) => private_key.sign_with_context(context.as_bytes(), &message_bytes)?,
+                None => private_key.sign(&message_bytes)?,
+            };
+            hex::encode(&signature).into_bytes()
+        }
+        format => {
+            let mut metadata = SignatureMetadata::new();
+            if options.eip191 {
+                metadata = metadata.with_encoding(MessageEncoding::Eip191);
+            }
+            if options.timestamp {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+                metadata = metadata.with_created_at(now);
+            }
+            for (name, value) in options.attributes {
+                metadata = metadata.with_attribute(name, value.as_bytes());
+            }
+            
+            let container = private_key.sign_container_with_context(context.unwrap_or("").as_bytes(), &message, metadata)?;
+            eprintln!("Signed with key {}", container.fingerprint());
+            match format {
+                SignatureFormat::Binary => container.to_bytes(),
+                _ => container.to_armored().into_bytes(),
+            }
+        }
+    };
     
     match output {
         Some(path) => {
-            fs::write(path, signature_hex)
-                .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+            fs::write(path, signature)?;
             println!("Signature saved to: {}", path.display());
         }
         None => {
-            println!("{}", signature_hex);
+            let mut stdout = io::stdout();
+            stdout.write_all(&signature)
+                .and_then(|()| match options.format {
+                    SignatureFormat::Hex => writeln!(stdout),
+                    _ => Ok(()),
+                })?;
+        }
+    }
+    
+    Ok(())
+}
+
+fn sign_directory<D: HashFunction + Send + Sync>(private_key_path: &Path, dir: &PathBuf, output_dir: Option<&PathBuf>) -> Result<()> {
+    let private_key = load_private_key::<D>(private_key_path)?;
+    let output_dir = output_dir.unwrap_or(dir);
+    
+    // Collect regular files in a stable order, skipping signatures from earlier runs
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_none_or(|ext| ext != "sig"))
+        .collect();
+    paths.sort();
+    
+    let messages = paths
+        .iter()
+        .map(fs::read)
+        .collect::<std::io::Result<Vec<Vec<u8>>>>()?;
+    let signatures = private_key.sign_batch(&messages)?;
+    
+    for (path, signature) in paths.iter().zip(signatures) {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".sig");
+        let signature_path = output_dir.join(file_name);
+        fs::write(&signature_path, hex::encode(signature?))?;
+    }
+    
+    println!("Signed {} files into: {}", paths.len(), output_dir.display());
+    Ok(())
+}
+
+/// Reads a file and splits it into lines, without their line endings
+fn read_lines(path: &PathBuf) -> Result<Vec<Vec<u8>>> {
+    let content = fs::read(path)?;
+    if content.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(content
+        .strip_suffix(b"\n")
+        .unwrap_or(&content)
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line).to_vec())
+        .collect())
+}
+
+fn sign_lines<D: HashFunction + Send + Sync>(private_key_path: &Path, lines_path: &PathBuf, output: Option<&PathBuf>) -> Result<()> {
+    let private_key = load_private_key::<D>(private_key_path)?;
+    
+    let messages = read_lines(lines_path)?;
+    
+    let signatures = private_key
+        .sign_batch(&messages)?
+        .into_iter()
+        .map(|signature| signature.map(hex::encode))
+        .collect::<Result<Vec<String>>>()?;
+    let signatures_hex = signatures.join("\n");
+    
+    match output {
+        Some(path) => {
+            fs::write(path, signatures_hex + "\n")?;
+            println!("Signed {} lines into: {}", signatures.len(), path.display());
+        }
+        None => {
+            println!("{}", signatures_hex);
         }
     }
     
     Ok(())
 }
 
-fn verify_signature(public_key_path: &PathBuf, signature_path: &PathBuf, message: Option<&str>) -> Result<()> {
-    let public_key = load_public_key(public_key_path)?;
+fn sign_merkle(private_key_path: &Path, lines_path: &PathBuf, signature_path: &PathBuf, proofs_path: &PathBuf) -> Result<()> {
+    let private_key = load_private_key::<Sha256>(private_key_path)?;
+    let messages = read_lines(lines_path)?;
+    
+    let batch = private_key.sign_merkle_batch(&messages)?;
+    
+    fs::write(signature_path, hex::encode(&batch.root_signature))?;
+    println!("Root signature saved to: {}", signature_path.display());
+    
+    let proofs: Vec<String> = batch.proofs.iter().map(|proof| hex::encode(proof.to_bytes())).collect();
+    fs::write(proofs_path, proofs.join("\n") + "\n")?;
+    println!("{} inclusion proofs saved to: {}", proofs.len(), proofs_path.display());
+    
+    Ok(())
+}
+
+/// Verifies a signature container, or a bare hex signature with `--hash`, `--eip191` and `--suite`
+fn verify_signature(
+    source: Option<KeySource>,
+    keystore: Option<&Path>,
+    signature_path: &PathBuf,
+    message: Option<&str>,
+    context: Option<&str>,
+    options: VerifyOptions,
+) -> Result<()> {
+    let VerifyOptions { hash, eip191, suite } = options;
+    let signature = fs::read(signature_path)
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidSignature)?;
+    
+    let is_valid = if SignatureContainer::is_container(&signature) {
+        let container = SignatureContainer::parse(&signature)?;
+        // The container records both, so the flags cannot change how it is checked
+        if hash.is_some() {
+            eprintln!("Warning: ignoring --hash; signature containers record their hash function");
+        }
+        if eip191 {
+            eprintln!("Warning: ignoring --eip191; signature containers record their message encoding");
+        }
+        if suite.is_some() {
+            eprintln!("Warning: ignoring --suite; blind signatures are not signature containers");
+        }
+        // Without a key, find the signer in the key store by fingerprint
+        let fingerprint = container.fingerprint().to_hex();
+        let source = source.unwrap_or(KeySource::Store { key: &fingerprint, keystore });
+        match HashAlgorithm::from_id(container.hash_algorithm())? {
+            HashAlgorithm::Sha256 => verify_container::<Sha256>(source, &container, message, context)?,
+            HashAlgorithm::Keccak256 => verify_container::<Keccak256>(source, &container, message, context)?,
+        }
+    } else {
+        let source = source.ok_or(rabin_williams::RabinWilliamsError::KeyNotFound)?;
+        match hash.map_or_else(|| source.default_hash(), Ok)? {
+            HashAlgorithm::Sha256 => verify_hex_signature::<Sha256>(source, &signature, message, context, eip191, suite)?,
+            HashAlgorithm::Keccak256 => verify_hex_signature::<Keccak256>(source, &signature, message, context, eip191, suite)?,
+        }
+    };
+    
+    if is_valid {
+        println!("✓ Signature is valid");
+        Ok(())
+    } else {
+        println!("✗ Signature is invalid");
+        Err(rabin_williams::RabinWilliamsError::InvalidSignature)
+    }
+}
+
+fn verify_container<D: HashFunction>(
+    source: KeySource,
+    container: &SignatureContainer,
+    message: Option<&str>,
+    context: Option<&str>,
+) -> Result<bool> {
+    let public_key = source.public_key::<D>()?;
     let message_bytes = read_message(message)?;
     
+    if !public_key.verify_container_with_context(context.unwrap_or("").as_bytes(), &message_bytes, container)? {
+        return Ok(false);
+    }
+    
+    // Metadata is only shown once the signature vouches for it
+    let fingerprint = container.fingerprint();
+    println!("Signed by key: {} ({})", fingerprint, fingerprint.to_words());
+    let metadata = container.metadata();
+    if let Some(created_at) = metadata.created_at() {
+        println!("Created at: {} (seconds since the Unix epoch)", created_at);
+    }
+    for (name, value) in metadata.attributes() {
+        println!("Attribute {}: {}", escape_control(name), escape_control(&String::from_utf8_lossy(value)));
+    }
+    Ok(true)
+}
+
+/// Escapes control characters so that signed metadata cannot move the cursor
+/// or forge output lines
+fn escape_control(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_control() { c.escape_default().to_string() } else { c.to_string() })
+        .collect()
+}
+
+fn verify_hex_signature<D: HashFunction>(
+    source: KeySource,
+    signature_hex: &[u8],
+    message: Option<&str>,
+    context: Option<&str>,
+    eip191: bool,
+    suite: Option<BlindSuite>,
+) -> Result<bool> {
+    let public_key = source.public_key::<D>()?;
+    let message_bytes = encode_message(read_message(message)?, eip191);
+    
     // Compute and display message hash for debugging
-    let hash_wrapper = HashWrapper::<Sha256>::default();
-    let message_hash = hash_wrapper.hash(&message_bytes);
+    let message_hash = debug_message_hash::<D>(public_key.n(), &message_bytes, context);
     let message_hash_bytes = message_hash.to_bytes_be();
-    eprintln!("DEBUG: Message hash (SHA-256): {}", hex::encode(&message_hash_bytes));
+    eprintln!("DEBUG: Message hash: {}", hex::encode(&message_hash_bytes));
     eprintln!("DEBUG: Message hash length: {} bytes", message_hash_bytes.len());
     eprintln!("DEBUG: Message bytes length: {} bytes", message_bytes.len());
     
-    let signature_hex = fs::read_to_string(signature_path)
-        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidSignature)?;
-    let signature: Vec<u8> = hex::decode(signature_hex.trim())
+    let signature: Vec<u8> = hex::decode(String::from_utf8_lossy(signature_hex).trim())
         .map_err(|_| rabin_williams::RabinWilliamsError::InvalidSignature)?;
     
     eprintln!("DEBUG: Signature length: {} bytes", signature.len());
@@ -256,32 +1032,28 @@ fn verify_signature(public_key_path: &PathBuf, signature_path: &PathBuf, message
         eprintln!("DEBUG: Signature first byte (flags): 0x{:02x}", signature[0]);
     }
     
-    let is_valid = public_key.verify(&message_bytes, &signature)?;
-    
-    if is_valid {
-        println!("✓ Signature is valid");
-        Ok(())
-    } else {
-        println!("✗ Signature is invalid");
-        Err(rabin_williams::RabinWilliamsError::InvalidSignature)
+    match (suite, context) {
+        (Some(suite), _) => public_key.verify_with_suite(suite, &message_bytes, &signature),
+        (None, Some(context)) => public_key.verify_with_context(context.as_bytes(), &message_bytes, &signature),
+        (None, None) => public_key.verify(&message_bytes, &signature),
     }
 }
 
-fn blind_sign(private_key_path: &PathBuf, blinded_message_path: &PathBuf, output: Option<&PathBuf>) -> Result<()> {
-    let private_key = load_private_key(private_key_path)?;
+fn blind_sign(private_key_path: &Path, blinded_message_path: &PathBuf, output: Option<&PathBuf>) -> Result<()> {
+    let private_key = load_private_key::<Sha256>(private_key_path)?;
     
     let blinded_message_hex = fs::read_to_string(blinded_message_path)
         .map_err(|_| rabin_williams::RabinWilliamsError::MessageTooLarge)?;
     let blinded_message_bytes: Vec<u8> = hex::decode(blinded_message_hex.trim())
-        .map_err(|_| rabin_williams::RabinWilliamsError::MessageTooLarge)?;
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidBlindedMessage)?;
+    let blinded_message = BlindedMessage::from_bytes(&blinded_message_bytes);
     
-    let blinded_signature = private_key.raw_sign(&blinded_message_bytes)?;
-    let signature_hex = hex::encode(&blinded_signature);
+    let blinded_signature = private_key.blind_sign(&blinded_message)?;
+    let signature_hex = hex::encode(blinded_signature.as_bytes());
     
     match output {
         Some(path) => {
-            fs::write(path, signature_hex)
-                .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+            fs::write(path, signature_hex)?;
             println!("Blinded signature saved to: {}", path.display());
         }
         None => {
@@ -293,36 +1065,79 @@ fn blind_sign(private_key_path: &PathBuf, blinded_message_path: &PathBuf, output
 }
 
 fn blind_message(
-    public_key_path: &PathBuf,
+    public_key_path: &Path,
     message: Option<&str>,
     blinded_message_path: &PathBuf,
     blinding_factor_path: &PathBuf,
+    context: Option<&str>,
+    modulus_proof_path: Option<&Path>,
 ) -> Result<()> {
-    let public_key = load_public_key(public_key_path)?;
+    let public_key = load_public_key::<Sha256>(public_key_path)?;
+    if let Some(path) = modulus_proof_path {
+        if !public_key.verify_modulus_proof(&load_modulus_proof(path)?)? {
+            return Err(rabin_williams::RabinWilliamsError::InvalidModulusProof);
+        }
+    }
     let message_bytes = read_message(message)?;
     
-    let (blinded_message, r) = public_key.blind_message(&message_bytes);
+    // Without a context the message gets the RWBSSA-FDH-Deterministic encoding
+    let (blinded_message, r) = match context {
+        Some(context) => public_key.blind_message_with_context(context.as_bytes(), &message_bytes),
+        None => public_key.blind_message(&message_bytes),
+    };
     
     let blinded_message_hex = hex::encode(blinded_message.to_bytes_be());
-    fs::write(blinded_message_path, blinded_message_hex)
-        .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+    fs::write(blinded_message_path, blinded_message_hex)?;
     println!("Blinded message saved to: {}", blinded_message_path.display());
     
     let r_hex = hex::encode(r.to_bytes_be());
-    fs::write(blinding_factor_path, r_hex)
-        .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+    fs::write(blinding_factor_path, r_hex)?;
     println!("Blinding factor saved to: {}", blinding_factor_path.display());
+    match context {
+        Some(_) => println!("Verify the unblinded signature with the same --context"),
+        None => println!("Verify the unblinded signature with --suite {}", BlindSuite::FdhDeterministic),
+    }
+    
+    Ok(())
+}
+
+fn load_modulus_proof(path: &Path) -> Result<ModulusProof> {
+    let proof_hex = fs::read_to_string(path)
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidModulusProof)?;
+    let proof_bytes: Vec<u8> = hex::decode(proof_hex.trim())
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidModulusProof)?;
+    ModulusProof::from_bytes(&proof_bytes)
+}
+
+fn prove_modulus(private_key_path: &Path, output: &Path) -> Result<()> {
+    let private_key = load_private_key::<Sha256>(private_key_path)?;
+    
+    fs::write(output, hex::encode(private_key.prove_modulus()?.to_bytes()))?;
+    println!("Modulus proof saved to: {}", output.display());
     
     Ok(())
 }
 
+fn verify_modulus(public_key_path: &Path, proof_path: &Path) -> Result<()> {
+    let public_key = load_public_key::<Sha256>(public_key_path)?;
+    let proof = load_modulus_proof(proof_path)?;
+    
+    if public_key.verify_modulus_proof(&proof)? {
+        println!("✓ Modulus is a well-formed Rabin-Williams modulus");
+        Ok(())
+    } else {
+        println!("✗ Modulus proof is invalid");
+        Err(rabin_williams::RabinWilliamsError::InvalidModulusProof)
+    }
+}
+
 fn unblind_signature(
-    public_key_path: &PathBuf,
+    public_key_path: &Path,
     blinded_signature_path: &PathBuf,
     blinding_factor_path: &PathBuf,
     output: Option<&PathBuf>,
 ) -> Result<()> {
-    let public_key = load_public_key(public_key_path)?;
+    let public_key = load_public_key::<Sha256>(public_key_path)?;
     
     let blinded_signature_hex = fs::read_to_string(blinded_signature_path)
         .map_err(|_| rabin_williams::RabinWilliamsError::InvalidSignature)?;
@@ -340,8 +1155,7 @@ fn unblind_signature(
     
     match output {
         Some(path) => {
-            fs::write(path, signature_hex)
-                .map_err(|_| rabin_williams::RabinWilliamsError::ComputationError)?;
+            fs::write(path, signature_hex)?;
             println!("Unblinded signature saved to: {}", path.display());
         }
         None => {
@@ -352,3 +1166,321 @@ fn unblind_signature(
     Ok(())
 }
 
+fn encrypt_message(public_key_path: &Path, message: Option<&str>, output: Option<&PathBuf>) -> Result<()> {
+    let public_key = load_public_key::<Sha256>(public_key_path)?;
+    let message = read_message(message)?;
+    
+    let ciphertext_hex = hex::encode(public_key.encrypt(&message)?);
+    
+    match output {
+        Some(path) => {
+            fs::write(path, ciphertext_hex)?;
+            println!("Ciphertext saved to: {}", path.display());
+        }
+        None => {
+            println!("{}", ciphertext_hex);
+        }
+    }
+    
+    Ok(())
+}
+
+fn decrypt_message(private_key_path: &Path, ciphertext_path: &PathBuf, output: Option<&PathBuf>) -> Result<()> {
+    let private_key = load_private_key::<Sha256>(private_key_path)?;
+    
+    let ciphertext_hex = fs::read_to_string(ciphertext_path)
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidCiphertext)?;
+    let ciphertext: Vec<u8> = hex::decode(ciphertext_hex.trim())
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidCiphertext)?;
+    
+    let plaintext = private_key.decrypt(&ciphertext)?;
+    
+    match output {
+        Some(path) => {
+            fs::write(path, plaintext)?;
+            println!("Plaintext saved to: {}", path.display());
+        }
+        None => {
+            io::stdout().write_all(&plaintext)?;
+        }
+    }
+    
+    Ok(())
+}
+
+fn encrypt_file(public_key_path: &Path, input: &Path, output: &Path) -> Result<()> {
+    let public_key = load_public_key::<Sha256>(public_key_path)?;
+    let plaintext = fs::read(input)?;
+    
+    fs::write(output, kem::seal(&public_key, &plaintext)?)?;
+    println!("Encrypted file saved to: {}", output.display());
+    
+    Ok(())
+}
+
+fn decrypt_file(private_key_path: &Path, input: &Path, output: &Path) -> Result<()> {
+    let private_key = load_private_key::<Sha256>(private_key_path)?;
+    let sealed = fs::read(input)
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidCiphertext)?;
+    
+    fs::write(output, kem::open(&private_key, &sealed)?)?;
+    println!("Decrypted file saved to: {}", output.display());
+    
+    Ok(())
+}
+
+fn split_key(private_key_path: &Path, threshold: u8, shares: u8, output_dir: &Path) -> Result<()> {
+    let private_key = load_private_key::<Sha256>(private_key_path)?;
+    
+    fs::create_dir_all(output_dir)?;
+    for share in private_key.split(threshold, shares)? {
+        // Owner-only like a private key file: with a threshold of 1, one share is the whole key
+        let path = output_dir.join(format!("share-{}.hex", share.index()));
+        save_secret_file(&path, &hex::encode(share.to_bytes()))?;
+        println!("Share {} saved to: {}", share.index(), path.display());
+    }
+    println!("Any {} of the {} shares recover the key", threshold, shares);
+    
+    Ok(())
+}
+
+fn recover_key(public_key_path: &Path, share_paths: &[PathBuf], output: &Path) -> Result<()> {
+    let public_key = load_public_key::<Sha256>(public_key_path)?;
+    
+    let shares = share_paths
+        .iter()
+        .map(|path| {
+            let share_hex = fs::read_to_string(path)
+                .map_err(|_| rabin_williams::RabinWilliamsError::InvalidShare)?;
+            let share_bytes = hex::decode(share_hex.trim())
+                .map_err(|_| rabin_williams::RabinWilliamsError::InvalidShare)?;
+            KeyShare::from_bytes(&share_bytes)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    let private_key = PrivateKey::recover(&shares, &public_key)?;
+    save_private_key(&private_key, output)?;
+    println!("Private key recovered to: {}", output.display());
+    
+    Ok(())
+}
+
+fn open_keystore(path: Option<&Path>) -> Result<KeyStore> {
+    match path {
+        Some(path) => KeyStore::open(path),
+        None => KeyStore::open_default(),
+    }
+}
+
+/// Names the hash function a stored key was created for
+fn stored_hash(metadata: &KeyMetadata) -> Result<HashAlgorithm> {
+    HashAlgorithm::from_id(metadata.hash_algorithm())
+}
+
+fn keys_command(keystore: Option<&Path>, command: KeysCommands) -> Result<()> {
+    let store = open_keystore(keystore)?;
+    match command {
+        KeysCommands::List => keys_list(&store),
+        KeysCommands::Generate { name, bits, usage, hash } => match hash {
+            HashAlgorithm::Sha256 => keys_generate::<Sha256>(&store, &name, bits, usage),
+            HashAlgorithm::Keccak256 => keys_generate::<Keccak256>(&store, &name, bits, usage),
+        },
+        KeysCommands::Import { name, public_key, private_key, hash } => match hash {
+            HashAlgorithm::Sha256 => keys_import::<Sha256>(&store, &name, &public_key, private_key.as_ref()),
+            HashAlgorithm::Keccak256 => keys_import::<Keccak256>(&store, &name, &public_key, private_key.as_ref()),
+        },
+        KeysCommands::Export { key, public_key, private_key } => {
+            keys_export(&store, &key, &public_key, private_key.as_deref())
+        }
+        KeysCommands::Delete { key } => keys_delete(&store, &key),
+        KeysCommands::Rotate { name } => keys_rotate(&store, &name),
+    }
+}
+
+fn keys_list(store: &KeyStore) -> Result<()> {
+    for metadata in store.list()? {
+        let hash = stored_hash(&metadata).map_or("unknown", HashAlgorithm::as_str);
+        println!(
+            "{}  {:<16} {:>5}-bit {:<9} {:<22} {:<7} {}  created {}",
+            metadata.fingerprint(),
+            metadata.name(),
+            metadata.bits(),
+            hash,
+            metadata.usage().as_str(),
+            metadata.status().as_str(),
+            if metadata.has_private_key() { "private" } else { "public" },
+            metadata.created_at(),
+        );
+    }
+    Ok(())
+}
+
+fn print_stored_key(metadata: &KeyMetadata) {
+    println!("Key '{}' stored with fingerprint {}", metadata.name(), metadata.fingerprint());
+    println!("Words: {}", metadata.fingerprint().to_words());
+}
+
+fn keys_generate<D: HashFunction>(store: &KeyStore, name: &str, bits: usize, usage: KeyUsage) -> Result<()> {
+    println!("Generating {}-bit key pair...", bits);
+    print_stored_key(&store.generate::<D>(name, bits, usage)?);
+    Ok(())
+}
+
+fn keys_import<D: HashFunction>(
+    store: &KeyStore,
+    name: &str,
+    public_key_path: &Path,
+    private_key_path: Option<&PathBuf>,
+) -> Result<()> {
+    let public_key = load_public_key::<D>(public_key_path)?;
+    let private_key = private_key_path.map(|path| load_private_key::<D>(path)).transpose()?;
+    print_stored_key(&store.import(name, &public_key, private_key.as_ref())?);
+    Ok(())
+}
+
+fn keys_export(store: &KeyStore, key: &str, public_key_path: &Path, private_key_path: Option<&Path>) -> Result<()> {
+    match stored_hash(&store.find(key)?)? {
+        HashAlgorithm::Sha256 => export_key::<Sha256>(store, key, public_key_path, private_key_path),
+        HashAlgorithm::Keccak256 => export_key::<Keccak256>(store, key, public_key_path, private_key_path),
+    }
+}
+
+fn export_key<D: HashFunction>(
+    store: &KeyStore,
+    key: &str,
+    public_key_path: &Path,
+    private_key_path: Option<&Path>,
+) -> Result<()> {
+    let public_key = store.public_key::<D>(key)?;
+    save_public_key(&public_key, public_key_path)?;
+    println!("Public key saved to: {}", public_key_path.display());
+    if let Some(path) = private_key_path {
+        save_private_key(&store.private_key::<D>(key)?, path)?;
+        println!("Private key saved to: {}", path.display());
+    }
+    Ok(())
+}
+
+fn keys_delete(store: &KeyStore, key: &str) -> Result<()> {
+    let metadata = store.delete(key)?;
+    println!("Deleted key '{}' with fingerprint {}", metadata.name(), metadata.fingerprint());
+    Ok(())
+}
+
+fn keys_rotate(store: &KeyStore, name: &str) -> Result<()> {
+    let old = store.find(name)?;
+    let new = match stored_hash(&old)? {
+        HashAlgorithm::Sha256 => store.rotate::<Sha256>(name)?,
+        HashAlgorithm::Keccak256 => store.rotate::<Keccak256>(name)?,
+    };
+    println!("Retired key with fingerprint {}", old.fingerprint());
+    print_stored_key(&new);
+    Ok(())
+}
+
+#[cfg(feature = "insecure-demo")]
+fn mint_private_key_path(mint: &Path, denomination: u64) -> PathBuf {
+    mint.join(format!("private_key_{}.hex", denomination))
+}
+
+#[cfg(feature = "insecure-demo")]
+fn mint_public_key_path(mint: &Path, denomination: u64) -> PathBuf {
+    mint.join(format!("public_key_{}.hex", denomination))
+}
+
+/// Lists the denominations that have a public key in the mint directory
+#[cfg(feature = "insecure-demo")]
+fn mint_denominations(mint: &Path) -> Result<Vec<u64>> {
+    let entries = fs::read_dir(mint)
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidKeySize)?;
+    let mut denominations: Vec<u64> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_prefix("public_key_")?.strip_suffix(".hex")?.parse().ok()
+        })
+        .collect();
+    denominations.sort_unstable();
+    Ok(denominations)
+}
+
+#[cfg(feature = "insecure-demo")]
+fn load_mint_public_keys(mint: &Path) -> Result<MintPublicKeys<Sha256>> {
+    let keys = mint_denominations(mint)?
+        .into_iter()
+        .map(|denomination| Ok((denomination, load_public_key(&mint_public_key_path(mint, denomination))?)))
+        .collect::<Result<_>>()?;
+    Ok(MintPublicKeys::new(keys))
+}
+
+#[cfg(feature = "insecure-demo")]
+fn load_mint(mint: &Path) -> Result<Mint<Sha256, FileSpendStore>> {
+    let keys = mint_denominations(mint)?
+        .into_iter()
+        .map(|denomination| Ok((denomination, load_private_key(&mint_private_key_path(mint, denomination))?)))
+        .collect::<Result<_>>()?;
+    Mint::new(keys, FileSpendStore::open(mint.join("spent.txt"))?)
+}
+
+#[cfg(feature = "insecure-demo")]
+fn load_coin(path: &PathBuf) -> Result<Coin> {
+    let coin_hex = fs::read_to_string(path)
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidCoinEncoding)?;
+    let coin_bytes = hex::decode(coin_hex.trim())
+        .map_err(|_| rabin_williams::RabinWilliamsError::InvalidCoinEncoding)?;
+    Coin::from_bytes(&coin_bytes)
+}
+
+#[cfg(feature = "insecure-demo")]
+fn ecash_init(mint: &PathBuf, denominations: &[u64], bits: usize) -> Result<()> {
+    fs::create_dir_all(mint)?;
+    
+    for &denomination in denominations {
+        generate_keypair(bits, &mint_public_key_path(mint, denomination), &mint_private_key_path(mint, denomination), KeyUsage::Signing, None)?;
+    }
+    
+    println!("Mint created in {} with denominations {:?}", mint.display(), denominations);
+    Ok(())
+}
+
+#[cfg(feature = "insecure-demo")]
+fn ecash_withdraw(mint: &Path, denomination: u64, coin_path: &PathBuf) -> Result<()> {
+    let mint = load_mint(mint)?;
+    
+    // Client side: blind a fresh serial
+    let (request, withdrawal) = mint.public_keys().withdraw(denomination)?;
+    
+    // Mint side: sign without seeing the serial
+    let blind_signature = mint.withdraw(&request)?;
+    
+    // Client side: unblind into a coin
+    let coin = withdrawal.finalize(&blind_signature)?;
+    
+    fs::write(coin_path, hex::encode(coin.to_bytes()))?;
+    println!("Withdrew a coin worth {} to: {}", denomination, coin_path.display());
+    Ok(())
+}
+
+#[cfg(feature = "insecure-demo")]
+fn ecash_spend(mint: &Path, coin_path: &PathBuf) -> Result<()> {
+    let public_keys = load_mint_public_keys(mint)?;
+    let coin = load_coin(coin_path)?;
+    
+    if public_keys.verify_coin(&coin)? {
+        println!("✓ Coin worth {} is valid", coin.denomination);
+        Ok(())
+    } else {
+        println!("✗ Coin is invalid");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "insecure-demo")]
+fn ecash_deposit(mint: &Path, coin_path: &PathBuf) -> Result<()> {
+    let mut mint = load_mint(mint)?;
+    let coin = load_coin(coin_path)?;
+    
+    let denomination = mint.deposit(&coin)?;
+    println!("✓ Deposited a coin worth {}", denomination);
+    Ok(())
+}
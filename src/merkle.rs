@@ -0,0 +1,275 @@
+//! Merkle-tree batch signing with per-message inclusion proofs
+//!
+//! The signer builds a SHA-256 Merkle tree over a batch of messages and signs
+//! only the root. Each message is then accompanied by an inclusion proof, so a
+//! verifier checks one message with a handful of hashes plus a single signature
+//! verification. The tree uses SHA-256 regardless of the key's hash function so
+//! that `RabinWilliamsVerifier.verifyWithMerkleProof` can rebuild the root with
+//! the `sha256` precompile.
+//!
+//! Leaves are `SHA-256(0x00 || message)` and interior nodes are
+//! `SHA-256(0x01 || left || right)`, which keeps leaves and interior nodes from
+//! being confused. A node without a sibling is promoted to the next level
+//! unchanged rather than being paired with itself.
+//!
+//! The root is signed under the context string `MERKLE_ROOT_CONTEXT`, so a root
+//! signature is never valid as a signature on a 32-byte message, and a plain
+//! signature on some message never passes for a batch signature.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::HashFunction;
+use crate::keys::{PrivateKey, PublicKey};
+use sha2::{Digest, Sha256};
+
+/// Context string that Merkle roots are signed under
+pub const MERKLE_ROOT_CONTEXT: &[u8] = b"rabin-williams merkle root";
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hashes a message into a Merkle leaf
+pub fn leaf_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+/// Hashes two child nodes into their parent
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// A Merkle tree over a batch of messages
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    /// Every level of the tree, from the leaves up to the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Builds a tree over `messages`, which must not be empty
+    pub fn new<M: AsRef<[u8]>>(messages: &[M]) -> Result<Self> {
+        if messages.is_empty() {
+            return Err(RabinWilliamsError::EmptyBatch);
+        }
+
+        let mut levels = vec![messages.iter().map(|m| leaf_hash(m.as_ref())).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = &levels[levels.len() - 1];
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(Self { levels })
+    }
+
+    /// Returns the root of the tree
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns the number of messages in the tree
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Always false, since a tree holds at least one message
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Returns the inclusion proof for the message at `index`
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut siblings = Vec::new();
+        let mut path = 0u64;
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                if sibling < position {
+                    path |= 1 << siblings.len();
+                }
+                siblings.push(level[sibling]);
+            }
+            position /= 2;
+        }
+
+        Some(MerkleProof { siblings, path })
+    }
+}
+
+/// Proof that a message is included in a Merkle tree
+///
+/// `siblings` lists the sibling hashes from the leaf up to the root. Bit `i` of
+/// `path` is set when `siblings[i]` is the left child.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub siblings: Vec<[u8; 32]>,
+    pub path: u64,
+}
+
+impl MerkleProof {
+    /// Recomputes the root of the tree containing `message`
+    pub fn root(&self, message: &[u8]) -> [u8; 32] {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf_hash(message), |node, (i, sibling)| {
+                if self.path >> i & 1 == 1 {
+                    node_hash(sibling, &node)
+                } else {
+                    node_hash(&node, sibling)
+                }
+            })
+    }
+
+    /// Serializes the proof as the 8-byte big-endian path followed by the siblings
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + 32 * self.siblings.len());
+        bytes.extend_from_slice(&self.path.to_be_bytes());
+        for sibling in &self.siblings {
+            bytes.extend_from_slice(sibling);
+        }
+        bytes
+    }
+
+    /// Parses a proof produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 8 || !(bytes.len() - 8).is_multiple_of(32) {
+            return Err(RabinWilliamsError::InvalidProof);
+        }
+        let (path, siblings) = bytes.split_at(8);
+        let path = u64::from_be_bytes(path.try_into().map_err(|_| RabinWilliamsError::InvalidProof)?);
+        let siblings = siblings
+            .chunks_exact(32)
+            .map(|chunk| chunk.try_into().map_err(|_| RabinWilliamsError::InvalidProof))
+            .collect::<Result<Vec<[u8; 32]>>>()?;
+        if siblings.len() < 64 && path >> siblings.len() != 0 {
+            return Err(RabinWilliamsError::InvalidProof);
+        }
+        Ok(Self { siblings, path })
+    }
+}
+
+/// A signature over the root of a Merkle tree plus one proof per message
+#[derive(Clone, Debug)]
+pub struct MerkleBatchSignature {
+    pub root_signature: Vec<u8>,
+    pub proofs: Vec<MerkleProof>,
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Signs a batch of messages with a single signature over their Merkle root
+    ///
+    /// The returned proofs are in the same order as `messages`; the message at
+    /// index `i` verifies with `PublicKey::verify_in_batch` using `proofs[i]`.
+    pub fn sign_merkle_batch<M: AsRef<[u8]>>(&self, messages: &[M]) -> Result<MerkleBatchSignature> {
+        let tree = MerkleTree::new(messages)?;
+        let root_signature = self.sign_with_context(MERKLE_ROOT_CONTEXT, &tree.root())?;
+        let proofs = (0..tree.len())
+            .map(|index| tree.proof(index).ok_or(RabinWilliamsError::ComputationError))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(MerkleBatchSignature { root_signature, proofs })
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Verifies that `message` was part of a batch signed with `sign_merkle_batch`
+    pub fn verify_in_batch(&self, message: &[u8], proof: &MerkleProof, root_signature: &[u8]) -> Result<bool> {
+        self.verify_with_context(MERKLE_ROOT_CONTEXT, &proof.root(message), root_signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+
+    fn messages(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| format!("message {}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_proofs_for_all_sizes() -> Result<()> {
+        // Cover full, lopsided and single-leaf trees
+        for count in 1..=17 {
+            let messages = messages(count);
+            let tree = MerkleTree::new(&messages)?;
+            for (index, message) in messages.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert_eq!(proof.root(message), tree.root(), "count {} index {}", count, index);
+                assert_eq!(MerkleProof::from_bytes(&proof.to_bytes())?, proof);
+            }
+            assert!(tree.proof(count).is_none());
+        }
+
+        assert!(matches!(MerkleTree::new::<&[u8]>(&[]), Err(RabinWilliamsError::EmptyBatch)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_leaf_and_node_domains_differ() {
+        // An interior node must not be accepted as a leaf of a shorter tree
+        let left = leaf_hash(b"a");
+        let right = leaf_hash(b"b");
+        let mut concatenated = left.to_vec();
+        concatenated.extend_from_slice(&right);
+        assert_ne!(leaf_hash(&concatenated), node_hash(&left, &right));
+    }
+
+    #[test]
+    fn test_sign_verify_merkle_batch() -> Result<()> {
+        let key_pair = KeyPair::generate(1024)?;
+        let messages = messages(10);
+
+        let batch = key_pair.private.sign_merkle_batch(&messages)?;
+        assert_eq!(batch.proofs.len(), messages.len());
+        for (message, proof) in messages.iter().zip(&batch.proofs) {
+            assert!(key_pair.public.verify_in_batch(message, proof, &batch.root_signature)?);
+        }
+
+        // Proofs are bound to their message and position
+        assert!(!key_pair.public.verify_in_batch(b"forged", &batch.proofs[0], &batch.root_signature)?);
+        assert!(!key_pair.public.verify_in_batch(&messages[0], &batch.proofs[1], &batch.root_signature)?);
+
+        let mut flipped = batch.proofs[4].clone();
+        flipped.path ^= 1;
+        assert!(!key_pair.public.verify_in_batch(&messages[4], &flipped, &batch.root_signature)?);
+
+        // The root signature is not a signature on the root as a message, and a
+        // signature on the root as a message is not a batch signature
+        let root = MerkleTree::new(&messages)?.root();
+        assert!(!key_pair.public.verify(&root, &batch.root_signature)?);
+        let plain = key_pair.private.sign(&root)?;
+        assert!(!key_pair.public.verify_in_batch(&messages[0], &batch.proofs[0], &plain)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_from_bytes_rejects_malformed() {
+        assert!(MerkleProof::from_bytes(&[0u8; 7]).is_err());
+        assert!(MerkleProof::from_bytes(&[0u8; 8 + 31]).is_err());
+
+        // Path bits beyond the number of siblings are rejected
+        let mut bytes = vec![0u8; 8 + 32];
+        bytes[7] = 0b10;
+        assert!(MerkleProof::from_bytes(&bytes).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Non-interactive proofs that a modulus is a well-formed Rabin-Williams modulus
+//!
+//! Blind signatures are only unlinkable if the issuer's `n` is a product of two
+//! distinct primes p ≡ 3 (mod 8) and q ≡ 7 (mod 8). A malicious issuer could
+//! otherwise pick a modulus where blinding leaks information and tag users.
+//!
+//! The proof follows the Paillier-Blum modulus proof of Canetti et al.
+//! (CGGMP21), made non-interactive with Fiat-Shamir. For each challenge `y`
+//! derived from `n` with the key's hash function, the prover reveals
+//!
+//! - `z` with `z^n = y (mod n)`, which exists for every `y` only if
+//!   `gcd(n, φ(n)) = 1`, so `n` is square-free, and
+//! - `x` with `x^4 = e·f·y (mod n)` for `e ∈ {1, -1}` and `f ∈ {1, 2}`, which
+//!   exists for every `y` only if `n` is a Blum integer with two prime factors.
+//!
+//! A cheating prover answers each challenge with probability at most 1/2, so
+//! `PROOF_ROUNDS` challenges give 80 bits of soundness. The verifier also checks
+//! that `n` is composite and `n ≡ 5 (mod 8)`; for a product of two primes that are
+//! 3 (mod 4), this holds exactly when one is 3 and the other 7 (mod 8).
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{mgf1, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use crate::utils::{jacobi, make_quadratic_residue, mod_inverse, to_fixed_bytes};
+use num_bigint::BigUint;
+use num_prime::{nt_funcs::is_prime, PrimalityTestConfig};
+
+/// Number of challenges in a proof
+pub const PROOF_ROUNDS: usize = 80;
+
+/// Domain separator for deriving challenges
+const MODULUS_PROOF_CONTEXT: &[u8] = b"rabin-williams modulus proof";
+
+/// The prover's answer to one challenge
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProofRound {
+    e: i32,
+    f: u32,
+    /// Fourth root of e·f·y
+    x: BigUint,
+    /// n-th root of y
+    z: BigUint,
+}
+
+/// A proof that a public modulus is a well-formed Rabin-Williams modulus
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModulusProof {
+    /// Length in bytes of each encoded x and z
+    element_len: usize,
+    rounds: Vec<ProofRound>,
+}
+
+impl ModulusProof {
+    /// Encodes the proof as a 2-byte big-endian round count followed by each
+    /// round's flag byte, `x` and `z`, both as long as the modulus
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.rounds.len() as u16).to_be_bytes().to_vec();
+        for round in &self.rounds {
+            // Same flag layout as signatures: bit 0 for e = -1, bit 1 for f = 2
+            bytes.push((round.e == -1) as u8 | (((round.f == 2) as u8) << 1));
+            bytes.extend_from_slice(&to_fixed_bytes(&round.x, self.element_len));
+            bytes.extend_from_slice(&to_fixed_bytes(&round.z, self.element_len));
+        }
+        bytes
+    }
+
+    /// Decodes a proof produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 2 {
+            return Err(RabinWilliamsError::InvalidModulusProof);
+        }
+        let (count, rest) = bytes.split_at(2);
+        let count = u16::from_be_bytes([count[0], count[1]]) as usize;
+        if count == 0 || rest.len() % count != 0 || (rest.len() / count) % 2 != 1 {
+            return Err(RabinWilliamsError::InvalidModulusProof);
+        }
+        let element_len = (rest.len() / count - 1) / 2;
+        if element_len == 0 {
+            return Err(RabinWilliamsError::InvalidModulusProof);
+        }
+
+        let rounds = rest
+            .chunks(1 + 2 * element_len)
+            .map(|chunk| {
+                let flags = chunk[0];
+                if flags > 3 {
+                    return Err(RabinWilliamsError::InvalidModulusProof);
+                }
+                let (x, z) = chunk[1..].split_at(element_len);
+                Ok(ProofRound {
+                    e: if flags & 1 == 1 { -1 } else { 1 },
+                    f: if flags & 2 == 2 { 2 } else { 1 },
+                    x: BigUint::from_bytes_be(x),
+                    z: BigUint::from_bytes_be(z),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { element_len, rounds })
+    }
+}
+
+/// Derives the challenges for a modulus
+///
+/// The key's hash function binds `n` into a seed, which MGF1 expands into
+/// challenges spread over the whole of `[0, n)`.
+fn challenges<D: HashFunction>(public_key: &PublicKey<D>) -> Vec<BigUint> {
+    let n = public_key.n();
+    let seed = public_key
+        .hash_fn
+        .hash_with_context_to_bits(MODULUS_PROOF_CONTEXT, &n.to_bytes_be(), D::default_bits())
+        .to_bytes_be();
+
+    // 16 extra bytes make the reduction modulo n close to uniform
+    let len = n.bits().div_ceil(8) as usize + 16;
+    (0..PROOF_ROUNDS as u32)
+        .map(|i| BigUint::from_bytes_be(&mgf1::<D>(&[&seed[..], &i.to_be_bytes()].concat(), len)) % n)
+        .collect()
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Proves that this key's modulus is a well-formed Rabin-Williams modulus
+    ///
+    /// The proof is deterministic and reveals nothing about the factors. Fails
+    /// with `InvalidKeySize` if the primes are not 3 and 7 (mod 8).
+    pub fn prove_modulus(&self) -> Result<ModulusProof> {
+        let residues = (&self.p % 8u32, &self.q % 8u32);
+        let (three, seven) = (BigUint::from(3u32), BigUint::from(7u32));
+        if residues != (three.clone(), seven.clone()) && residues != (seven, three) {
+            return Err(RabinWilliamsError::InvalidKeySize);
+        }
+
+        let n = self.n();
+        let params = self.crt_params()?;
+        let phi = (&self.p - 1u32) * (&self.q - 1u32);
+        let n_inv = mod_inverse(&n, &phi).ok_or(RabinWilliamsError::ComputationError)?;
+
+        let rounds = challenges(&self.public_key())
+            .into_iter()
+            .map(|y| {
+                if jacobi(&y, &n) == 0 {
+                    return Err(RabinWilliamsError::ComputationError);
+                }
+                let (a, (e, f)) = make_quadratic_residue(&y, &self.p, &self.q);
+
+                // Raising a residue to (p+1)/4 gives the square root that is itself a
+                // residue, so doing it twice gives a fourth root
+                let xp = (&a % &self.p).modpow(&params.p_exp, &self.p).modpow(&params.p_exp, &self.p);
+                let xq = (&a % &self.q).modpow(&params.q_exp, &self.q).modpow(&params.q_exp, &self.q);
+                let x = self.crt_combine(&xp, &xq, &params);
+
+                let z = y.modpow(&n_inv, &n);
+                Ok(ProofRound { e, f, x, z })
+            })
+            .collect::<Result<_>>()?;
+        Ok(ModulusProof {
+            element_len: n.bits().div_ceil(8) as usize,
+            rounds,
+        })
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Checks a proof that this key's modulus is a well-formed Rabin-Williams modulus
+    pub fn verify_modulus_proof(&self, proof: &ModulusProof) -> Result<bool> {
+        let n = self.n();
+        if n % 8u32 != BigUint::from(5u32) || proof.rounds.len() != PROOF_ROUNDS {
+            return Ok(false);
+        }
+        if is_prime(n, Some(PrimalityTestConfig::default())).probably() {
+            return Ok(false);
+        }
+
+        let four = BigUint::from(4u32);
+        let valid = challenges(self).iter().zip(&proof.rounds).all(|(y, round)| {
+            if &round.x >= n || &round.z >= n {
+                return false;
+            }
+            let tweaked = y * round.f % n;
+            let tweaked = if round.e == -1 { (n - tweaked) % n } else { tweaked };
+            round.x.modpow(&four, n) == tweaked && &round.z.modpow(n, n) == y
+        });
+        Ok(valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_prove_verify_modulus() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let proof = key_pair.private.prove_modulus()?;
+        assert!(key_pair.public.verify_modulus_proof(&proof)?);
+
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), 2 + PROOF_ROUNDS * (1 + 2 * 128));
+        assert_eq!(ModulusProof::from_bytes(&bytes)?, proof);
+
+        // The proof is bound to its modulus
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        assert!(!other.public.verify_modulus_proof(&proof)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_modified_proofs() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let proof = key_pair.private.prove_modulus()?;
+
+        let mut modified = proof.clone();
+        modified.rounds[3].e = -modified.rounds[3].e;
+        assert!(!key_pair.public.verify_modulus_proof(&modified)?);
+
+        let mut modified = proof.clone();
+        modified.rounds[7].z += 1u32;
+        assert!(!key_pair.public.verify_modulus_proof(&modified)?);
+
+        let mut modified = proof.clone();
+        modified.rounds.pop();
+        assert!(!key_pair.public.verify_modulus_proof(&modified)?);
+
+        assert!(ModulusProof::from_bytes(&proof.to_bytes()[..100]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_malformed_moduli() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let p = key_pair.private.p.clone();
+
+        // Both primes are 3 (mod 8), so n ≡ 1 (mod 8) and the RW tweaks do not cover every class
+        let bad = PrivateKey::<Sha256>::from_primes(p, BigUint::from(11u32));
+        assert!(matches!(bad.prove_modulus(), Err(RabinWilliamsError::InvalidKeySize)));
+        let proof = key_pair.private.prove_modulus()?;
+        assert!(!bad.public_key().verify_modulus_proof(&proof)?);
+
+        // A proof does not carry over to a modulus with a repeated factor
+        let squared = PublicKey::<Sha256>::from_n(key_pair.public.n() * key_pair.public.n());
+        assert!(!squared.verify_modulus_proof(&proof)?);
+
+        // A prime is rejected outright
+        let prime = PublicKey::<Sha256>::from_n(BigUint::from(13u32));
+        assert!(!prime.verify_modulus_proof(&proof)?);
+
+        Ok(())
+    }
+}
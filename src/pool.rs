@@ -0,0 +1,305 @@
+//! Online/offline signing with precomputed blinding pairs
+//!
+//! Signing exponentiates the message modulo p and q with secret exponents. To
+//! keep an attacker who chooses messages from learning about the primes through
+//! timing or power traces, `sign_with_pool` signs `m·r²` for a random `r` and
+//! divides the blinding back out of each half of the root.
+//!
+//! Everything that depends only on `r` is computed ahead of time (the offline
+//! phase) and kept in a [`BlindingPool`]: `r² mod n` and, for each prime, the
+//! inverse of the factor that `r²` contributes to the square root. The online
+//! phase takes one pair from the pool and does two exponentiations and a few
+//! multiplications. A pool can refill itself on a background thread.
+//!
+//! Pool signatures are identical to those from `PrivateKey::sign`.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::HashFunction;
+use crate::keys::{CrtParams, PrivateKey};
+use crate::utils::mod_inverse;
+use num_bigint::{BigUint, RandBigInt};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// A precomputed blinding pair for one signature
+#[derive(Clone, Debug)]
+pub struct BlindingPair {
+    /// r² mod n, which multiplies the message before signing
+    pub(crate) r_squared: BigUint,
+    /// (r·(r/p))⁻¹ mod p, which removes the blinding from the root modulo p
+    pub(crate) unblind_p: BigUint,
+    /// (r·(r/q))⁻¹ mod q, which removes the blinding from the root modulo q
+    pub(crate) unblind_q: BigUint,
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Computes a fresh blinding pair for `sign_with_pool`
+    ///
+    /// Raising `m·r²` to `(p+1)/4` gives the principal root of `m` times
+    /// `r^((p+1)/2) = r·(r/p)`, where `(r/p)` is the Legendre symbol, so the pair
+    /// stores the inverse of that factor for each prime.
+    pub fn blinding_pair(&self) -> Result<BlindingPair> {
+        let n = self.n();
+        let mut rng = rand::thread_rng();
+        loop {
+            let r = rng.gen_biguint_range(&BigUint::from(2u32), &n);
+            let unblind = |prime: &BigUint| {
+                let legendre = self.prime_modpow(&r, &((prime - 1u32) / 2u32), prime);
+                mod_inverse(&(&r * legendre % prime), prime)
+            };
+            // r shares a factor with n with negligible probability; draw again if it does
+            if let (Some(unblind_p), Some(unblind_q)) = (unblind(&self.p), unblind(&self.q)) {
+                return Ok(BlindingPair {
+                    r_squared: &r * &r % &n,
+                    unblind_p,
+                    unblind_q,
+                });
+            }
+        }
+    }
+
+    /// Creates a pool of `capacity` precomputed blinding pairs for this key
+    pub fn blinding_pool(&self, capacity: usize) -> Result<BlindingPool<D>> {
+        BlindingPool::new(self, capacity)
+    }
+
+    /// Signs a message with a blinding pair taken from `pool`
+    ///
+    /// Produces the same signature as `sign`, but the secret exponentiations
+    /// only ever see blinded values. Exponents are blinded as well when the key
+    /// has side-channel blinding turned on.
+    pub fn sign_with_pool(&self, message: &[u8], pool: &BlindingPool<D>) -> Result<Vec<u8>> {
+        self.require_signing()?;
+        let n = self.n();
+        if pool.n != n {
+            return Err(RabinWilliamsError::BlindingPoolMismatch);
+        }
+        let pair = pool.take()?;
+
+        let m = self.hash_fn.hash_for_modulus(message, &n);
+        Ok(self.sign_residue(&m, &pool.params, Some(&pair)))
+    }
+}
+
+/// State shared between a pool and its refill thread
+#[derive(Debug)]
+struct Shared {
+    pairs: Mutex<VecDeque<BlindingPair>>,
+    /// Signalled when the pool drops to half its capacity or is shutting down
+    low: Condvar,
+    shutdown: AtomicBool,
+}
+
+/// A pool of precomputed blinding pairs for one private key
+///
+/// Taking a pair from an empty pool computes one on the spot, so signing never
+/// waits for a refill.
+#[derive(Debug)]
+pub struct BlindingPool<D: HashFunction> {
+    key: PrivateKey<D>,
+    n: BigUint,
+    params: CrtParams,
+    capacity: usize,
+    shared: Arc<Shared>,
+    refill_thread: Option<JoinHandle<()>>,
+}
+
+impl<D: HashFunction> BlindingPool<D> {
+    /// Creates a pool filled with `capacity` pairs
+    ///
+    /// The pool does not refill itself; call `refill` between bursts of signing
+    /// or use `with_background_refill`.
+    pub fn new(key: &PrivateKey<D>, capacity: usize) -> Result<Self> {
+        let pool = Self {
+            key: key.clone(),
+            n: key.n(),
+            params: key.crt_params()?,
+            capacity,
+            shared: Arc::new(Shared {
+                pairs: Mutex::new(VecDeque::with_capacity(capacity)),
+                low: Condvar::new(),
+                shutdown: AtomicBool::new(false),
+            }),
+            refill_thread: None,
+        };
+        pool.refill()?;
+        Ok(pool)
+    }
+
+    /// Returns the number of pairs the pool is filled to
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of pairs currently available
+    pub fn len(&self) -> usize {
+        self.shared.pairs.lock().map(|pairs| pairs.len()).unwrap_or(0)
+    }
+
+    /// Returns true if no precomputed pairs are available
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Tops the pool up to its capacity on the calling thread
+    pub fn refill(&self) -> Result<()> {
+        refill_pairs(&self.key, &self.shared, self.capacity)
+    }
+
+    /// Takes one pair, computing a fresh one if the pool is empty
+    fn take(&self) -> Result<BlindingPair> {
+        let mut pairs = self.shared.pairs.lock().map_err(|_| RabinWilliamsError::ComputationError)?;
+        let pair = pairs.pop_front();
+        if pairs.len() <= self.capacity / 2 {
+            self.shared.low.notify_one();
+        }
+        drop(pairs);
+
+        match pair {
+            Some(pair) => Ok(pair),
+            None => self.key.blinding_pair(),
+        }
+    }
+}
+
+impl<D: HashFunction + Send + 'static> BlindingPool<D> {
+    /// Creates a pool that refills itself on a background thread
+    ///
+    /// The thread wakes up whenever the pool drops to half its capacity and tops
+    /// it up again. It stops when the pool is dropped.
+    pub fn with_background_refill(key: &PrivateKey<D>, capacity: usize) -> Result<Self> {
+        let mut pool = Self::new(key, capacity)?;
+        let key = pool.key.clone();
+        let shared = Arc::clone(&pool.shared);
+
+        pool.refill_thread = Some(std::thread::spawn(move || loop {
+            let Ok(pairs) = shared.pairs.lock() else { return };
+            let Ok(pairs) = shared.low.wait_while(pairs, |pairs| {
+                pairs.len() > capacity / 2 && !shared.shutdown.load(Ordering::Acquire)
+            }) else {
+                return;
+            };
+            drop(pairs);
+
+            if shared.shutdown.load(Ordering::Acquire) || refill_pairs(&key, &shared, capacity).is_err() {
+                return;
+            }
+        }));
+        Ok(pool)
+    }
+}
+
+impl<D: HashFunction> Drop for BlindingPool<D> {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        self.shared.low.notify_all();
+        if let Some(thread) = self.refill_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Adds pairs until the pool holds `capacity`, computing each one without
+/// holding the lock so signers are not blocked
+fn refill_pairs<D: HashFunction>(key: &PrivateKey<D>, shared: &Shared, capacity: usize) -> Result<()> {
+    loop {
+        if shared.shutdown.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        {
+            let pairs = shared.pairs.lock().map_err(|_| RabinWilliamsError::ComputationError)?;
+            if pairs.len() >= capacity {
+                return Ok(());
+            }
+        }
+        let pair = key.blinding_pair()?;
+        let mut pairs = shared.pairs.lock().map_err(|_| RabinWilliamsError::ComputationError)?;
+        if pairs.len() < capacity {
+            pairs.push_back(pair);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_sign_with_pool_matches_sign() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let pool = key_pair.private.blinding_pool(4)?;
+        assert_eq!(pool.len(), 4);
+
+        // Draining the pool falls back to computing pairs on the spot
+        for i in 0..8u32 {
+            let message = i.to_be_bytes();
+            let signature = key_pair.private.sign_with_pool(&message, &pool)?;
+            assert_eq!(signature, key_pair.private.sign(&message)?);
+            assert!(key_pair.public.verify(&message, &signature)?);
+        }
+        assert!(pool.is_empty());
+
+        pool.refill()?;
+        assert_eq!(pool.len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_background_refill() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let pool = BlindingPool::with_background_refill(&key_pair.private, 8)?;
+
+        for i in 0..6u32 {
+            key_pair.private.sign_with_pool(&i.to_be_bytes(), &pool)?;
+        }
+
+        // Without the refill thread only 2 pairs would be left; the thread tops the
+        // pool up whenever a signature leaves it at half capacity or below
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while pool.len() <= pool.capacity() / 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(pool.len() > pool.capacity() / 2);
+
+        // Dropping the pool stops the thread
+        drop(pool);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pool_belongs_to_one_key() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let pool = other.private.blinding_pool(1)?;
+
+        let result = key_pair.private.sign_with_pool(b"message", &pool);
+        assert!(matches!(result, Err(RabinWilliamsError::BlindingPoolMismatch)));
+        assert_eq!(pool.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blinding_pair_unblinds() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let key = &key_pair.private;
+        let pair = key.blinding_pair()?;
+        let params = key.crt_params()?;
+
+        // The blinded principal root times the unblinding factor is the principal root
+        let m = BigUint::from(4u32);
+        let root_p = (&m * &pair.r_squared % &key.p).modpow(&params.p_exp, &key.p) * &pair.unblind_p % &key.p;
+        assert_eq!(root_p, m.modpow(&params.p_exp, &key.p));
+        let root_q = (&m * &pair.r_squared % &key.q).modpow(&params.q_exp, &key.q) * &pair.unblind_q % &key.q;
+        assert_eq!(root_q, m.modpow(&params.q_exp, &key.q));
+
+        Ok(())
+    }
+}
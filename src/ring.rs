@@ -0,0 +1,263 @@
+//! Ring signatures over Rabin-Williams public keys
+//!
+//! A ring signature shows that the holder of one of the private keys for a set
+//! of public keys signed a message, without revealing which one. This is the
+//! construction of Rivest, Shamir and Tauman ("How to Leak a Secret") with the
+//! Rabin function `x^2 mod n_i` as each member's trapdoor.
+//!
+//! Members' moduli may differ in size, so every trapdoor function is extended
+//! to a common domain of `b` bits, 160 bits more than the largest modulus:
+//! writing `x = q·n_i + r`, the extension maps `x` to `q·n_i + (r^2 mod n_i)`
+//! when `(q + 1)·n_i ≤ 2^b` and leaves `x` unchanged otherwise. The combining
+//! function chains the members' outputs through a keyed permutation on `b`-bit
+//! values, a four-round Feistel network over MGF1, keyed by the message and the
+//! ring.
+//!
+//! The Rabin function is not a permutation: the signer can only invert it on
+//! squares, so signing retries with a fresh glue value until the value it must
+//! invert is a square, about four times on average.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, mgf1, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use crate::utils::to_fixed_bytes;
+use num_bigint::BigUint;
+use num_traits::One;
+use rand::{Rng, RngCore};
+
+/// Domain separator for deriving the permutation key
+const RING_CONTEXT: &[u8] = b"rabin-williams ring signature";
+
+/// Extra bits in the common domain beyond the largest modulus
+const DOMAIN_MARGIN_BYTES: usize = 20;
+
+/// Number of Feistel rounds in the keyed permutation
+const FEISTEL_ROUNDS: u8 = 4;
+
+/// Length in bytes of the common domain for a ring, always even so it splits
+/// into two Feistel halves
+fn domain_len<D: HashFunction>(ring: &[PublicKey<D>]) -> usize {
+    let max_len = ring.iter().map(|key| key.n().bits().div_ceil(8) as usize).max().unwrap_or(0);
+    (max_len + DOMAIN_MARGIN_BYTES).next_multiple_of(2)
+}
+
+/// Derives the permutation key from the ring and the message
+fn permutation_key<D: HashFunction>(ring: &[PublicKey<D>], message: &[u8]) -> Vec<u8> {
+    let mut encoded_ring = (ring.len() as u32).to_be_bytes().to_vec();
+    for key in ring {
+        let n = key.n().to_bytes_be();
+        encoded_ring.extend_from_slice(&(n.len() as u32).to_be_bytes());
+        encoded_ring.extend_from_slice(&n);
+    }
+    digest::<D>(&[RING_CONTEXT, &encoded_ring, message])
+}
+
+/// Feistel round function
+fn round_function<D: HashFunction>(key: &[u8], round: u8, half: &[u8]) -> Vec<u8> {
+    mgf1::<D>(&[key, &[round], half].concat(), half.len())
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+/// The keyed permutation E_k on domain values
+fn permute<D: HashFunction>(key: &[u8], block: &[u8]) -> Vec<u8> {
+    let (left, right) = block.split_at(block.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+    for round in 0..FEISTEL_ROUNDS {
+        let next = xor(&left, &round_function::<D>(key, round, &right));
+        left = std::mem::replace(&mut right, next);
+    }
+    [left, right].concat()
+}
+
+/// The inverse permutation E_k⁻¹
+fn unpermute<D: HashFunction>(key: &[u8], block: &[u8]) -> Vec<u8> {
+    let (left, right) = block.split_at(block.len() / 2);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+    for round in (0..FEISTEL_ROUNDS).rev() {
+        let previous = xor(&right, &round_function::<D>(key, round, &left));
+        right = std::mem::replace(&mut left, previous);
+    }
+    [left, right].concat()
+}
+
+/// Returns true if `x = q·n + r` lies in a full block of the domain, where the
+/// extended function squares `r`
+fn in_full_block(q: &BigUint, n: &BigUint, len: usize) -> bool {
+    (q + 1u32) * n <= BigUint::one() << (8 * len)
+}
+
+/// The Rabin function extended to the common domain
+fn extended_square(n: &BigUint, x: &[u8]) -> Vec<u8> {
+    let len = x.len();
+    let x = BigUint::from_bytes_be(x);
+    let (q, r) = (&x / n, &x % n);
+    if in_full_block(&q, n, len) {
+        to_fixed_bytes(&(q * n + &r * &r % n), len)
+    } else {
+        to_fixed_bytes(&x, len)
+    }
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Signs a message on behalf of a ring of public keys that includes this key's
+    ///
+    /// The signature shows that one member of the ring signed, without revealing
+    /// which. Verify it with [`verify_ring`] and the same ring, in the same order.
+    pub fn sign_ring(&self, ring: &[PublicKey<D>], message: &[u8]) -> Result<Vec<u8>> {
+        self.require_signing()?;
+        for member in ring {
+            member.require_signing()?;
+        }
+        let n = self.n();
+        let signer = ring.iter().position(|key| key.n() == &n).ok_or(RabinWilliamsError::SignerNotInRing)?;
+
+        let len = domain_len(ring);
+        let key = permutation_key(ring, message);
+        let mut rng = rand::thread_rng();
+        let mut random_block = || {
+            let mut block = vec![0u8; len];
+            rng.fill_bytes(&mut block);
+            block
+        };
+
+        loop {
+            let glue = random_block();
+            let xs: Vec<Vec<u8>> = ring.iter().map(|_| random_block()).collect();
+
+            // Run the ring equation forward up to the signer and backward from the
+            // glue down to it; the signer's output must bridge the gap
+            let mut before = glue.clone();
+            for (member, x) in ring.iter().zip(&xs).take(signer) {
+                before = permute::<D>(&key, &xor(&before, &extended_square(member.n(), x)));
+            }
+            let mut after = glue.clone();
+            for (member, x) in ring.iter().zip(&xs).skip(signer + 1).rev() {
+                after = xor(&unpermute::<D>(&key, &after), &extended_square(member.n(), x));
+            }
+            let y = BigUint::from_bytes_be(&xor(&unpermute::<D>(&key, &after), &before));
+
+            // Invert the extended function at y, with a random choice among the roots
+            let (q, r) = (&y / &n, &y % &n);
+            let x = if in_full_block(&q, &n, len) {
+                match self.square_roots(&r) {
+                    Ok(roots) => q * &n + &roots[rng.gen_range(0..4)],
+                    Err(_) => continue,
+                }
+            } else {
+                y
+            };
+
+            let mut xs = xs;
+            xs[signer] = to_fixed_bytes(&x, len);
+            return Ok([vec![glue], xs].concat().concat());
+        }
+    }
+}
+
+/// Verifies a ring signature produced by `PrivateKey::sign_ring`
+///
+/// The ring must list the same keys in the same order as when signing.
+pub fn verify_ring<D: HashFunction>(ring: &[PublicKey<D>], message: &[u8], signature: &[u8]) -> Result<bool> {
+    if ring.is_empty() {
+        return Err(RabinWilliamsError::EmptyBatch);
+    }
+    for member in ring {
+        member.require_signing()?;
+    }
+
+    let len = domain_len(ring);
+    if signature.len() != len * (ring.len() + 1) {
+        return Err(RabinWilliamsError::InvalidSignature);
+    }
+    let key = permutation_key(ring, message);
+
+    let mut blocks = signature.chunks(len);
+    let glue = blocks.next().ok_or(RabinWilliamsError::InvalidSignature)?;
+    let mut value = glue.to_vec();
+    for (member, x) in ring.iter().zip(blocks) {
+        value = permute::<D>(&key, &xor(&value, &extended_square(member.n(), x)));
+    }
+    Ok(value == glue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    fn ring_of(key_pairs: &[KeyPair<Sha256>]) -> Vec<PublicKey<Sha256>> {
+        key_pairs.iter().map(|key_pair| key_pair.public.clone()).collect()
+    }
+
+    #[test]
+    fn test_sign_verify_ring() -> Result<()> {
+        // Members may have moduli of different sizes
+        let key_pairs = vec![KeyPair::generate(1024)?, KeyPair::generate(1152)?, KeyPair::generate(1024)?];
+        let ring = ring_of(&key_pairs);
+        let message = b"the documents are authentic";
+
+        for key_pair in &key_pairs {
+            let signature = key_pair.private.sign_ring(&ring, message)?;
+            assert_eq!(signature.len(), 4 * domain_len(&ring));
+            assert!(verify_ring(&ring, message, &signature)?);
+            assert!(!verify_ring(&ring, b"the documents are forged", &signature)?);
+        }
+
+        // A ring of one is an ordinary (if large) signature
+        let alone = ring_of(&key_pairs[..1]);
+        let signature = key_pairs[0].private.sign_ring(&alone, message)?;
+        assert!(verify_ring(&alone, message, &signature)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signature_is_bound_to_ring() -> Result<()> {
+        let key_pairs = vec![KeyPair::generate(1024)?, KeyPair::generate(1024)?, KeyPair::generate(1024)?];
+        let ring = ring_of(&key_pairs);
+        let message = b"message";
+        let signature = key_pairs[1].private.sign_ring(&ring, message)?;
+
+        let reordered = vec![ring[1].clone(), ring[0].clone(), ring[2].clone()];
+        assert!(!verify_ring(&reordered, message, &signature)?);
+
+        let mut replaced = ring.clone();
+        replaced[2] = KeyPair::generate(1024)?.public;
+        assert!(!verify_ring(&replaced, message, &signature)?);
+
+        assert!(verify_ring(&ring[..2], message, &signature).is_err());
+
+        let mut modified = signature.clone();
+        modified[10] ^= 1;
+        assert!(!verify_ring(&ring, message, &modified)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signer_must_be_in_ring() -> Result<()> {
+        let key_pairs = vec![KeyPair::generate(1024)?, KeyPair::generate(1024)?];
+        let outsider: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let result = outsider.private.sign_ring(&ring_of(&key_pairs), b"message");
+        assert!(matches!(result, Err(RabinWilliamsError::SignerNotInRing)));
+        assert!(matches!(verify_ring::<Sha256>(&[], b"message", &[]), Err(RabinWilliamsError::EmptyBatch)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_permutation_roundtrip() {
+        let key = b"key";
+        let block: Vec<u8> = (0..148).collect();
+        let permuted = permute::<Sha256>(key, &block);
+
+        assert_ne!(permuted, block);
+        assert_eq!(unpermute::<Sha256>(key, &permuted), block);
+        assert_ne!(permute::<Sha256>(b"other key", &block), permuted);
+    }
+}
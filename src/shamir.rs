@@ -0,0 +1,346 @@
+//! Shamir secret-sharing backups of private keys
+//!
+//! A private key is determined by `p` once `n` is known, so only `p` is shared.
+//! It becomes the constant term of a random polynomial of degree `threshold - 1`
+//! over the prime field of the smallest prime above `2^(8·len)`, where `len` is
+//! the length of `p` in bytes. Share `i` is the polynomial evaluated at `i`; any
+//! `threshold` shares recover `p` by Lagrange interpolation, and fewer reveal
+//! nothing about it.
+//!
+//! Each encoded share carries a fingerprint of the public key and a checksum, so
+//! shares of different keys or damaged shares are caught before recovery, and
+//! the recovered prime is checked against `n`. Version 2 shares use the leading
+//! bytes of the key's [`Fingerprint`](crate::Fingerprint); version 1 shares,
+//! which are still accepted, used a hash of `n` alone.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use crate::utils::mod_inverse;
+use num_bigint::{BigUint, RandBigInt};
+use num_prime::nt_funcs::next_prime;
+use num_prime::PrimalityTestConfig;
+use num_traits::{One, Zero};
+use sha2::Sha256;
+
+/// Version byte at the start of every encoded share
+const SHARE_VERSION: u8 = 2;
+
+/// Version of shares whose fingerprint is a hash of `n` alone
+const LEGACY_SHARE_VERSION: u8 = 1;
+
+/// Domain separator for version 1 share fingerprints
+const LEGACY_SHARE_FINGERPRINT_CONTEXT: &[u8] = b"rabin-williams key share";
+
+/// Length in bytes of the key fingerprint in a share
+pub const SHARE_FINGERPRINT_LEN: usize = 8;
+
+/// Length in bytes of the checksum at the end of an encoded share
+const SHARE_CHECKSUM_LEN: usize = 4;
+
+/// One share of a private key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyShare {
+    version: u8,
+    fingerprint: [u8; SHARE_FINGERPRINT_LEN],
+    threshold: u8,
+    index: u8,
+    /// Length in bytes of the shared prime, which fixes the field
+    prime_len: u16,
+    value: BigUint,
+}
+
+impl KeyShare {
+    /// Returns the x-coordinate of the share, from 1 to the number of shares
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the number of shares needed to recover the key
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Returns the fingerprint of the public key the share belongs to
+    pub fn fingerprint(&self) -> &[u8] {
+        &self.fingerprint
+    }
+
+    /// Encodes the share as a version byte, the fingerprint, the threshold and
+    /// index bytes, the 2-byte big-endian prime length, the value and a checksum
+    ///
+    /// The value is one byte longer than the prime, which is enough for any
+    /// element of the field.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.version];
+        bytes.extend_from_slice(&self.fingerprint);
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.prime_len.to_be_bytes());
+        let value = self.value.to_bytes_be();
+        bytes.resize(bytes.len() + self.prime_len as usize + 1 - value.len(), 0);
+        bytes.extend_from_slice(&value);
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        bytes
+    }
+
+    /// Decodes a share produced by `to_bytes`
+    ///
+    /// Fails with `InvalidShare` if the encoding or checksum is wrong.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header_len = 1 + SHARE_FINGERPRINT_LEN + 4;
+        if bytes.len() < header_len + SHARE_CHECKSUM_LEN || ![LEGACY_SHARE_VERSION, SHARE_VERSION].contains(&bytes[0]) {
+            return Err(RabinWilliamsError::InvalidShare);
+        }
+        let (body, check) = bytes.split_at(bytes.len() - SHARE_CHECKSUM_LEN);
+        if checksum(body) != check {
+            return Err(RabinWilliamsError::InvalidShare);
+        }
+
+        let (header, value) = body.split_at(header_len);
+        let fingerprint = header[1..1 + SHARE_FINGERPRINT_LEN].try_into().map_err(|_| RabinWilliamsError::InvalidShare)?;
+        let [threshold, index, len_hi, len_lo] = header[1 + SHARE_FINGERPRINT_LEN..] else {
+            return Err(RabinWilliamsError::InvalidShare);
+        };
+        let prime_len = u16::from_be_bytes([len_hi, len_lo]);
+        if threshold == 0 || index == 0 || prime_len == 0 || value.len() != prime_len as usize + 1 {
+            return Err(RabinWilliamsError::InvalidShare);
+        }
+
+        Ok(Self {
+            version: header[0],
+            fingerprint,
+            threshold,
+            index,
+            prime_len,
+            value: BigUint::from_bytes_be(value),
+        })
+    }
+}
+
+fn checksum(bytes: &[u8]) -> Vec<u8> {
+    digest::<Sha256>(&[bytes])[..SHARE_CHECKSUM_LEN].to_vec()
+}
+
+/// Fingerprint identifying the public key a share of the given version belongs
+/// to: the leading bytes of the key's fingerprint, or for version 1 of a hash of `n`
+fn share_fingerprint<D: HashFunction>(public_key: &PublicKey<D>, version: u8) -> [u8; SHARE_FINGERPRINT_LEN] {
+    let hash = if version == LEGACY_SHARE_VERSION {
+        digest::<Sha256>(&[LEGACY_SHARE_FINGERPRINT_CONTEXT, &public_key.n().to_bytes_be()])
+    } else {
+        public_key.fingerprint().as_bytes().to_vec()
+    };
+    let mut fingerprint = [0u8; SHARE_FINGERPRINT_LEN];
+    fingerprint.copy_from_slice(&hash[..SHARE_FINGERPRINT_LEN]);
+    fingerprint
+}
+
+/// Evaluates a polynomial, given by its coefficients from the constant term up,
+/// at `x` over the field
+pub(crate) fn evaluate_polynomial(coefficients: &[BigUint], x: &BigUint, field: &BigUint) -> BigUint {
+    // Horner's rule, from the highest coefficient down
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigUint::zero(), |acc, coefficient| (acc * x + coefficient) % field)
+}
+
+/// Interpolates the polynomial through `points` at x = 0 over the field
+///
+/// The x-coordinates must be distinct and nonzero.
+pub(crate) fn interpolate_at_zero(points: &[(BigUint, BigUint)], field: &BigUint) -> Result<BigUint> {
+    let mut value = BigUint::zero();
+    for (xi, yi) in points {
+        let (mut numerator, mut denominator) = (BigUint::one(), BigUint::one());
+        for (xj, _) in points.iter().filter(|(xj, _)| xj != xi) {
+            numerator = numerator * xj % field;
+            denominator = denominator * ((xj + field - xi) % field) % field;
+        }
+        let inverse = mod_inverse(&denominator, field).ok_or(RabinWilliamsError::ComputationError)?;
+        value = (value + yi * numerator % field * inverse) % field;
+    }
+    Ok(value)
+}
+
+/// The field prime for sharing primes of `prime_len` bytes
+fn field_prime(prime_len: u16) -> Result<BigUint> {
+    next_prime(&(BigUint::one() << (8 * prime_len as usize)), Some(PrimalityTestConfig::default()))
+        .ok_or(RabinWilliamsError::ComputationError)
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Splits the key into `shares` shares, any `threshold` of which recover it
+    ///
+    /// Fails with `InvalidThreshold` unless `1 <= threshold <= shares`. With a
+    /// threshold of 1 every share is a full copy of the key.
+    pub fn split(&self, threshold: u8, shares: u8) -> Result<Vec<KeyShare>> {
+        if threshold == 0 || threshold > shares {
+            return Err(RabinWilliamsError::InvalidThreshold);
+        }
+        let prime_len = u16::try_from(self.p.bits().div_ceil(8)).map_err(|_| RabinWilliamsError::InvalidKeySize)?;
+        let field = field_prime(prime_len)?;
+
+        let mut rng = rand::thread_rng();
+        let mut coefficients = vec![self.p.clone()];
+        coefficients.extend((1..threshold).map(|_| rng.gen_biguint_below(&field)));
+
+        let fingerprint = share_fingerprint(&self.public_key(), SHARE_VERSION);
+        Ok((1..=shares)
+            .map(|index| KeyShare {
+                version: SHARE_VERSION,
+                fingerprint,
+                threshold,
+                index,
+                prime_len,
+                value: evaluate_polynomial(&coefficients, &BigUint::from(index), &field),
+            })
+            .collect())
+    }
+
+    /// Recovers a private key from shares produced by `split`
+    ///
+    /// Fails with `ShareMismatch` if the shares belong to another key or disagree
+    /// with each other, `NotEnoughShares` if fewer than the threshold are given,
+    /// and `InvalidShare` if the recovered prime does not divide `n`. The key
+    /// takes its hash function and usage from `public_key`.
+    pub fn recover(shares: &[KeyShare], public_key: &PublicKey<D>) -> Result<Self> {
+        let first = shares.first().ok_or(RabinWilliamsError::NotEnoughShares)?;
+        for (i, share) in shares.iter().enumerate() {
+            let consistent = share.fingerprint == share_fingerprint(public_key, share.version)
+                && share.threshold == first.threshold
+                && share.prime_len == first.prime_len;
+            if !consistent || shares[..i].iter().any(|other| other.index == share.index) {
+                return Err(RabinWilliamsError::ShareMismatch);
+            }
+        }
+        if shares.len() < first.threshold as usize {
+            return Err(RabinWilliamsError::NotEnoughShares);
+        }
+
+        // Lagrange interpolation at x = 0 over the first `threshold` shares
+        let field = field_prime(first.prime_len)?;
+        let points: Vec<(BigUint, BigUint)> = shares[..first.threshold as usize]
+            .iter()
+            .map(|share| (BigUint::from(share.index), share.value.clone()))
+            .collect();
+        let p = interpolate_at_zero(&points, &field)?;
+
+        let n = public_key.n();
+        if p <= BigUint::one() || &p >= n || !(n % &p).is_zero() {
+            return Err(RabinWilliamsError::InvalidShare);
+        }
+        let q = n / &p;
+        let mut key = Self::from_primes(p, q).with_usage(public_key.usage());
+        key.hash_fn = public_key.hash_fn.clone();
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+
+    #[test]
+    fn test_split_recover() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let shares = key_pair.private.split(3, 5)?;
+        assert_eq!(shares.len(), 5);
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<KeyShare> = subset.iter().map(|&i| shares[i].clone()).collect();
+            let recovered = PrivateKey::recover(&subset, &key_pair.public)?;
+            assert_eq!((&recovered.p, &recovered.q), (&key_pair.private.p, &key_pair.private.q));
+            assert!(key_pair.public.verify(b"message", &recovered.sign(b"message")?)?);
+        }
+
+        // Extra shares are fine, too few are not
+        PrivateKey::recover(&shares, &key_pair.public)?;
+        let result = PrivateKey::recover(&shares[..2], &key_pair.public);
+        assert!(matches!(result, Err(RabinWilliamsError::NotEnoughShares)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_share_encoding() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let share = key_pair.private.split(2, 3)?.remove(1);
+
+        let bytes = share.to_bytes();
+        assert_eq!(bytes.len(), 1 + SHARE_FINGERPRINT_LEN + 4 + 65 + SHARE_CHECKSUM_LEN);
+        assert_eq!(KeyShare::from_bytes(&bytes)?, share);
+        assert_eq!((share.index(), share.threshold()), (2, 2));
+
+        // Any damage is caught by the checksum
+        for position in [0, 5, 10, 40, bytes.len() - 1] {
+            let mut damaged = bytes.clone();
+            damaged[position] ^= 1;
+            assert!(matches!(KeyShare::from_bytes(&damaged), Err(RabinWilliamsError::InvalidShare)));
+        }
+        assert!(KeyShare::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recovers_version_1_shares() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        // Version 1 shares differ only in their version byte and fingerprint
+        let legacy: Vec<KeyShare> = key_pair
+            .private
+            .split(2, 3)?
+            .into_iter()
+            .map(|share| {
+                let mut bytes = share.to_bytes();
+                bytes[0] = LEGACY_SHARE_VERSION;
+                let hash = digest::<Sha256>(&[b"rabin-williams key share", &key_pair.public.n().to_bytes_be()]);
+                bytes[1..1 + SHARE_FINGERPRINT_LEN].copy_from_slice(&hash[..SHARE_FINGERPRINT_LEN]);
+                let body_len = bytes.len() - SHARE_CHECKSUM_LEN;
+                let check = checksum(&bytes[..body_len]);
+                bytes[body_len..].copy_from_slice(&check);
+                KeyShare::from_bytes(&bytes)
+            })
+            .collect::<Result<_>>()?;
+        assert_eq!(legacy[0].to_bytes()[0], 1);
+
+        let recovered = PrivateKey::recover(&legacy[..2], &key_pair.public)?;
+        assert_eq!((&recovered.p, &recovered.q), (&key_pair.private.p, &key_pair.private.q));
+
+        // A version 1 share is still tied to its key
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let result = PrivateKey::recover(&legacy[..2], &other.public);
+        assert!(matches!(result, Err(RabinWilliamsError::ShareMismatch)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_mismatched_shares() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let shares = key_pair.private.split(2, 3)?;
+        let other_shares = other.private.split(2, 3)?;
+
+        let result = PrivateKey::recover(&shares[..2], &other.public);
+        assert!(matches!(result, Err(RabinWilliamsError::ShareMismatch)));
+
+        let mixed = vec![shares[0].clone(), other_shares[1].clone()];
+        assert!(matches!(PrivateKey::recover(&mixed, &key_pair.public), Err(RabinWilliamsError::ShareMismatch)));
+
+        let repeated = vec![shares[0].clone(), shares[0].clone()];
+        assert!(matches!(PrivateKey::recover(&repeated, &key_pair.public), Err(RabinWilliamsError::ShareMismatch)));
+
+        // Shares from two splittings of the same key do not combine
+        let resplit = key_pair.private.split(2, 3)?;
+        let mixed = vec![shares[0].clone(), resplit[1].clone()];
+        assert!(matches!(PrivateKey::recover(&mixed, &key_pair.public), Err(RabinWilliamsError::InvalidShare)));
+
+        assert!(matches!(key_pair.private.split(4, 3), Err(RabinWilliamsError::InvalidThreshold)));
+        assert!(matches!(key_pair.private.split(0, 3), Err(RabinWilliamsError::InvalidThreshold)));
+
+        Ok(())
+    }
+}
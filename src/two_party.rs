@@ -0,0 +1,555 @@
+//! Two-party signing where neither party holds the factors of n
+//!
+//! For p ≡ 3 and q ≡ 7 (mod 8), Williams' exponent `d = (φ(n)/4 + 1) / 2`
+//! signs without the factors: if `a = f·m` has Jacobi symbol 1, `a^d` is the
+//! principal square root of `e·a` with `e = ±1`, and `e` shows in whether it
+//! squares to `a` or `-a`. `d` is even, so the result is the root that
+//! `raw_sign` computes with the CRT and the signatures are identical.
+//!
+//! Splitting the key writes `d = d₁ + d₂ (mod φ(n))` with `d₁` uniform, so each
+//! share on its own says nothing about `d`, and the factors are not kept. A
+//! combiner sends the message to both parties, each party hashes it to `a`
+//! itself and returns `yᵢ = a^dᵢ`, and the combiner multiplies the results.
+//!
+//! Parties only ever exponentiate hashed and tweaked messages with Jacobi
+//! symbol 1. Raising a value the requester chose to `dᵢ` would be unsafe: the
+//! two answers combine to the principal square root of that value, and roots
+//! of chosen squares, or of values with Jacobi symbol -1, factor `n`.
+//!
+//! Each partial result carries a proof that `log_a(yᵢ) = log_g(vᵢ)`, where
+//! `vᵢ = g^dᵢ` is the party's verification key for a public square `g`, so the
+//! combiner rejects a wrong partial result and names the party that sent it. A
+//! party can only negate its result undetected, which yields another valid
+//! signature.
+//!
+//! The parties sign whatever message they are sent, so the combiner, and anyone
+//! who can reach a party, can obtain signatures on messages of its choice.
+//! Splitting protects the key material, not the signing capability: the
+//! combiner must be trusted, and party endpoints must only accept it.
+//!
+//! Parties can run in process or behind a local socket, see [`serve`] and
+//! [`SocketSigner`].
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use crate::utils::{jacobi, mod_inverse, to_fixed_bytes};
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use std::marker::PhantomData;
+
+/// Domain separator for partial-signature proof challenges
+const PARTIAL_PROOF_CONTEXT: &[u8] = b"rabin-williams two-party partial signature";
+
+/// Extra bits in proof nonces so that responses hide the exponent share
+const STATISTICAL_SECURITY_BITS: u64 = 128;
+
+/// Encodes fields as 16-bit length-prefixed byte strings
+fn encode_fields(fields: &[&[u8]]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for field in fields {
+        let len = u16::try_from(field.len()).map_err(|_| RabinWilliamsError::MessageTooLarge)?;
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(field);
+    }
+    Ok(bytes)
+}
+
+/// Decodes exactly `count` fields produced by `encode_fields`
+fn decode_fields(mut bytes: &[u8], count: usize) -> Option<Vec<&[u8]>> {
+    let mut fields = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]) as usize;
+        fields.push(bytes.get(2..2 + len)?);
+        bytes = &bytes[2 + len..];
+    }
+    bytes.is_empty().then_some(fields)
+}
+
+/// One party's share of the signing exponent
+#[derive(Clone, Debug)]
+pub struct SigningShare<D: HashFunction> {
+    /// 1 or 2
+    index: u8,
+    n: BigUint,
+    /// Public base of the verification keys
+    g: BigUint,
+    d: BigUint,
+    _hash: PhantomData<D>,
+}
+
+/// A partial signature and the proof that it was computed with the party's share
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartialSignature {
+    index: u8,
+    /// a^dᵢ mod n
+    value: BigUint,
+    challenge: BigUint,
+    response: BigUint,
+}
+
+impl PartialSignature {
+    /// Returns the index of the party that produced the partial signature
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Encodes the partial signature as the index byte followed by the value,
+    /// challenge and response, each length-prefixed
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let fields = encode_fields(&[
+            &self.value.to_bytes_be(),
+            &self.challenge.to_bytes_be(),
+            &self.response.to_bytes_be(),
+        ])?;
+        Ok([vec![self.index], fields].concat())
+    }
+
+    /// Decodes a partial signature produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (&index, rest) = bytes.split_first().ok_or(RabinWilliamsError::InvalidPartialSignature(0))?;
+        let fields = decode_fields(rest, 3).ok_or(RabinWilliamsError::InvalidPartialSignature(index))?;
+        Ok(Self {
+            index,
+            value: BigUint::from_bytes_be(fields[0]),
+            challenge: BigUint::from_bytes_be(fields[1]),
+            response: BigUint::from_bytes_be(fields[2]),
+        })
+    }
+}
+
+/// Derives the proof challenge from the statement and the prover's commitments
+fn challenge<D: HashFunction>(n: &BigUint, elements: [&BigUint; 6]) -> BigUint {
+    let len = n.bits().div_ceil(8) as usize;
+    let encoded: Vec<Vec<u8>> = elements.iter().map(|element| to_fixed_bytes(element, len)).collect();
+    let mut parts: Vec<&[u8]> = vec![PARTIAL_PROOF_CONTEXT, &encoded[0]];
+    parts.extend(encoded[1..].iter().map(Vec::as_slice));
+    BigUint::from_bytes_be(&digest::<D>(&parts))
+}
+
+impl<D: HashFunction> SigningShare<D> {
+    /// Returns the party's index, 1 or 2
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the public verification key `g^dᵢ` for this share
+    pub fn verification_key(&self) -> BigUint {
+        self.g.modpow(&self.d, &self.n)
+    }
+
+    /// Computes this party's partial signature on a message, with a proof of
+    /// correctness
+    ///
+    /// The party hashes and tweaks the message itself, so a requester cannot
+    /// choose the value that is exponentiated.
+    pub fn partial_sign(&self, message: &[u8]) -> Result<PartialSignature> {
+        let (_, a) = williams_input(&PublicKey::<D>::from_n(self.n.clone()), message)?;
+        self.partial_sign_input(&a)
+    }
+
+    /// Computes the partial signature on a hashed and tweaked message `a`
+    ///
+    /// Fails with `InvalidSignature` unless `a` is in `[1, n)` with Jacobi
+    /// symbol 1; for other values the combined root would factor `n`.
+    fn partial_sign_input(&self, a: &BigUint) -> Result<PartialSignature> {
+        if a.is_zero() || a >= &self.n || jacobi(a, &self.n) != 1 {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+        let value = a.modpow(&self.d, &self.n);
+
+        // Chaum-Pedersen proof of equal discrete logs in a group of unknown order:
+        // the response is computed over the integers with a nonce large enough to
+        // hide d·c
+        let nonce_bits = self.n.bits() + D::default_bits() as u64 + STATISTICAL_SECURITY_BITS;
+        let r = rand::thread_rng().gen_biguint(nonce_bits);
+        let (g_r, a_r) = (self.g.modpow(&r, &self.n), a.modpow(&r, &self.n));
+        let verification_key = self.verification_key();
+        let challenge = challenge::<D>(&self.n, [&self.g, a, &verification_key, &value, &g_r, &a_r]);
+        let response = &self.d * &challenge + r;
+
+        Ok(PartialSignature {
+            index: self.index,
+            value,
+            challenge,
+            response,
+        })
+    }
+
+    /// Encodes the share as the index byte followed by n, g and the exponent
+    /// share, each length-prefixed
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let fields = encode_fields(&[&self.n.to_bytes_be(), &self.g.to_bytes_be(), &self.d.to_bytes_be()])?;
+        Ok([vec![self.index], fields].concat())
+    }
+
+    /// Decodes a share produced by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (&index, rest) = bytes.split_first().ok_or(RabinWilliamsError::InvalidShare)?;
+        let fields = decode_fields(rest, 3).ok_or(RabinWilliamsError::InvalidShare)?;
+        if !(1..=2).contains(&index) {
+            return Err(RabinWilliamsError::InvalidShare);
+        }
+        Ok(Self {
+            index,
+            n: BigUint::from_bytes_be(fields[0]),
+            g: BigUint::from_bytes_be(fields[1]),
+            d: BigUint::from_bytes_be(fields[2]),
+            _hash: PhantomData,
+        })
+    }
+}
+
+/// Something that returns a party's partial signatures, in process or remotely
+pub trait PartialSigner {
+    /// Requests a partial signature on a message
+    fn partial_sign(&self, message: &[u8]) -> Result<PartialSignature>;
+}
+
+impl<D: HashFunction> PartialSigner for SigningShare<D> {
+    fn partial_sign(&self, message: &[u8]) -> Result<PartialSignature> {
+        SigningShare::partial_sign(self, message)
+    }
+}
+
+/// Public state for combining partial signatures into signatures
+#[derive(Clone, Debug)]
+pub struct Combiner<D: HashFunction> {
+    public_key: PublicKey<D>,
+    g: BigUint,
+    /// Verification keys of parties 1 and 2
+    verification_keys: [BigUint; 2],
+}
+
+impl<D: HashFunction> Combiner<D> {
+    /// Creates a combiner from the public key, the base `g` and the parties'
+    /// verification keys, in index order
+    pub fn new(public_key: PublicKey<D>, g: BigUint, verification_keys: [BigUint; 2]) -> Self {
+        Self {
+            public_key,
+            g,
+            verification_keys,
+        }
+    }
+
+    /// Returns the public key signatures verify under
+    pub fn public_key(&self) -> &PublicKey<D> {
+        &self.public_key
+    }
+
+    /// Checks a party's partial signature on a message against its verification key
+    pub fn verify_partial(&self, message: &[u8], partial: &PartialSignature) -> Result<bool> {
+        let (_, a) = williams_input(&self.public_key, message)?;
+        Ok(self.verify_partial_input(&a, partial))
+    }
+
+    /// Checks a partial signature on the hashed and tweaked message `a`
+    fn verify_partial_input(&self, a: &BigUint, partial: &PartialSignature) -> bool {
+        let n = self.public_key.n();
+        let Some(verification_key) = partial
+            .index
+            .checked_sub(1)
+            .and_then(|i| self.verification_keys.get(i as usize))
+        else {
+            return false;
+        };
+        if &partial.value >= n {
+            return false;
+        }
+        let (Some(v_inv), Some(y_inv)) = (mod_inverse(verification_key, n), mod_inverse(&partial.value, n)) else {
+            return false;
+        };
+
+        // Recompute the commitments g^r = g^z·v^-c and a^r = a^z·y^-c
+        let g_r = self.g.modpow(&partial.response, n) * v_inv.modpow(&partial.challenge, n) % n;
+        let a_r = a.modpow(&partial.response, n) * y_inv.modpow(&partial.challenge, n) % n;
+        challenge::<D>(n, [&self.g, a, verification_key, &partial.value, &g_r, &a_r]) == partial.challenge
+    }
+
+    /// Signs a message with the help of both parties
+    ///
+    /// Produces the same signature as `PrivateKey::sign`. Fails with
+    /// `InvalidPartialSignature` naming the party whose partial signature does
+    /// not verify.
+    pub fn sign(&self, message: &[u8], parties: [&dyn PartialSigner; 2]) -> Result<Vec<u8>> {
+        let (f, a) = williams_input(&self.public_key, message)?;
+        let n = self.public_key.n();
+
+        let mut x = BigUint::one();
+        for (index, party) in (1u8..).zip(parties) {
+            let partial = party.partial_sign(message)?;
+            if partial.index != index || !self.verify_partial_input(&a, &partial) {
+                return Err(RabinWilliamsError::InvalidPartialSignature(index));
+            }
+            x = x * &partial.value % n;
+        }
+        williams_signature::<D>(n, &a, f, &x)
+    }
+}
+
+/// Hashes a message and applies the tweak `f` that gives it Jacobi symbol 1,
+/// returning `f` and the value `a = f·H(m)` to raise to Williams' exponent
+pub(crate) fn williams_input<D: HashFunction>(public_key: &PublicKey<D>, message: &[u8]) -> Result<(u32, BigUint)> {
+    public_key.require_signing()?;
+    let n = public_key.n();
+    let m = public_key.hash_fn.hash_for_modulus(message, n);
+
+    // f = 2 fixes the Jacobi symbol, since (2/n) = -1 for n ≡ 5 (mod 8)
+    let f = match jacobi(&m, n) {
+        1 => 1u32,
+        -1 => 2u32,
+        _ => return Err(RabinWilliamsError::ComputationError),
+    };
+    Ok((f, m * f % n))
+}
+
+/// Packs `x = a^d` as a signature, reading `e` off whether x² is `a` or `-a`
+pub(crate) fn williams_signature<D: HashFunction>(n: &BigUint, a: &BigUint, f: u32, x: &BigUint) -> Result<Vec<u8>> {
+    let e = if x * x % n == *a {
+        1
+    } else if (x * x + a) % n == BigUint::zero() {
+        -1
+    } else {
+        return Err(RabinWilliamsError::ComputationError);
+    };
+    Ok(PrivateKey::<D>::pack_signature(e, f, x))
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Splits the signing capability between two parties
+    ///
+    /// Returns the parties' shares, in index order, and a combiner. Neither share
+    /// reveals the factors; discard this private key once the shares are handed out.
+    pub fn split_signing(&self) -> Result<([SigningShare<D>; 2], Combiner<D>)> {
+        self.require_signing()?;
+        let n = self.n();
+        let phi = (&self.p - 1u32) * (&self.q - 1u32);
+        let d = (&phi / 4u32 + 1u32) / 2u32;
+
+        let mut rng = rand::thread_rng();
+        let d1 = rng.gen_biguint_below(&phi);
+        let d2 = (&d + &phi - &d1) % &phi;
+
+        // A random square generates (with overwhelming probability) a large subgroup
+        let g = loop {
+            let r = rng.gen_biguint_range(&BigUint::from(2u32), &n);
+            if jacobi(&r, &n) != 0 {
+                break &r * &r % &n;
+            }
+        };
+
+        let share = |index, d| SigningShare {
+            index,
+            n: n.clone(),
+            g: g.clone(),
+            d,
+            _hash: PhantomData,
+        };
+        let shares = [share(1, d1), share(2, d2)];
+        let verification_keys = [shares[0].verification_key(), shares[1].verification_key()];
+        let combiner = Combiner {
+            public_key: self.public_key(),
+            g,
+            verification_keys,
+        };
+        Ok((shares, combiner))
+    }
+}
+
+#[cfg(unix)]
+pub use socket::{serve, serve_connection, SocketSigner, MAX_MESSAGE_LEN};
+
+/// Local-socket transport: a party serves its share on a Unix domain socket
+///
+/// Requests are the length-prefixed message, and responses the length-prefixed
+/// encoded partial signature. Connections are not authenticated, so the
+/// socket's permissions must limit it to the combiner.
+#[cfg(unix)]
+mod socket {
+    use super::*;
+    use crate::utils::{read_frame, write_frame};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+
+    /// Longest message a party accepts over its socket
+    pub const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+    /// Longest partial signature a `SocketSigner` accepts in response
+    const MAX_RESPONSE_LEN: usize = 1 << 16;
+
+    /// Answers one request on an accepted connection
+    ///
+    /// Closes the connection without a response if the request is invalid.
+    pub fn serve_connection<D: HashFunction>(mut stream: UnixStream, share: &SigningShare<D>) -> Result<()> {
+        let message = read_frame(&mut stream, MAX_MESSAGE_LEN)?;
+        let partial = share.partial_sign(&message)?;
+        write_frame(&mut stream, &partial.to_bytes()?)
+    }
+
+    /// Answers requests on a listening socket until accepting a connection fails
+    ///
+    /// A bad request only ends its own connection.
+    pub fn serve<D: HashFunction>(listener: &UnixListener, share: &SigningShare<D>) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().map_err(|_| RabinWilliamsError::TransportError)?;
+            if let Err(error) = serve_connection(stream, share) {
+                tracing::warn!("Rejected partial signing request: {}", error);
+            }
+        }
+    }
+
+    /// A party reached over a Unix domain socket
+    #[derive(Clone, Debug)]
+    pub struct SocketSigner {
+        path: PathBuf,
+    }
+
+    impl SocketSigner {
+        /// Creates a signer that connects to the socket at `path` for each request
+        pub fn new(path: impl Into<PathBuf>) -> Self {
+            Self { path: path.into() }
+        }
+    }
+
+    impl PartialSigner for SocketSigner {
+        fn partial_sign(&self, message: &[u8]) -> Result<PartialSignature> {
+            if message.len() > MAX_MESSAGE_LEN {
+                return Err(RabinWilliamsError::MessageTooLarge);
+            }
+            let mut stream = UnixStream::connect(&self.path).map_err(|_| RabinWilliamsError::TransportError)?;
+            write_frame(&mut stream, message)?;
+            PartialSignature::from_bytes(&read_frame(&mut stream, MAX_RESPONSE_LEN)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    /// A party that tampers with its partial signatures
+    struct Faulty<'a>(&'a SigningShare<Sha256>);
+
+    impl PartialSigner for Faulty<'_> {
+        fn partial_sign(&self, message: &[u8]) -> Result<PartialSignature> {
+            let mut partial = self.0.partial_sign(message)?;
+            partial.value = partial.value * 2u32 % &self.0.n;
+            Ok(partial)
+        }
+    }
+
+    #[test]
+    fn test_two_party_signing() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let ([first, second], combiner) = key_pair.private.split_signing()?;
+
+        // The result is exactly the signature the whole key would produce
+        for i in 0..8u32 {
+            let message = i.to_be_bytes();
+            let signature = combiner.sign(&message, [&first, &second])?;
+            assert_eq!(signature, key_pair.private.sign(&message)?);
+            assert!(key_pair.public.verify(&message, &signature)?);
+        }
+
+        // Shares are fresh each time the key is split
+        let ([other_first, _], _) = key_pair.private.split_signing()?;
+        assert_ne!(other_first.d, first.d);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_faulty_partial_signatures() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let ([first, second], combiner) = key_pair.private.split_signing()?;
+
+        let result = combiner.sign(b"message", [&first, &Faulty(&second)]);
+        assert!(matches!(result, Err(RabinWilliamsError::InvalidPartialSignature(2))));
+
+        // Parties in the wrong order, or a share of another key, are caught too
+        let result = combiner.sign(b"message", [&second, &first]);
+        assert!(matches!(result, Err(RabinWilliamsError::InvalidPartialSignature(1))));
+        let ([_, foreign], _) = KeyPair::<Sha256>::generate(1024)?.private.split_signing()?;
+        assert!(combiner.sign(b"message", [&first, &foreign]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encodings() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let ([first, _], combiner) = key_pair.private.split_signing()?;
+
+        let decoded = SigningShare::<Sha256>::from_bytes(&first.to_bytes()?)?;
+        assert_eq!(decoded.verification_key(), combiner.verification_keys[0]);
+
+        let partial = first.partial_sign(b"message")?;
+        assert_eq!(PartialSignature::from_bytes(&partial.to_bytes()?)?, partial);
+        assert!(combiner.verify_partial(b"message", &partial)?);
+        assert!(!combiner.verify_partial(b"other message", &partial)?);
+        assert!(PartialSignature::from_bytes(&partial.to_bytes()?[..40]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_refuses_chosen_values() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let ([first, second], _) = key_pair.private.split_signing()?;
+        let n = key_pair.public.n().clone();
+        let z = key_pair.public.coprime();
+        let z_squared = &z * &z % &n;
+
+        // A combiner that sends z² gets partial signatures on its hash, whose
+        // product is not a square root of ±z²
+        let message = z_squared.to_bytes_be();
+        let x = first.partial_sign(&message)?.value * second.partial_sign(&message)?.value % &n;
+        assert_ne!(&x * &x % &n, z_squared);
+        assert_ne!(&x * &x % &n, &n - &z_squared);
+
+        // Values with Jacobi symbol -1 are refused outright
+        let two = BigUint::from(2u32);
+        assert_eq!(jacobi(&two, &n), -1);
+        assert!(matches!(first.partial_sign_input(&two), Err(RabinWilliamsError::InvalidSignature)));
+        let (_, a) = williams_input(&key_pair.public, b"message")?;
+        assert!(first.partial_sign_input(&a).is_ok());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_transport() -> Result<()> {
+        use std::os::unix::net::UnixListener;
+
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let (shares, combiner) = key_pair.private.split_signing()?;
+
+        let nonce = hex::encode(rand::random::<[u8; 8]>());
+        let mut paths = Vec::new();
+        let mut servers = Vec::new();
+        for share in shares {
+            let path = std::env::temp_dir().join(format!("rw-party-{}-{}.sock", share.index(), nonce));
+            let listener = UnixListener::bind(&path).map_err(|_| RabinWilliamsError::TransportError)?;
+            servers.push(std::thread::spawn(move || -> Result<()> {
+                let (stream, _) = listener.accept().map_err(|_| RabinWilliamsError::TransportError)?;
+                serve_connection(stream, &share)
+            }));
+            paths.push(path);
+        }
+        let signers: Vec<SocketSigner> = paths.iter().map(SocketSigner::new).collect();
+
+        let signature = combiner.sign(b"over the wire", [&signers[0], &signers[1]])?;
+        assert!(key_pair.public.verify(b"over the wire", &signature)?);
+        for server in servers {
+            server.join().map_err(|_| RabinWilliamsError::ComputationError)??;
+        }
+        for path in paths {
+            let _ = std::fs::remove_file(path);
+        }
+
+        Ok(())
+    }
+}
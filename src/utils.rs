@@ -1,6 +1,7 @@
 use num_bigint::{BigUint, BigInt, ToBigInt};
 use num_traits::{One, Zero};
 use crate::errors::{Result, RabinWilliamsError};
+use std::io::{Read, Write};
 
 /// Computes the modular square root using the Tonelli-Shanks algorithm
 pub fn mod_sqrt(a: &BigUint, p: &BigUint) -> Result<BigUint> {
@@ -109,7 +110,7 @@ pub fn mod_inverse(a: &BigUint, m: &BigUint) -> Option<BigUint> {
         return None;
     }
     if t < BigInt::zero() {
-        t = t + m.to_bigint().unwrap();
+        t += m.to_bigint().unwrap();
     }
     Some(t.to_biguint().unwrap())
 }
@@ -122,6 +123,33 @@ pub fn is_quadratic_residue(a: &BigUint, p: &BigUint) -> bool {
     a.modpow(&exp, p) == BigUint::one()
 }
 
+/// Computes the Jacobi symbol (a/n) for odd n
+///
+/// Returns 0 when a and n share a factor, so it doubles as a coprimality check.
+pub fn jacobi(a: &BigUint, n: &BigUint) -> i32 {
+    assert!(n.bit(0), "Jacobi symbol requires an odd modulus");
+
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+    while !a.is_zero() {
+        while !a.bit(0) {
+            a >>= 1;
+            // (2/n) = -1 exactly when n ≡ 3 or 5 (mod 8)
+            if n.bit(1) != n.bit(2) {
+                result = -result;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        // Quadratic reciprocity flips the sign when both are ≡ 3 (mod 4)
+        if a.bit(0) && a.bit(1) && n.bit(0) && n.bit(1) {
+            result = -result;
+        }
+        a %= &n;
+    }
+    if n.is_one() { result } else { 0 }
+}
+
 pub fn make_quadratic_residue(a: &BigUint, p: &BigUint, q: &BigUint) -> (BigUint, (i32, u32)) {
     let n = p * q;
     let candidates = [
@@ -131,13 +159,45 @@ pub fn make_quadratic_residue(a: &BigUint, p: &BigUint, q: &BigUint) -> (BigUint
         (((n.clone() - a.clone()) * 2u32) % &n, -1, 2),
     ];
     for (cand, e, f) in candidates.iter() {
-        if is_quadratic_residue(&cand, p) && is_quadratic_residue(&cand, q) {
+        if is_quadratic_residue(cand, p) && is_quadratic_residue(cand, q) {
             return (cand.clone(), (*e, *f));
         }
     }
     panic!("No quadratic residue found for given a, p, q");
 }
 
+/// Encodes `value` big-endian in exactly `len` bytes
+pub(crate) fn to_fixed_bytes(value: &BigUint, len: usize) -> Vec<u8> {
+    let bytes = value.to_bytes_be();
+    let mut output = vec![0u8; len - bytes.len()];
+    output.extend_from_slice(&bytes);
+    output
+}
+
+/// Writes a message to a stream, prefixed with its 4-byte big-endian length
+pub(crate) fn write_frame<W: Write>(stream: &mut W, bytes: &[u8]) -> Result<()> {
+    let len = u32::try_from(bytes.len()).map_err(|_| RabinWilliamsError::MessageTooLarge)?;
+    stream
+        .write_all(&[&len.to_be_bytes()[..], bytes].concat())
+        .map_err(|_| RabinWilliamsError::TransportError)
+}
+
+/// Reads a message written by `write_frame`
+///
+/// Fails with `TransportError` without allocating if the peer announces more
+/// than `max_len` bytes.
+pub(crate) fn read_frame<R: Read>(stream: &mut R, max_len: usize) -> Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).map_err(|_| RabinWilliamsError::TransportError)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > max_len {
+        return Err(RabinWilliamsError::TransportError);
+    }
+    let mut bytes = vec![0u8; len];
+    stream.read_exact(&mut bytes).map_err(|_| RabinWilliamsError::TransportError)?;
+    Ok(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,6 +308,25 @@ mod tests {
         assert!(!is_quadratic_residue(&BigUint::from(2u32), &BigUint::from(1u32)));
     }
 
+    #[test]
+    fn test_jacobi() {
+        // Agrees with the Legendre symbol for primes
+        let p = BigUint::from(7u32);
+        for a in 1u32..7 {
+            let expected = if is_quadratic_residue(&BigUint::from(a), &p) { 1 } else { -1 };
+            assert_eq!(jacobi(&BigUint::from(a), &p), expected);
+        }
+
+        // (2/15) = (2/3)(2/5) = 1 and (7/15) = (7/3)(7/5) = -1
+        let n = BigUint::from(15u32);
+        assert_eq!(jacobi(&BigUint::from(2u32), &n), 1);
+        assert_eq!(jacobi(&BigUint::from(7u32), &n), -1);
+
+        // Shared factors give 0
+        assert_eq!(jacobi(&BigUint::from(6u32), &n), 0);
+        assert_eq!(jacobi(&BigUint::from(0u32), &n), 0);
+    }
+
     #[test]
     fn test_make_quadratic_residue() {
         let p = BigUint::from(7u32);
@@ -278,4 +357,34 @@ mod tests {
         // Verify the result is always less than n
         assert!(result < n);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_to_fixed_bytes() {
+        assert_eq!(to_fixed_bytes(&BigUint::from(0x0102u32), 4), vec![0, 0, 1, 2]);
+        assert_eq!(to_fixed_bytes(&BigUint::zero(), 2), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_frames() -> Result<()> {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, b"first")?;
+        write_frame(&mut stream, b"")?;
+        assert_eq!(&stream[..4], &[0, 0, 0, 5]);
+
+        let mut reader = stream.as_slice();
+        assert_eq!(read_frame(&mut reader, 5)?, b"first");
+        assert_eq!(read_frame(&mut reader, 5)?, b"");
+        assert!(matches!(read_frame(&mut reader, 5), Err(RabinWilliamsError::TransportError)));
+
+        // A frame cut short is an error, not a short message
+        let mut truncated = &stream[..7];
+        assert!(read_frame(&mut truncated, 5).is_err());
+
+        // So is a frame longer than the caller allows, even a huge one
+        let mut reader = stream.as_slice();
+        assert!(matches!(read_frame(&mut reader, 4), Err(RabinWilliamsError::TransportError)));
+        let mut huge: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(matches!(read_frame(&mut huge, 1 << 16), Err(RabinWilliamsError::TransportError)));
+        Ok(())
+    }
+}
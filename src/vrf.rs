@@ -0,0 +1,248 @@
+//! A verifiable random function from unique Rabin-Williams signatures
+//!
+//! The input is hashed over the full domain of the modulus with MGF1, and the
+//! proof is its RW signature with a canonical root: of the four square roots,
+//! the one below `n / 2` with Jacobi symbol 1. Anyone can check that choice, and
+//! for a well-formed modulus only one tweak `(e, f)` makes the hash a square, so
+//! every input has exactly one valid proof. The output is a hash of that proof.
+//!
+//! Uniqueness relies on `n` being a product of primes that are 3 and 7 (mod 8).
+//! Verifiers who do not trust the key holder should check a `ModulusProof` for
+//! the key first.
+
+use crate::errors::{RabinWilliamsError, Result};
+use crate::hash::{digest, mgf1, HashFunction};
+use crate::keys::{PrivateKey, PublicKey};
+use crate::utils::jacobi;
+use num_bigint::BigUint;
+
+/// Domain separator for hashing inputs into the modulus
+const VRF_INPUT_CONTEXT: &[u8] = b"rabin-williams vrf input";
+
+/// Domain separator for hashing proofs into outputs
+const VRF_OUTPUT_CONTEXT: &[u8] = b"rabin-williams vrf output";
+
+/// Hashes an input to a value one bit shorter than the modulus
+fn hash_input<D: HashFunction>(n: &BigUint, input: &[u8]) -> BigUint {
+    let bits = n.bits() as usize - 1;
+    let mut bytes = mgf1::<D>(&[VRF_INPUT_CONTEXT, input].concat(), bits.div_ceil(8));
+    if !bits.is_multiple_of(8) {
+        bytes[0] &= 0xFF >> (8 - bits % 8);
+    }
+    BigUint::from_bytes_be(&bytes)
+}
+
+/// Encodes a proof as the flag byte followed by the root, as long as the modulus
+fn encode_proof(n: &BigUint, e: i32, f: u32, x: &BigUint) -> Vec<u8> {
+    let x = x.to_bytes_be();
+    // Same flag layout as signatures: bit 0 for e = -1, bit 1 for f = 2
+    let mut proof = vec![(e == -1) as u8 | (((f == 2) as u8) << 1)];
+    proof.resize(1 + n.bits().div_ceil(8) as usize - x.len(), 0);
+    proof.extend_from_slice(&x);
+    proof
+}
+
+fn output<D: HashFunction>(proof: &[u8]) -> Vec<u8> {
+    digest::<D>(&[VRF_OUTPUT_CONTEXT, proof])
+}
+
+impl<D: HashFunction> PrivateKey<D> {
+    /// Evaluates the VRF on an input
+    ///
+    /// Returns the output and a proof that lets anyone holding the public key
+    /// recompute and check it. Both are deterministic.
+    pub fn vrf_prove(&self, input: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let n = self.n();
+        let m = hash_input::<D>(&n, input);
+        let signature = self.raw_sign(&m.to_bytes_be())?;
+
+        // The principal root has Jacobi symbol 1, and so does its negation
+        let public_key = self.public_key();
+        let (e, f, x) = public_key.extract_signature(&signature)?;
+        let x = x.clone().min(&n - &x);
+
+        let proof = encode_proof(&n, e, f, &x);
+        if public_key.vrf_verify(input, &proof)? != output::<D>(&proof) {
+            return Err(RabinWilliamsError::ComputationError);
+        }
+        Ok((output::<D>(&proof), proof))
+    }
+}
+
+impl<D: HashFunction> PublicKey<D> {
+    /// Checks a VRF proof and returns the output it certifies
+    ///
+    /// Fails with `InvalidSignature` unless the proof is the canonical signature
+    /// of the input.
+    pub fn vrf_verify(&self, input: &[u8], proof: &[u8]) -> Result<Vec<u8>> {
+        let n = self.n();
+        if proof.len() != 1 + n.bits().div_ceil(8) as usize {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+
+        let (_, _, x) = self.extract_signature(proof)?;
+        let canonical = &x << 1 < *n && jacobi(&x, n) == 1;
+        if !canonical || !self.verify_hash(&hash_input::<D>(n, input), proof)? {
+            return Err(RabinWilliamsError::InvalidSignature);
+        }
+        Ok(output::<D>(proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::KeyPair;
+    use sha2::Sha256;
+
+    #[test]
+    fn test_vrf_prove_verify() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let (output, proof) = key_pair.private.vrf_prove(b"round 42")?;
+        assert_eq!(output.len(), 32);
+        assert_eq!(proof.len(), 129);
+        assert_eq!(key_pair.public.vrf_verify(b"round 42", &proof)?, output);
+
+        // Deterministic, and different inputs give different outputs
+        assert_eq!(key_pair.private.vrf_prove(b"round 42")?, (output.clone(), proof.clone()));
+        let (other_output, _) = key_pair.private.vrf_prove(b"round 43")?;
+        assert_ne!(other_output, output);
+
+        assert!(key_pair.public.vrf_verify(b"round 43", &proof).is_err());
+
+        // Another key gives another output for the same input
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        assert_ne!(other.private.vrf_prove(b"round 42")?.0, output);
+        assert!(other.public.vrf_verify(b"round 42", &proof).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_the_canonical_root_verifies() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let n = key_pair.public.n();
+        let (_, proof) = key_pair.private.vrf_prove(b"input")?;
+        let (e, f, _) = key_pair.public.extract_signature(&proof)?;
+
+        // Every square root of e·f·H(input) is a valid RW signature, but only one is accepted
+        let tweaked = hash_input::<Sha256>(n, b"input") * f % n;
+        let tweaked = if e == -1 { n - tweaked } else { tweaked };
+        let roots = key_pair.private.square_roots(&tweaked)?;
+        let accepted = roots
+            .iter()
+            .map(|root| encode_proof(n, e, f, root))
+            .filter(|candidate| key_pair.public.vrf_verify(b"input", candidate).is_ok())
+            .collect::<Vec<_>>();
+        assert_eq!(accepted, vec![proof.clone()]);
+
+        // Proofs must be encoded at full length
+        assert!(key_pair.public.vrf_verify(b"input", &proof[..proof.len() - 1]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_proof_under_wrong_key() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let other: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let (_, proof) = key_pair.private.vrf_prove(b"input")?;
+        let (_, other_proof) = other.private.vrf_prove(b"input")?;
+
+        assert!(other.public.vrf_verify(b"input", &proof).is_err());
+        assert!(key_pair.public.vrf_verify(b"input", &other_proof).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_proof_for_wrong_input() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let (_, proof) = key_pair.private.vrf_prove(b"input")?;
+
+        for input in [&b""[..], b"Input", b"input\0", b"inpu"] {
+            assert!(key_pair.public.vrf_verify(input, &proof).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_tampered_proof() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let n = key_pair.public.n();
+        let (_, proof) = key_pair.private.vrf_prove(b"input")?;
+
+        // Flipped tweak bits and unused flag bits
+        for flag in [0x01, 0x02, 0x04, 0x80] {
+            let mut tampered = proof.clone();
+            tampered[0] ^= flag;
+            assert!(key_pair.public.vrf_verify(b"input", &tampered).is_err());
+        }
+
+        // Flipped bits in the root
+        for index in [1, proof.len() / 2, proof.len() - 1] {
+            let mut tampered = proof.clone();
+            tampered[index] ^= 0x01;
+            assert!(key_pair.public.vrf_verify(b"input", &tampered).is_err());
+        }
+
+        // The negated root is also a square root, but not the canonical one
+        let (e, f, x) = key_pair.public.extract_signature(&proof)?;
+        assert!(key_pair.public.vrf_verify(b"input", &encode_proof(n, e, f, &(n - &x))).is_err());
+        assert!(key_pair.public.vrf_verify(b"input", &encode_proof(n, e, f, &BigUint::from(0u32))).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_encoding() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+        let n = key_pair.public.n();
+        let proof_len = 1 + n.bits().div_ceil(8) as usize;
+
+        for input in [&b""[..], b"a", b"round 42"] {
+            let (output, proof) = key_pair.private.vrf_prove(input)?;
+            assert_eq!(proof.len(), proof_len);
+            assert_eq!(proof[0] & 0xFC, 0);
+            assert_eq!(output, digest::<Sha256>(&[VRF_OUTPUT_CONTEXT, &proof]));
+
+            // The root is canonical and re-encodes to the same bytes
+            let (e, f, x) = key_pair.public.extract_signature(&proof)?;
+            assert!(&x << 1 < *n);
+            assert_eq!(jacobi(&x, n), 1);
+            assert_eq!(encode_proof(n, e, f, &x), proof);
+        }
+
+        // Neither a leading zero byte nor a trailing byte is accepted
+        let (_, proof) = key_pair.private.vrf_prove(b"input")?;
+        let mut padded = vec![proof[0], 0];
+        padded.extend_from_slice(&proof[1..]);
+        assert!(key_pair.public.vrf_verify(b"input", &padded).is_err());
+        let mut extended = proof.clone();
+        extended.push(0);
+        assert!(key_pair.public.vrf_verify(b"input", &extended).is_err());
+        assert!(key_pair.public.vrf_verify(b"input", &[]).is_err());
+        assert!(key_pair.public.vrf_verify(b"input", &proof[..1]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_is_unique_per_input() -> Result<()> {
+        let key_pair: KeyPair<Sha256> = KeyPair::generate(1024)?;
+
+        let mut outputs = std::collections::HashSet::new();
+        for round in 0..32u32 {
+            let input = round.to_be_bytes();
+            let (output, proof) = key_pair.private.vrf_prove(&input)?;
+            // Proving again gives the same output and proof
+            assert_eq!(key_pair.private.vrf_prove(&input)?, (output.clone(), proof.clone()));
+            assert_eq!(key_pair.public.vrf_verify(&input, &proof)?, output);
+            assert!(outputs.insert(output));
+        }
+
+        Ok(())
+    }
+}